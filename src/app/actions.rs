@@ -1,8 +1,8 @@
-use std::collections::HashMap;
 use std::fmt::{self, Display};
 use std::slice::Iter;
 
-use crate::inputs::key::Key;
+use super::keymap::{self, KeyMatch, KeySequence, Keymap, Mode, Resolution};
+use crate::inputs::key::{Key, KeyCode};
 
 /// We define all available action
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -19,12 +19,33 @@ pub enum Action {
     ScrollUp,
     ScrollLeft,
     ScrollRight,
+    EnterCommand,
+    MoveTabLeft,
+    MoveTabRight,
+    SplitWindow,
+    SplitWindowVertically,
+    CloseWindow,
+    GrowWindow,
+    ShrinkWindow,
+    FocusWindowLeft,
+    FocusWindowDown,
+    FocusWindowUp,
+    FocusWindowRight,
+    ShowHelp,
+    ToggleHelpPanel,
+    ToggleLogPanel,
+    ShrinkHelpPanel,
+    GrowHelpPanel,
+    ShrinkLogPanel,
+    GrowLogPanel,
+    CommandPalette,
+    JumpToMatchingBracket,
 }
 
 impl Action {
     /// All available actions
     pub fn iterator() -> Iter<'static, Action> {
-        static ACTIONS: [Action; 12] = [
+        static ACTIONS: [Action; 33] = [
             Action::Quit,
             Action::BeginWriteMode,
             Action::EndWriteMode,
@@ -37,6 +58,27 @@ impl Action {
             Action::ScrollUp,
             Action::ScrollLeft,
             Action::ScrollRight,
+            Action::EnterCommand,
+            Action::MoveTabLeft,
+            Action::MoveTabRight,
+            Action::SplitWindow,
+            Action::SplitWindowVertically,
+            Action::CloseWindow,
+            Action::GrowWindow,
+            Action::ShrinkWindow,
+            Action::FocusWindowLeft,
+            Action::FocusWindowDown,
+            Action::FocusWindowUp,
+            Action::FocusWindowRight,
+            Action::ShowHelp,
+            Action::ToggleHelpPanel,
+            Action::ToggleLogPanel,
+            Action::ShrinkHelpPanel,
+            Action::GrowHelpPanel,
+            Action::ShrinkLogPanel,
+            Action::GrowLogPanel,
+            Action::CommandPalette,
+            Action::JumpToMatchingBracket,
         ];
         ACTIONS.iter()
     }
@@ -45,21 +87,62 @@ impl Action {
         Action::iterator().cloned().collect()
     }
 
-    /// List of key associated to action
-    pub fn keys(&self) -> &[Key] {
+    /// Name of the action in config files, e.g. `save_file`
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        for c in format!("{:?}", self).chars() {
+            if c.is_uppercase() && !name.is_empty() {
+                name.push('_');
+            }
+            name.extend(c.to_lowercase());
+        }
+        name
+    }
+
+    pub fn from_name(name: &str) -> Result<Action, String> {
+        Action::iterator()
+            .find(|action| action.name() == name)
+            .copied()
+            .ok_or_else(|| format!("Unknown action {}", name))
+    }
+
+    /// Keys bound to the action, unless others are bound to it in the keymap
+    pub fn default_keys(&self) -> Vec<Key> {
         match self {
-            Action::Quit => &[Key::Char('q')],
-            Action::BeginWriteMode => &[Key::Char('w')],
-            Action::EndWriteMode => &[Key::Ctrl('w')],
-            Action::OpenFile => &[Key::Ctrl('o')],
-            Action::SaveFile => &[Key::Ctrl('s')],
-            Action::NextFile => &[Key::Char('n')],
-            Action::PreviousFile => &[Key::Char('p')],
-            Action::CloseFile => &[Key::Ctrl('c')],
-            Action::ScrollDown => &[Key::Down],
-            Action::ScrollUp => &[Key::Up],
-            Action::ScrollLeft => &[Key::Left],
-            Action::ScrollRight => &[Key::Right],
+            Action::Quit => vec![Key::char('q')],
+            Action::BeginWriteMode => vec![Key::char('w')],
+            Action::EndWriteMode => vec![Key::ctrl('w')],
+            Action::OpenFile => vec![Key::ctrl('o')],
+            Action::SaveFile => vec![Key::ctrl('s')],
+            Action::NextFile => vec![Key::char('n')],
+            Action::PreviousFile => vec![Key::char('p')],
+            Action::CloseFile => vec![Key::ctrl('c')],
+            Action::ScrollDown => vec![Key::plain(KeyCode::Down)],
+            Action::ScrollUp => vec![Key::plain(KeyCode::Up)],
+            Action::ScrollLeft => vec![Key::plain(KeyCode::Left)],
+            Action::ScrollRight => vec![Key::plain(KeyCode::Right)],
+            Action::EnterCommand => vec![Key::char(':')],
+            Action::MoveTabLeft => vec![Key::char('<')],
+            Action::MoveTabRight => vec![Key::char('>')],
+            Action::SplitWindow => vec![Key::alt('s')],
+            Action::SplitWindowVertically => vec![Key::alt('v')],
+            Action::CloseWindow => vec![Key::alt('q')],
+            Action::GrowWindow => vec![Key::alt('=')],
+            Action::ShrinkWindow => vec![Key::alt('-')],
+            Action::FocusWindowLeft => vec![Key::alt('h')],
+            Action::FocusWindowDown => vec![Key::alt('j')],
+            Action::FocusWindowUp => vec![Key::alt('k')],
+            Action::FocusWindowRight => vec![Key::alt('l')],
+            Action::ShowHelp => vec![Key::plain(KeyCode::F(1)), Key::char('?')],
+            Action::ToggleHelpPanel => vec![Key::plain(KeyCode::F(2))],
+            Action::ToggleLogPanel => vec![Key::plain(KeyCode::F(3))],
+            Action::ShrinkHelpPanel => vec![Key::alt(',')],
+            Action::GrowHelpPanel => vec![Key::alt('.')],
+            Action::ShrinkLogPanel => vec![Key::alt('d')],
+            Action::GrowLogPanel => vec![Key::alt('u')],
+            // Most terminals report Ctrl-Shift-p as Ctrl-p
+            Action::CommandPalette => vec![Key::ctrl('P'), Key::ctrl('p')],
+            Action::JumpToMatchingBracket => vec![Key::char('%')],
         }
     }
 }
@@ -80,67 +163,97 @@ impl Display for Action {
             Action::ScrollUp => "Scroll Up",
             Action::ScrollLeft => "Scroll Left",
             Action::ScrollRight => "Scroll Right",
+            Action::EnterCommand => "Command Line",
+            Action::MoveTabLeft => "Move Tab Left",
+            Action::MoveTabRight => "Move Tab Right",
+            Action::SplitWindow => "Split Window",
+            Action::SplitWindowVertically => "Split Window Vertically",
+            Action::CloseWindow => "Close Window",
+            Action::GrowWindow => "Grow Window",
+            Action::ShrinkWindow => "Shrink Window",
+            Action::FocusWindowLeft => "Window Left",
+            Action::FocusWindowDown => "Window Down",
+            Action::FocusWindowUp => "Window Up",
+            Action::FocusWindowRight => "Window Right",
+            Action::ShowHelp => "Help",
+            Action::ToggleHelpPanel => "Toggle Help Panel",
+            Action::ToggleLogPanel => "Toggle Log Panel",
+            Action::ShrinkHelpPanel => "Narrow Help Panel",
+            Action::GrowHelpPanel => "Widen Help Panel",
+            Action::ShrinkLogPanel => "Shorten Log Panel",
+            Action::GrowLogPanel => "Heighten Log Panel",
+            Action::CommandPalette => "Command Palette",
+            Action::JumpToMatchingBracket => "Matching Bracket",
         };
         write!(f, "{}", str)
     }
 }
 
-/// The application should have some contextual actions.
+/// The application should have some contextual actions, run by the keys
+/// bound to them in the keymap.
 #[derive(Default, Debug, Clone)]
-pub struct Actions(Vec<Action>);
+pub struct Actions {
+    actions: Vec<Action>,
+    keymap: Keymap,
+}
 
 impl Actions {
-    /// Given a key, find the corresponding action
-    pub fn find(&self, key: Key) -> Option<&Action> {
-        Action::iterator()
-            .filter(|action| self.0.contains(action))
-            .find(|action| action.keys().contains(&key))
+    pub fn new(actions: Vec<Action>, keymap: Keymap) -> Self {
+        Self { actions, keymap }
+    }
+
+    /// Given keys typed one after the other, find what they lead to in `mode`
+    pub fn find(&self, mode: Mode, keys: &[Key]) -> KeyMatch {
+        let found = self.keymap.find(mode, keys);
+        KeyMatch {
+            action: found.action.filter(|action| self.actions.contains(action)),
+            ..found
+        }
+    }
+
+    /// What to do with the keys typed so far in `mode`
+    pub fn resolve(&self, mode: Mode, pending: &[Key], timed_out: bool) -> Resolution {
+        keymap::resolve(pending, timed_out, |keys| self.find(mode, keys))
+    }
+
+    /// Key sequences bound to `action` in `mode`
+    pub fn keys(&self, mode: Mode, action: Action) -> &[KeySequence] {
+        self.keymap.keys(mode, action)
+    }
+
+    /// Key sequences running `action` when typed in `mode`. In write mode,
+    /// single keys of normal mode typing no text run it too, unless a write
+    /// mode binding takes them.
+    pub fn keys_typed_in(&self, mode: Mode, action: Action) -> Vec<&KeySequence> {
+        let mut keys: Vec<&KeySequence> = self.keys(mode, action).iter().collect();
+        if mode == Mode::Write {
+            keys.extend(self.keys(Mode::Normal, action).iter().filter(|sequence| match sequence.as_slice() {
+                [key] => !key.types_text() && self.keymap.find(Mode::Write, sequence) == KeyMatch::default(),
+                _ => false,
+            }));
+        }
+        keys
     }
 
     /// Get contextual actions.
     /// (just for building a help view)
     pub fn actions(&self) -> &[Action] {
-        self.0.as_slice()
+        self.actions.as_slice()
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
     }
 }
 
 impl From<Vec<Action>> for Actions {
-    /// Build contextual action
-    ///
-    /// # Panics
-    ///
-    /// If two actions have same key
+    /// Build contextual actions, bound to their default keys
     fn from(actions: Vec<Action>) -> Self {
-        // Check key unicity
-        let mut map: HashMap<Key, Vec<Action>> = HashMap::new();
-        for action in actions.iter() {
-            for key in action.keys().iter() {
-                match map.get_mut(key) {
-                    Some(vec) => vec.push(*action),
-                    None => {
-                        map.insert(*key, vec![*action]);
-                    }
-                }
-            }
-        }
-        let errors = map
-            .iter()
-            .filter(|(_, actions)| actions.len() > 1) // at least two actions share same shortcut
-            .map(|(key, actions)| {
-                let actions = actions
-                    .iter()
-                    .map(Action::to_string)
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("Conflict key {} with actions {}", key, actions)
-            })
-            .collect::<Vec<_>>();
-        if !errors.is_empty() {
-            panic!("{}", errors.join("; "))
-        }
-
-        // Ok, we can create contextual actions
-        Self(actions)
+        Self::new(actions, Keymap::default())
     }
 }
 
@@ -151,15 +264,17 @@ mod tests {
     #[test]
     fn should_find_action_by_key() {
         let actions: Actions = vec![Action::Quit, Action::BeginWriteMode, Action::EndWriteMode].into();
-        let result = actions.find(Key::Ctrl('c'));
-        assert_eq!(result, Some(&Action::Quit));
+        let result = actions.find(Mode::Normal, &[Key::char('q')]).action;
+        assert_eq!(result, Some(Action::Quit));
     }
 
     #[test]
     fn should_find_action_by_key_not_found() {
         let actions: Actions = vec![Action::Quit, Action::BeginWriteMode, Action::EndWriteMode].into();
-        let result = actions.find(Key::Alt('w'));
+        let result = actions.find(Mode::Normal, &[Key::alt('w')]).action;
         assert_eq!(result, None);
+        // Bound, but not among the contextual actions
+        assert_eq!(actions.find(Mode::Normal, &[Key::ctrl('s')]).action, None);
     }
 
     #[test]
@@ -182,22 +297,20 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn should_panic_when_create_actions_conflict_key() {
-        let _actions: Actions = vec![
-            Action::Quit,
-            Action::BeginWriteMode,
-            Action::EndWriteMode,
-            Action::OpenFile,
-            Action::SaveFile,
-            Action::NextFile,
-            Action::PreviousFile,
-            Action::CloseFile,
-            Action::ScrollDown,
-            Action::ScrollUp,
-            Action::ScrollLeft,
-            Action::ScrollRight,
-        ]
-        .into();
+    fn should_run_the_last_bound_action_when_keys_conflict() {
+        let mut keymap = Keymap::default();
+        keymap.bind(Mode::Normal, Action::SaveFile, vec![vec![Key::char('q')]]);
+        let actions = Actions::new(Action::values(), keymap);
+        assert_eq!(actions.find(Mode::Normal, &[Key::char('q')]).action, Some(Action::SaveFile));
+        assert_eq!(actions.keymap().conflicts().len(), 1);
+    }
+
+    #[test]
+    fn should_name_actions_for_config_files() {
+        assert_eq!(Action::SplitWindowVertically.name(), "split_window_vertically");
+        for action in Action::iterator() {
+            assert_eq!(Action::from_name(&action.name()), Ok(*action));
+        }
+        assert!(Action::from_name("fly").is_err());
     }
 }
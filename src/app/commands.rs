@@ -0,0 +1,192 @@
+use std::fmt::{self, Display};
+
+use super::options::{self, Assignment, EditorOption};
+use super::settings::{LineNumbers, ListChars, WrapMode};
+
+/// Commands typed in the command line, opened with `:`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Go to a line, or to a byte offset in the hex view
+    Goto(String),
+    /// Find text, or a byte sequence in the hex view. Repeats the last search
+    /// when empty.
+    Find(String),
+    /// Save the open files and their views to a session file, or to the
+    /// session of the working directory without a path
+    MkSession(Option<String>),
+    /// Restore a session file, or the session of the working directory
+    /// without a path
+    LoadSession(Option<String>),
+    /// Remove the session of the working directory, so that it is no longer
+    /// restored on startup
+    RmSession,
+    /// Switch how lines are numbered in the gutter
+    Numbers(LineNumbers),
+    /// Switch how long lines are shown, or toggle it without an argument
+    Wrap(Option<WrapMode>),
+    /// Set the width tabs are expanded to
+    TabWidth(usize),
+    /// Switch whether whitespace is shown, or toggle it without an argument
+    List(Option<bool>),
+    /// Set the glyphs whitespace is shown with
+    ListChars(ListChars),
+    /// Switch the indentation guides, or toggle them without an argument
+    Guides(Option<bool>),
+    /// Switch the scrollbar, or toggle it without an argument
+    Scrollbar(Option<bool>),
+    /// Switch the minimap, or toggle it without an argument
+    Minimap(Option<bool>),
+    /// Select the tab at a 1-based position
+    Tab(usize),
+    /// Move the selected tab to a 1-based position
+    TabMove(usize),
+    /// Switch to a built-in or user theme, or list the themes without a name
+    Theme(Option<String>),
+    /// Show the history of messages
+    Messages,
+    /// Change an option, or list them all without an argument
+    Set(Option<(EditorOption, Assignment)>),
+}
+
+impl Command {
+    /// All available commands, with the usage shown when they are mistyped
+    pub fn usages() -> &'static [&'static str] {
+        &[
+            "goto <line|offset>",
+            "find [text|bytes]",
+            "mksession [path]",
+            "loadsession [path]",
+            "rmsession",
+            "numbers <off|absolute|relative|hybrid>",
+            "wrap [soft|off]",
+            "tabwidth <n>",
+            "list [on|off]",
+            "listchars <tab:→ ,trail:·,nbsp:⍽,eol:¬>",
+            "guides [on|off]",
+            "scrollbar [on|off]",
+            "minimap [on|off]",
+            "tab <n>",
+            "tabmove <n>",
+            "theme [name]",
+            "messages",
+            "set [option[=value]|nooption|option!|option?]",
+        ]
+    }
+
+    /// Parse a command line, without the leading `:`
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (line, ""),
+        };
+        match name {
+            "goto" | "g" if !argument.is_empty() => Ok(Command::Goto(argument.to_owned())),
+            "find" | "f" => Ok(Command::Find(argument.to_owned())),
+            "mksession" | "mks" => Ok(Command::MkSession(Some(argument.to_owned()).filter(|path| !path.is_empty()))),
+            "loadsession" => Ok(Command::LoadSession(Some(argument.to_owned()).filter(|path| !path.is_empty()))),
+            "rmsession" => Ok(Command::RmSession),
+            "numbers" | "nu" if !argument.is_empty() => Ok(Command::Numbers(argument.parse()?)),
+            "wrap" if argument.is_empty() => Ok(Command::Wrap(None)),
+            "wrap" => Ok(Command::Wrap(Some(argument.parse()?))),
+            "tabwidth" | "ts" if !argument.is_empty() => Ok(Command::TabWidth(options::parse_tab_width(argument)?)),
+            "list" => Ok(Command::List(parse_switch(argument)?)),
+            "listchars" | "lcs" if !argument.is_empty() => Ok(Command::ListChars(argument.parse()?)),
+            "guides" => Ok(Command::Guides(parse_switch(argument)?)),
+            "scrollbar" => Ok(Command::Scrollbar(parse_switch(argument)?)),
+            "minimap" => Ok(Command::Minimap(parse_switch(argument)?)),
+            "tab" if !argument.is_empty() => Ok(Command::Tab(parse_position(argument)?)),
+            "tabmove" if !argument.is_empty() => Ok(Command::TabMove(parse_position(argument)?)),
+            "theme" | "colorscheme" => Ok(Command::Theme(Some(argument.to_owned()).filter(|name| !name.is_empty()))),
+            "messages" | "mes" => Ok(Command::Messages),
+            "set" | "se" if argument.is_empty() => Ok(Command::Set(None)),
+            "set" | "se" => Ok(Command::Set(Some(options::parse_assignment(argument)?))),
+            _ => Err(format!("Unknown command :{}, expected one of :{}", line, Command::usages().join(", :"))),
+        }
+    }
+}
+
+/// A command defined in the `[commands]` table of a config file, running a
+/// command line under a name of its own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserCommand {
+    pub name: String,
+    pub command: Command,
+}
+
+impl UserCommand {
+    /// Define `name` as `line`, which is parsed right away so that mistakes
+    /// are reported with the config. Names of built-in commands are refused.
+    pub fn new(name: &str, line: &str) -> Result<Self, String> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid command name {:?}, expected a single word", name));
+        }
+        if Command::usages().iter().any(|usage| usage.split_whitespace().next() == Some(name)) {
+            return Err(format!(":{} is a built-in command", name));
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            command: Command::parse(line)?,
+        })
+    }
+}
+
+/// Parse a 1-based position
+fn parse_position(argument: &str) -> Result<usize, String> {
+    argument
+        .parse::<usize>()
+        .ok()
+        .filter(|position| *position > 0)
+        .ok_or_else(|| format!("Invalid position {}, expected a number from 1", argument))
+}
+
+/// Parse `on` or `off`, or nothing to toggle
+fn parse_switch(argument: &str) -> Result<Option<bool>, String> {
+    match argument {
+        "" => Ok(None),
+        "on" => Ok(Some(true)),
+        "off" => Ok(Some(false)),
+        _ => Err(format!("Invalid switch {}, expected on or off", argument)),
+    }
+}
+
+/// The argument `on` or `off`, or nothing for a toggle
+fn switch(value: Option<bool>) -> &'static str {
+    match value {
+        Some(true) => "on",
+        Some(false) => "off",
+        None => "",
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Goto(target) => write!(f, "goto {}", target),
+            Command::Find(pattern) => write!(f, "find {}", pattern),
+            Command::MkSession(path) => write!(f, "mksession {}", path.as_deref().unwrap_or_default()),
+            Command::LoadSession(path) => write!(f, "loadsession {}", path.as_deref().unwrap_or_default()),
+            Command::RmSession => write!(f, "rmsession"),
+            Command::Numbers(line_numbers) => write!(f, "numbers {}", line_numbers),
+            Command::Wrap(wrap) => write!(f, "wrap {}", wrap.map(|wrap| wrap.to_string()).unwrap_or_default()),
+            Command::TabWidth(width) => write!(f, "tabwidth {}", width),
+            Command::List(list) => write!(f, "list {}", switch(*list)),
+            Command::ListChars(list_chars) => write!(f, "listchars {}", list_chars),
+            Command::Guides(guides) => write!(f, "guides {}", switch(*guides)),
+            Command::Scrollbar(scrollbar) => write!(f, "scrollbar {}", switch(*scrollbar)),
+            Command::Minimap(minimap) => write!(f, "minimap {}", switch(*minimap)),
+            Command::Tab(position) => write!(f, "tab {}", position),
+            Command::TabMove(position) => write!(f, "tabmove {}", position),
+            Command::Theme(name) => write!(f, "theme {}", name.as_deref().unwrap_or_default()),
+            Command::Messages => write!(f, "messages"),
+            Command::Set(None) => write!(f, "set"),
+            Command::Set(Some((option, assignment))) => match assignment {
+                Assignment::Set(value) => write!(f, "set {}={}", option, value),
+                Assignment::On => write!(f, "set {}", option),
+                Assignment::Off => write!(f, "set no{}", option),
+                Assignment::Toggle => write!(f, "set {}!", option),
+                Assignment::Show => write!(f, "set {}?", option),
+            },
+        }
+    }
+}
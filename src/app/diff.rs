@@ -0,0 +1,402 @@
+use std::ops::{Index, IndexMut, Range};
+
+/// Kind of a line in a diff between two texts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    /// Line present in both texts
+    Context,
+    /// Line only present in the old text
+    Removed,
+    /// Line only present in the new text
+    Added,
+    /// Header starting a new hunk of changes
+    Hunk,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+impl DiffLine {
+    fn new(kind: DiffKind, text: &str) -> Self {
+        Self {
+            kind,
+            text: text.to_owned(),
+        }
+    }
+}
+
+/// Build a unified diff going from `old` to `new`, keeping `context` unchanged
+/// lines around every change. Returns an empty diff when both texts are equal.
+pub fn unified(old: &str, new: &str, context: usize) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = edit_script(&old_lines, &new_lines);
+
+    let mut out: Vec<DiffLine> = vec![];
+    let mut index = 0;
+    while index < edits.len() {
+        // Skip to the next change
+        let change_start = match edits[index..].iter().position(|edit| edit.kind != DiffKind::Context) {
+            Some(offset) => index + offset,
+            None => break,
+        };
+        let hunk_start = change_start.saturating_sub(context).max(index);
+
+        // Extend the hunk while changes are close enough to share context
+        let mut hunk_end = change_start;
+        let mut unchanged_run = 0;
+        for (offset, edit) in edits[change_start..].iter().enumerate() {
+            if edit.kind == DiffKind::Context {
+                unchanged_run += 1;
+                if unchanged_run > 2 * context {
+                    break;
+                }
+            } else {
+                unchanged_run = 0;
+                hunk_end = change_start + offset;
+            }
+        }
+        let hunk_end = (hunk_end + context + 1).min(edits.len());
+
+        let first = &edits[hunk_start];
+        out.push(DiffLine {
+            kind: DiffKind::Hunk,
+            text: format!("@@ -{} +{} @@", first.old_line + 1, first.new_line + 1),
+        });
+        out.extend(edits[hunk_start..hunk_end].iter().map(|edit| DiffLine::new(edit.kind, edit.text)));
+        index = hunk_end;
+    }
+    out
+}
+
+/// Lines of `new` that differ from `old`: those added or changed, and those
+/// before which lines were removed
+pub fn changed_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<usize> {
+    let (prefix, suffix) = common_affixes(old, new);
+    let mut lines: Vec<usize> = vec![];
+    for (kind, _, new_index) in myers(&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]) {
+        let line = prefix + new_index;
+        match kind {
+            DiffKind::Added => lines.push(line),
+            DiffKind::Removed => lines.push(line.min(new.len().saturating_sub(1))),
+            _ => {},
+        }
+    }
+    lines.dedup();
+    lines
+}
+
+struct Edit<'a> {
+    kind: DiffKind,
+    text: &'a str,
+    old_line: usize,
+    new_line: usize,
+}
+
+/// Shortest edit script between two line lists (Myers' algorithm), with the
+/// common prefix and suffix trimmed first since edits are usually local.
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    let (prefix, suffix) = common_affixes(old, new);
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut edits: Vec<Edit> = (0..prefix)
+        .map(|i| Edit {
+            kind: DiffKind::Context,
+            text: old[i],
+            old_line: i,
+            new_line: i,
+        })
+        .collect();
+
+    for (kind, old_index, new_index) in myers(old_mid, new_mid) {
+        let text = match kind {
+            DiffKind::Added => new_mid[new_index],
+            _ => old_mid[old_index],
+        };
+        edits.push(Edit {
+            kind,
+            text,
+            old_line: prefix + old_index,
+            new_line: prefix + new_index,
+        });
+    }
+
+    for i in 0..suffix {
+        edits.push(Edit {
+            kind: DiffKind::Context,
+            text: old[old.len() - suffix + i],
+            old_line: old.len() - suffix + i,
+            new_line: new.len() - suffix + i,
+        });
+    }
+    edits
+}
+
+/// Lengths of the common prefix and suffix of two lists, not overlapping
+fn common_affixes<T: PartialEq>(old: &[T], new: &[T]) -> (usize, usize) {
+    let prefix = old.iter().zip(new.iter()).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    (prefix, suffix)
+}
+
+/// Furthest x reached on every diagonal k = x - y, for k from `-max` to `max`
+struct Diagonals {
+    max: isize,
+    x: Vec<isize>,
+}
+
+impl Diagonals {
+    fn new(max: usize) -> Self {
+        Self {
+            max: max as isize,
+            x: vec![0; 2 * max + 1],
+        }
+    }
+}
+
+impl Index<isize> for Diagonals {
+    type Output = isize;
+
+    fn index(&self, k: isize) -> &isize {
+        &self.x[(k + self.max) as usize]
+    }
+}
+
+impl IndexMut<isize> for Diagonals {
+    fn index_mut(&mut self, k: isize) -> &mut isize {
+        &mut self.x[(k + self.max) as usize]
+    }
+}
+
+/// Returns the edits as `(kind, old index, new index)` triples in order, the
+/// removed lines of a change before the added ones.
+///
+/// This is the linear-space variant of Myers' algorithm: the middle of the
+/// shortest edit script is found by searching from both ends at once, and
+/// the parts before and after it are diffed the same way. Memory stays
+/// linear in the number of lines however different the texts are.
+fn myers<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(DiffKind, usize, usize)> {
+    let max = (old.len() + new.len()).div_ceil(2) + 1;
+    let mut forward = Diagonals::new(max);
+    let mut backward = Diagonals::new(max);
+    let mut edits = vec![];
+    conquer(old, 0..old.len(), new, 0..new.len(), &mut forward, &mut backward, &mut edits);
+    removals_first(&edits)
+}
+
+fn conquer<T: PartialEq>(
+    old: &[T],
+    mut old_range: Range<usize>,
+    new: &[T],
+    mut new_range: Range<usize>,
+    forward: &mut Diagonals,
+    backward: &mut Diagonals,
+    edits: &mut Vec<(DiffKind, usize, usize)>,
+) {
+    let (prefix, suffix) = common_affixes(&old[old_range.clone()], &new[new_range.clone()]);
+    edits.extend((0..prefix).map(|i| (DiffKind::Context, old_range.start + i, new_range.start + i)));
+    old_range.start += prefix;
+    new_range.start += prefix;
+    old_range.end -= suffix;
+    new_range.end -= suffix;
+
+    let middle = if old_range.is_empty() || new_range.is_empty() {
+        None
+    } else {
+        middle_snake(&old[old_range.clone()], &new[new_range.clone()], forward, backward)
+    };
+    match middle {
+        Some((x, y)) => {
+            let (old_split, new_split) = (old_range.start + x, new_range.start + y);
+            conquer(old, old_range.start..old_split, new, new_range.start..new_split, forward, backward, edits);
+            conquer(old, old_split..old_range.end, new, new_split..new_range.end, forward, backward, edits);
+        },
+        None => {
+            edits.extend(old_range.clone().map(|i| (DiffKind::Removed, i, new_range.start)));
+            edits.extend(new_range.clone().map(|j| (DiffKind::Added, old_range.end, j)));
+        },
+    }
+    edits.extend((0..suffix).map(|i| (DiffKind::Context, old_range.end + i, new_range.end + i)));
+}
+
+/// Start of the snake in the middle of the shortest edit script between two
+/// non-empty lists without a common prefix or suffix, as `(x, y)`
+fn middle_snake<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    forward: &mut Diagonals,
+    backward: &mut Diagonals,
+) -> Option<(usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    forward[1] = 0;
+    backward[1] = 0;
+    let max = (n + m + 1) / 2 + 1;
+    for d in 0..max {
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && forward[k - 1] < forward[k + 1]) {
+                forward[k + 1]
+            } else {
+                forward[k - 1] + 1
+            };
+            let y = x - k;
+            let start = (x, y);
+            if x < n && (0..m).contains(&y) {
+                x += old[x as usize..].iter().zip(&new[y as usize..]).take_while(|(a, b)| a == b).count() as isize;
+            }
+            forward[k] = x;
+            if odd && (k - delta).abs() < d && forward[k] + backward[delta - k] >= n {
+                return Some((start.0 as usize, start.1 as usize));
+            }
+        }
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && backward[k - 1] < backward[k + 1]) {
+                backward[k + 1]
+            } else {
+                backward[k - 1] + 1
+            };
+            let mut y = x - k;
+            if x < n && (0..m).contains(&y) {
+                let old_rest = old[..(n - x) as usize].iter().rev();
+                let advance = old_rest.zip(new[..(m - y) as usize].iter().rev()).take_while(|(a, b)| a == b).count();
+                x += advance as isize;
+                y += advance as isize;
+            }
+            backward[k] = x;
+            if !odd && (k - delta).abs() <= d && backward[k] + forward[delta - k] >= n {
+                return Some(((n - x) as usize, (m - y) as usize));
+            }
+        }
+    }
+    None
+}
+
+/// The same edits with the removed lines of every run of changes put before
+/// the added ones, the way unified diffs show them
+fn removals_first(edits: &[(DiffKind, usize, usize)]) -> Vec<(DiffKind, usize, usize)> {
+    let mut ordered = Vec::with_capacity(edits.len());
+    let mut index = 0;
+    while index < edits.len() {
+        let (kind, old_index, new_index) = edits[index];
+        if kind == DiffKind::Context {
+            ordered.push(edits[index]);
+            index += 1;
+            continue;
+        }
+        let run_end = edits[index..]
+            .iter()
+            .position(|(kind, _, _)| *kind == DiffKind::Context)
+            .map_or(edits.len(), |offset| index + offset);
+        let removed = edits[index..run_end].iter().filter(|(kind, _, _)| *kind == DiffKind::Removed).count();
+        let added = run_end - index - removed;
+        ordered.extend((0..removed).map(|i| (DiffKind::Removed, old_index + i, new_index)));
+        ordered.extend((0..added).map(|j| (DiffKind::Added, old_index + removed, new_index + j)));
+        index = run_end;
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(diff: &[DiffLine]) -> Vec<DiffKind> {
+        diff.iter().map(|line| line.kind).collect()
+    }
+
+    #[test]
+    fn should_return_empty_diff_for_equal_texts() {
+        assert!(unified("a\nb\nc", "a\nb\nc", 3).is_empty());
+    }
+
+    #[test]
+    fn should_diff_changed_line_with_context() {
+        let diff = unified("a\nb\nc\nd\ne", "a\nb\nX\nd\ne", 1);
+        assert_eq!(
+            kinds(&diff),
+            vec![DiffKind::Hunk, DiffKind::Context, DiffKind::Removed, DiffKind::Added, DiffKind::Context]
+        );
+        assert_eq!(diff[0].text, "@@ -2 +2 @@");
+        assert_eq!(diff[2].text, "c");
+        assert_eq!(diff[3].text, "X");
+    }
+
+    #[test]
+    fn should_split_distant_changes_into_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9";
+        let new = "0\n2\n3\n4\n5\n6\n7\n8\n10";
+        let diff = unified(old, new, 1);
+        let hunks = diff.iter().filter(|line| line.kind == DiffKind::Hunk).count();
+        assert_eq!(hunks, 2);
+    }
+
+    #[test]
+    fn should_diff_insertions_and_removals() {
+        let diff = unified("a\nc", "a\nb\nc\nd", 0);
+        let added: Vec<&str> = diff
+            .iter()
+            .filter(|line| line.kind == DiffKind::Added)
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(added, vec!["b", "d"]);
+        assert!(!diff.iter().any(|line| line.kind == DiffKind::Removed));
+    }
+    /// Length of the longest common subsequence, by dynamic programming
+    fn lcs_len(old: &[u8], new: &[u8]) -> usize {
+        let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+        for i in 0..old.len() {
+            for j in 0..new.len() {
+                lengths[i + 1][j + 1] =
+                    if old[i] == new[j] { lengths[i][j] + 1 } else { lengths[i][j + 1].max(lengths[i + 1][j]) };
+            }
+        }
+        lengths[old.len()][new.len()]
+    }
+
+    #[test]
+    fn should_find_shortest_edit_scripts() {
+        // Pseudo-random lists over a small alphabet, so that they share lines
+        let mut seed: u32 = 7;
+        let mut random_list = |len: usize| -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    (seed >> 16) as u8 % 3
+                })
+                .collect()
+        };
+        for round in 0..300 {
+            let old = random_list(round % 17);
+            let new = random_list(round % 11);
+            let edits = myers(&old, &new);
+            let rebuilt_old: Vec<u8> =
+                edits.iter().filter(|(kind, _, _)| *kind != DiffKind::Added).map(|(_, i, _)| old[*i]).collect();
+            let rebuilt_new: Vec<u8> =
+                edits.iter().filter(|(kind, _, _)| *kind != DiffKind::Removed).map(|(_, _, j)| new[*j]).collect();
+            assert_eq!(rebuilt_old, old);
+            assert_eq!(rebuilt_new, new);
+            assert!(edits.iter().all(|(kind, i, j)| *kind != DiffKind::Context || old[*i] == new[*j]));
+            let changes = edits.iter().filter(|(kind, _, _)| *kind != DiffKind::Context).count();
+            assert_eq!(changes, old.len() + new.len() - 2 * lcs_len(&old, &new));
+        }
+    }
+
+    #[test]
+    fn should_find_changed_lines() {
+        assert_eq!(changed_lines(&["a", "b", "c"], &["a", "b", "c"]), Vec::<usize>::new());
+        assert_eq!(changed_lines(&["a", "b", "c"], &["a", "X", "c", "d"]), vec![1, 3]);
+        // Removed lines mark the line that took their place
+        assert_eq!(changed_lines(&["a", "b", "c"], &["a", "c"]), vec![1]);
+        assert_eq!(changed_lines(&["a", "b"], &["a"]), vec![0]);
+    }
+}
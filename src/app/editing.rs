@@ -0,0 +1,154 @@
+/// Where a file is looked at: the scroll offset of the view as `(x, y)` and
+/// the cursor as `(line, column)`, columns counted in characters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ViewState {
+    pub scroll_offset: (usize, usize),
+    pub cursor: (usize, usize),
+}
+
+/// Number of characters of the given line, 0 past the end of the text
+pub fn line_len(text: &str, line: usize) -> usize {
+    text.split('\n').nth(line).map_or(0, |line| line.trim_end_matches('\r').chars().count())
+}
+
+/// Number of lines, counting an empty last line after a trailing line break
+/// since the cursor can be put there
+pub fn line_count(text: &str) -> usize {
+    text.split('\n').count()
+}
+
+/// Byte index of the `(line, column)` cursor in `text`, clamped to the text
+pub fn byte_index(text: &str, cursor: (usize, usize)) -> usize {
+    let (line, column) = cursor;
+    let mut line_start = 0;
+    for _ in 0..line {
+        match text[line_start..].find('\n') {
+            Some(end) => line_start += end + 1,
+            None => return text.len(),
+        }
+    }
+    let line_end = text[line_start..].find('\n').map_or(text.len(), |end| line_start + end);
+    text[line_start..line_end]
+        .char_indices()
+        .nth(column)
+        .map_or(line_end, |(index, _)| line_start + index)
+}
+
+/// Keep the cursor on an existing line and column
+pub fn clamp_cursor(text: &str, cursor: (usize, usize)) -> (usize, usize) {
+    let line = cursor.0.min(line_count(text) - 1);
+    (line, cursor.1.min(line_len(text, line)))
+}
+
+/// Insert `c` at the cursor, which moves past it
+pub fn insert_char(text: &mut String, cursor: &mut (usize, usize), c: char) {
+    let index = byte_index(text, *cursor);
+    text.insert(index, c);
+    *cursor = if c == '\n' { (cursor.0 + 1, 0) } else { (cursor.0, cursor.1 + 1) };
+}
+
+/// Delete the character before the cursor, joining lines at the start of one.
+/// Returns whether anything was deleted.
+pub fn delete_char_before(text: &mut String, cursor: &mut (usize, usize)) -> bool {
+    let index = byte_index(text, *cursor);
+    let previous = match text[..index].chars().next_back() {
+        Some(previous) => previous,
+        None => return false,
+    };
+    text.remove(index - previous.len_utf8());
+    *cursor = if cursor.1 == 0 {
+        (cursor.0 - 1, line_len(text, cursor.0 - 1).saturating_sub(line_len_after(text, index - 1)))
+    } else {
+        (cursor.0, cursor.1 - 1)
+    };
+    true
+}
+
+/// Characters from byte `index` to the end of its line
+fn line_len_after(text: &str, index: usize) -> usize {
+    let index = index.min(text.len());
+    let end = text[index..].find('\n').map_or(text.len(), |end| index + end);
+    text[index..end].trim_end_matches('\r').chars().count()
+}
+
+/// Move the cursor by `delta` lines, keeping its column where the line
+/// allows. Works on any content given its line count and line lengths, so
+/// that large files paged in from disk can have a cursor too.
+pub fn move_vertical(
+    cursor: (usize, usize),
+    delta: i32,
+    line_count: usize,
+    line_len: impl Fn(usize) -> usize,
+) -> Result<(usize, usize), String> {
+    let target = cursor.0 as i64 + delta as i64;
+    if target < 0 {
+        Err("Cannot move past start of file".to_owned())
+    } else if target as usize >= line_count.max(1) {
+        Err("Cannot move past end of file".to_owned())
+    } else {
+        let line = target as usize;
+        Ok((line, cursor.1.min(line_len(line))))
+    }
+}
+
+/// Move the cursor by `delta` characters within its line of `line_len` characters
+pub fn move_horizontal(cursor: (usize, usize), delta: i32, line_len: usize) -> Result<(usize, usize), String> {
+    let target = cursor.1 as i64 + delta as i64;
+    if target < 0 {
+        Err("Cannot move past start of line".to_owned())
+    } else if target as usize > line_len {
+        Err("Cannot move past end of line".to_owned())
+    } else {
+        Ok((cursor.0, target as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_find_byte_index_of_cursor() {
+        let text = "ab\nçd\n";
+        assert_eq!(byte_index(text, (0, 1)), 1);
+        assert_eq!(byte_index(text, (1, 1)), 5);
+        assert_eq!(byte_index(text, (1, 9)), 6);
+        assert_eq!(byte_index(text, (2, 0)), 7);
+        assert_eq!(byte_index(text, (5, 0)), 7);
+    }
+
+    #[test]
+    fn should_insert_at_cursor() {
+        let mut text = "ac".to_owned();
+        let mut cursor = (0, 1);
+        insert_char(&mut text, &mut cursor, 'b');
+        insert_char(&mut text, &mut cursor, '\n');
+        assert_eq!(text, "ab\nc");
+        assert_eq!(cursor, (1, 0));
+    }
+
+    #[test]
+    fn should_join_lines_when_deleting_at_line_start() {
+        let mut text = "ab\ncd".to_owned();
+        let mut cursor = (1, 0);
+        assert!(delete_char_before(&mut text, &mut cursor));
+        assert_eq!(text, "abcd");
+        assert_eq!(cursor, (0, 2));
+        assert!(delete_char_before(&mut text, &mut cursor));
+        assert_eq!(text, "acd");
+        assert_eq!(cursor, (0, 1));
+
+        let mut cursor = (0, 0);
+        assert!(!delete_char_before(&mut text, &mut cursor));
+    }
+
+    #[test]
+    fn should_keep_column_within_lines_when_moving() {
+        let text = "long line\nab\nlonger line";
+        let len = |line| line_len(text, line);
+        assert_eq!(move_vertical((0, 8), 1, line_count(text), len), Ok((1, 2)));
+        assert!(move_vertical((2, 0), 1, line_count(text), len).is_err());
+        assert_eq!(move_horizontal((1, 2), -1, len(1)), Ok((1, 1)));
+        assert!(move_horizontal((1, 2), 1, len(1)).is_err());
+    }
+}
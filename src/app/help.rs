@@ -0,0 +1,178 @@
+use super::actions::Actions;
+use super::commands::Command;
+use super::keymap::{self, Mode};
+use crate::inputs::key::{Key, KeyCode};
+
+/// A key and what it does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpEntry {
+    pub key: String,
+    pub description: String,
+}
+
+/// The keys of one mode of the editor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpSection {
+    pub mode: &'static str,
+    pub entries: Vec<HelpEntry>,
+}
+
+fn entry(key: impl ToString, description: impl ToString) -> HelpEntry {
+    HelpEntry {
+        key: key.to_string(),
+        description: description.to_string(),
+    }
+}
+
+/// Every key of the editor, grouped by the mode it works in, as bound in the
+/// keymap
+pub fn sections(actions: &Actions) -> Vec<HelpSection> {
+    let bound = |mode: Mode| -> Vec<HelpEntry> {
+        actions
+            .actions()
+            .iter()
+            .flat_map(|action| actions.keys(mode, *action).iter().map(move |keys| entry(keymap::format_sequence(keys), action)))
+            .collect()
+    };
+    let mut write = bound(Mode::Write);
+    write.extend([
+        entry("<Char>", "Insert Character"),
+        entry(Key::plain(KeyCode::Enter), "Insert Line Break"),
+        entry(Key::plain(KeyCode::Backspace), "Delete Character Before"),
+        entry("<Arrows>", "Move Cursor"),
+    ]);
+    let mut command_line = vec![
+        entry(Key::plain(KeyCode::Enter), "Run Command"),
+        entry(Key::plain(KeyCode::Backspace), "Delete Character"),
+        entry(Key::plain(KeyCode::Esc), "Cancel"),
+    ];
+    command_line.extend(Command::usages().iter().map(|usage| entry(format!(":{}", usage), "Command")));
+    vec![
+        HelpSection {
+            mode: "Normal",
+            entries: bound(Mode::Normal),
+        },
+        HelpSection {
+            mode: "Write",
+            entries: write,
+        },
+        HelpSection {
+            mode: "Hex Write",
+            entries: vec![
+                entry("<0-9a-f>", "Overwrite Nibble"),
+                entry(Key::plain(KeyCode::Backspace), "Previous Nibble"),
+            ],
+        },
+        HelpSection {
+            mode: "Command Line",
+            entries: command_line,
+        },
+        HelpSection {
+            mode: "Prompt",
+            entries: vec![
+                entry("<Key>", "Pick the Answer Shown"),
+                entry("<Up>/<Down>", "Scroll Diff"),
+                entry(Key::plain(KeyCode::Esc), "Cancel"),
+            ],
+        },
+        HelpSection {
+            mode: "Command Palette",
+            entries: vec![
+                entry("<Char>", "Search"),
+                entry("<Up>/<Down>", "Select"),
+                entry(Key::plain(KeyCode::Enter), "Run Selected"),
+                entry(Key::plain(KeyCode::Esc), "Close"),
+            ],
+        },
+        HelpSection {
+            mode: "Messages",
+            entries: vec![
+                entry("<Up>/<Down>", "Scroll"),
+                entry(Key::plain(KeyCode::Esc), "Close"),
+            ],
+        },
+        HelpSection {
+            mode: "Help",
+            entries: vec![
+                entry("<Char>", "Search"),
+                entry(Key::plain(KeyCode::Backspace), "Delete Character"),
+                entry("<Up>/<Down>", "Scroll"),
+                entry(Key::plain(KeyCode::Esc), "Close"),
+            ],
+        },
+    ]
+}
+
+/// Entries whose key or description contain `query`, ignoring case. Sections
+/// left empty are dropped, and those whose mode matches are kept whole.
+pub fn search(sections: Vec<HelpSection>, query: &str) -> Vec<HelpSection> {
+    let query = query.to_lowercase();
+    sections
+        .into_iter()
+        .filter_map(|mut section| {
+            if !section.mode.to_lowercase().contains(&query) {
+                section.entries.retain(|entry| {
+                    entry.key.to_lowercase().contains(&query) || entry.description.to_lowercase().contains(&query)
+                });
+            }
+            Some(section).filter(|section| !section.entries.is_empty())
+        })
+        .collect()
+}
+
+/// The full-screen help, while it is open
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HelpOverlay {
+    pub query: String,
+    /// First line shown
+    pub scroll: usize,
+}
+
+impl HelpOverlay {
+    /// Scroll by `delta` lines, keeping some of the `line_count` lines in view
+    pub fn scroll(&mut self, delta: i32, line_count: usize) {
+        let scroll = (self.scroll as i64 + delta as i64).max(0) as usize;
+        self.scroll = scroll.min(line_count.saturating_sub(1));
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.scroll = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.scroll = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::actions::Action;
+
+    #[test]
+    fn should_search_keys_and_descriptions() {
+        let actions: Actions = vec![Action::Quit, Action::SaveFile, Action::NextFile].into();
+        let found = search(sections(&actions), "SAVE");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].mode, "Normal");
+        assert_eq!(found[0].entries, vec![entry(Key::ctrl('s'), "Save File")]);
+
+        let found = search(sections(&actions), "prompt");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].entries.len(), 3);
+        assert!(search(sections(&actions), "nothing like it").is_empty());
+    }
+
+    #[test]
+    fn should_keep_the_scroll_within_the_lines() {
+        let mut overlay = HelpOverlay::default();
+        overlay.scroll(-3, 10);
+        assert_eq!(overlay.scroll, 0);
+        overlay.scroll(20, 10);
+        assert_eq!(overlay.scroll, 9);
+        overlay.push('q');
+        assert_eq!(overlay.scroll, 0);
+    }
+}
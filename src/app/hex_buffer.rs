@@ -0,0 +1,256 @@
+/// Bytes shown on every row of the hex view
+pub const BYTES_PER_ROW: usize = 16;
+/// How much of a file is looked at to tell whether it is binary
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+/// Whether `bytes` should be edited as raw bytes rather than as text:
+/// either it has NUL bytes early on, or it is not valid UTF-8.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) || std::str::from_utf8(bytes).is_err()
+}
+
+/// Parse a byte offset, either decimal or hexadecimal with a `0x` prefix
+pub fn parse_offset(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse::<usize>(),
+    };
+    parsed.map_err(|_| format!("Invalid offset {}", text))
+}
+
+/// Parse a byte sequence written as hex digits, e.g. `de ad be ef` or `deadbeef`
+pub fn parse_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(format!("Invalid byte sequence {}, expected pairs of hex digits", text));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair: String = pair.iter().collect();
+            u8::from_str_radix(&pair, 16).map_err(|_| format!("Invalid byte {}", pair))
+        })
+        .collect()
+}
+
+/// How a byte is shown next to its hex digits, a dot unless it is printable
+pub fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// `bytes` as the rows of the hex view, one per line: the offset, the hex
+/// digits and the printable characters. Binary content is journaled and
+/// diffed this way.
+pub fn dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (row, row_bytes) in bytes.chunks(BYTES_PER_ROW).enumerate() {
+        dump.push_str(&format!("{:08x} ", row * BYTES_PER_ROW));
+        for column in 0..BYTES_PER_ROW {
+            if column == BYTES_PER_ROW / 2 {
+                dump.push(' ');
+            }
+            match row_bytes.get(column) {
+                Some(byte) => dump.push_str(&format!(" {:02x}", byte)),
+                None => dump.push_str("   "),
+            }
+        }
+        dump.push_str("  |");
+        dump.extend(row_bytes.iter().map(|byte| printable(*byte)));
+        dump.push_str("|\n");
+    }
+    dump
+}
+
+/// Bytes of a `dump`, read from the hex digits of its rows
+pub fn parse_dump(dump: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    for line in dump.lines() {
+        // The printable characters may hold anything, and the offset is implied
+        let digits = line.split('|').next().unwrap_or_default().split_whitespace().skip(1);
+        for digit in digits {
+            bytes.push(u8::from_str_radix(digit, 16).map_err(|_| format!("Invalid byte {}", digit))?);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Content of a binary file, edited byte by byte in the hex view
+#[derive(Clone, Default)]
+pub struct HexBuffer {
+    bytes: Vec<u8>,
+    /// Offset of the byte under the cursor
+    cursor: usize,
+    /// Whether the cursor is on the low (second) hex digit of its byte
+    low_nibble: bool,
+    /// First row in view
+    top_row: usize,
+}
+
+impl HexBuffer {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            ..Self::default()
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Replace the bytes, e.g. after a reload, keeping the cursor where it can
+    pub fn set_bytes(&mut self, bytes: Vec<u8>) {
+        self.bytes = bytes;
+        self.cursor = self.cursor.min(self.bytes.len().saturating_sub(1));
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_low_nibble(&self) -> bool {
+        self.low_nibble
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.bytes.len().div_ceil(BYTES_PER_ROW)
+    }
+
+    pub fn top_row(&self) -> usize {
+        self.top_row
+    }
+
+    /// Scroll just enough for the cursor to be among the `visible_rows` in view
+    pub fn scroll_to_cursor(&mut self, visible_rows: usize) {
+        let cursor_row = self.cursor / BYTES_PER_ROW;
+        if cursor_row < self.top_row {
+            self.top_row = cursor_row;
+        } else if visible_rows > 0 && cursor_row >= self.top_row + visible_rows {
+            self.top_row = cursor_row + 1 - visible_rows;
+        }
+    }
+
+    /// Move the cursor by `delta` bytes, staying within the buffer
+    pub fn move_cursor(&mut self, delta: isize) -> Result<(), String> {
+        let target = self.cursor as isize + delta;
+        if target < 0 {
+            Err("Cannot move past start of file".to_owned())
+        } else if target as usize >= self.bytes.len() {
+            Err("Cannot move past end of file".to_owned())
+        } else {
+            self.cursor = target as usize;
+            self.low_nibble = false;
+            Ok(())
+        }
+    }
+
+    pub fn goto(&mut self, offset: usize) -> Result<(), String> {
+        if offset >= self.bytes.len() {
+            return Err(format!("Offset {:#x} is past end of file ({:#x} bytes)", offset, self.bytes.len()));
+        }
+        self.cursor = offset;
+        self.low_nibble = false;
+        Ok(())
+    }
+
+    /// Overwrite the hex digit under the cursor with `digit`, then move to the next one
+    pub fn overwrite_nibble(&mut self, digit: char) -> Result<(), String> {
+        let value = digit.to_digit(16).ok_or_else(|| format!("{} is not a hex digit", digit))? as u8;
+        let byte = self.bytes.get_mut(self.cursor).ok_or_else(|| "Nothing to overwrite".to_owned())?;
+        if self.low_nibble {
+            *byte = (*byte & 0xf0) | value;
+            self.low_nibble = false;
+            if self.cursor + 1 < self.bytes.len() {
+                self.cursor += 1;
+            }
+        } else {
+            *byte = (*byte & 0x0f) | (value << 4);
+            self.low_nibble = true;
+        }
+        Ok(())
+    }
+
+    /// Move the cursor back by one hex digit
+    pub fn back_nibble(&mut self) {
+        if self.low_nibble {
+            self.low_nibble = false;
+        } else if self.cursor > 0 {
+            self.cursor -= 1;
+            self.low_nibble = true;
+        }
+    }
+
+    /// Move the cursor to the next occurrence of `pattern` after it, wrapping
+    /// around at the end of the buffer. Returns the offset found.
+    pub fn find(&mut self, pattern: &[u8]) -> Result<usize, String> {
+        if pattern.is_empty() || pattern.len() > self.bytes.len() {
+            return Err("Pattern not found".to_owned());
+        }
+        let starts = self.bytes.len() - pattern.len() + 1;
+        let found = (1..=starts)
+            .map(|step| (self.cursor + step) % starts)
+            .find(|start| &self.bytes[*start..*start + pattern.len()] == pattern)
+            .ok_or_else(|| "Pattern not found".to_owned())?;
+        self.cursor = found;
+        self.low_nibble = false;
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_binary_content() {
+        assert!(is_binary(&[0x7f, b'E', b'L', b'F', 0, 0]));
+        assert!(is_binary(&[0xff, 0xfe, b'a']));
+        assert!(!is_binary("fn main() {}\n".as_bytes()));
+        assert!(!is_binary("héllo".as_bytes()));
+    }
+
+    #[test]
+    fn should_parse_offsets_and_bytes() {
+        assert_eq!(parse_offset("0x1f"), Ok(31));
+        assert_eq!(parse_offset("31"), Ok(31));
+        assert!(parse_offset("zz").is_err());
+        assert_eq!(parse_bytes("de ad BE ef"), Ok(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert!(parse_bytes("abc").is_err());
+    }
+
+    #[test]
+    fn should_parse_dumps_back() {
+        let bytes: Vec<u8> = (0..=255).chain(b"a|b".iter().copied()).collect();
+        let dump = dump(&bytes);
+        assert_eq!(dump.lines().count(), 17);
+        assert!(dump.starts_with("00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|\n"));
+        assert!(dump.ends_with("00000100  61 7c 62                                          |a|b|\n"));
+        assert_eq!(parse_dump(&dump), Ok(bytes));
+        assert_eq!(parse_dump(""), Ok(vec![]));
+        assert!(parse_dump("00000000  zz").is_err());
+    }
+
+    #[test]
+    fn should_overwrite_nibbles_and_advance() {
+        let mut buffer = HexBuffer::new(vec![0x00, 0x00]);
+        buffer.overwrite_nibble('a').unwrap();
+        assert_eq!(buffer.bytes(), &[0xa0, 0x00]);
+        buffer.overwrite_nibble('B').unwrap();
+        assert_eq!(buffer.bytes(), &[0xab, 0x00]);
+        assert_eq!(buffer.cursor(), 1);
+        assert!(buffer.overwrite_nibble('g').is_err());
+    }
+
+    #[test]
+    fn should_find_byte_sequences_wrapping_around() {
+        let mut buffer = HexBuffer::new(vec![1, 2, 3, 1, 2, 3]);
+        assert_eq!(buffer.find(&[1, 2]), Ok(3));
+        assert_eq!(buffer.find(&[1, 2]), Ok(0));
+        assert!(buffer.find(&[4]).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use syntect::highlighting::ScopeSelectors;
+use syntect::parsing::{ParseState, ScopeStack, SyntaxSet};
+use tui::style::Style;
+
+/// Pieces of a line with the style to draw each in
+pub type StyledLine = Vec<(Style, String)>;
+
+/// Parser state and scopes open between two lines
+#[derive(Clone, PartialEq, Eq)]
+struct LineState {
+    parse: ParseState,
+    scopes: ScopeStack,
+}
+
+/// A highlighted line, reused as long as its text and the state it starts in
+/// are unchanged
+struct CachedLine {
+    text: String,
+    start: LineState,
+    end: LineState,
+    styled: StyledLine,
+}
+
+/// Highlighted lines of one open file
+struct FileCache {
+    syntax: String,
+    lines: Vec<CachedLine>,
+}
+
+/// Syntax highlighting of open files, by the language their extension (or
+/// else their first line) names, in the styles the theme gives their scopes.
+/// Lines are cached per file so that only the edited lines and those after
+/// them whose state changed are highlighted again.
+#[derive(Default)]
+pub struct Highlighter {
+    /// Grammars, loaded on first use as they take a while to decode
+    syntax_set: Option<SyntaxSet>,
+    styles: Vec<(ScopeSelectors, Style)>,
+    files: HashMap<u64, FileCache>,
+}
+
+impl Highlighter {
+    /// Use the styles of a theme from now on, given by scope selector
+    pub fn set_styles(&mut self, styles: &[(String, Style)]) {
+        self.styles = styles
+            .iter()
+            .filter_map(|(selector, style)| ScopeSelectors::from_str(selector).ok().map(|selectors| (selectors, *style)))
+            .collect();
+        self.files.clear();
+    }
+
+    /// Styled lines `start..start + count` of the file with id `file_id`, or
+    /// `None` when its language is not known
+    pub fn highlight(
+        &mut self,
+        file_id: u64,
+        path: &str,
+        text: &str,
+        start: usize,
+        count: usize,
+    ) -> Option<Vec<StyledLine>> {
+        if self.styles.is_empty() {
+            return None;
+        }
+        let syntax_set = self.syntax_set.get_or_insert_with(SyntaxSet::load_defaults_newlines);
+        let extension = Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        let syntax = syntax_set
+            .find_syntax_by_extension(extension)
+            .or_else(|| syntax_set.find_syntax_by_first_line(text.lines().next().unwrap_or("")))?;
+        if syntax.name == syntax_set.find_syntax_plain_text().name {
+            return None;
+        }
+
+        let cache = self.files.entry(file_id).or_insert_with(|| FileCache {
+            syntax: syntax.name.clone(),
+            lines: vec![],
+        });
+        if cache.syntax != syntax.name {
+            cache.syntax = syntax.name.clone();
+            cache.lines.clear();
+        }
+
+        let mut state = LineState {
+            parse: ParseState::new(syntax),
+            scopes: ScopeStack::new(),
+        };
+        // Every line before the view is needed for the state the view starts
+        // in, but those unchanged since last time come from the cache
+        let mut styled_lines = vec![];
+        for (index, line) in text.lines().take(start + count).enumerate() {
+            let is_cached = cache
+                .lines
+                .get(index)
+                .is_some_and(|cached| cached.text == line && cached.start == state);
+            if !is_cached {
+                let cached = highlight_line(line, state.clone(), syntax_set, &self.styles);
+                match cache.lines.get_mut(index) {
+                    Some(previous) => *previous = cached,
+                    None => cache.lines.push(cached),
+                }
+            }
+            let cached = &cache.lines[index];
+            state = cached.end.clone();
+            if index >= start {
+                styled_lines.push(cached.styled.clone());
+            }
+        }
+        Some(styled_lines)
+    }
+
+    /// Drop the cached lines of files that are no longer open
+    pub fn retain_files(&mut self, open_file_ids: &[u64]) {
+        self.files.retain(|file_id, _| open_file_ids.contains(file_id));
+    }
+}
+
+fn highlight_line(line: &str, start: LineState, syntax_set: &SyntaxSet, styles: &[(ScopeSelectors, Style)]) -> CachedLine {
+    let mut end = start.clone();
+    // The grammars are loaded to expect the line break
+    let with_newline = format!("{}\n", line);
+    let styled = match end.parse.parse_line(&with_newline, syntax_set) {
+        Ok(changes) => {
+            // Scopes change at the given byte offsets, the pieces in between
+            // are styled by the scopes open over them
+            let mut styled: StyledLine = vec![];
+            let mut piece_start = 0;
+            for (offset, change) in changes.iter() {
+                let piece_end = (*offset).min(line.len());
+                push_piece(&mut styled, &line[piece_start..piece_end], scope_style(&end.scopes, styles));
+                piece_start = piece_end;
+                // Unbalanced scopes only cost the line its styles
+                let _ = end.scopes.apply(change);
+            }
+            push_piece(&mut styled, &line[piece_start..], scope_style(&end.scopes, styles));
+            styled
+        },
+        // Shown plain rather than not at all
+        Err(_) => vec![(Style::default(), line.to_owned())],
+    };
+    CachedLine {
+        text: line.to_owned(),
+        start,
+        end,
+        styled,
+    }
+}
+
+/// Append `piece` to the line, merged with the last piece if it has the same style
+fn push_piece(styled: &mut StyledLine, piece: &str, style: Style) {
+    if piece.is_empty() {
+        return;
+    }
+    match styled.last_mut() {
+        Some((last_style, last_piece)) if *last_style == style => last_piece.push_str(piece),
+        _ => styled.push((style, piece.to_owned())),
+    }
+}
+
+/// Style of the selector matching the open scopes the most specifically
+fn scope_style(scopes: &ScopeStack, styles: &[(ScopeSelectors, Style)]) -> Style {
+    styles
+        .iter()
+        .filter_map(|(selectors, style)| selectors.does_match(scopes.as_slice()).map(|power| (power, style)))
+        .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map_or(Style::default(), |(_, style)| *style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::theme::Theme;
+
+    fn highlighter() -> Highlighter {
+        let mut highlighter = Highlighter::default();
+        highlighter.set_styles(&Theme::built_in("dark").unwrap().syntax);
+        highlighter
+    }
+
+    fn texts(lines: &[StyledLine]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|(_, piece)| piece.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn should_highlight_known_languages_only() {
+        let mut highlighter = highlighter();
+        let text = "fn main() {\n    let x = 1;\n}\n";
+        let lines = highlighter.highlight(1, "main.rs", text, 1, 5).unwrap();
+        assert_eq!(texts(&lines), vec!["    let x = 1;", "}"]);
+        assert!(lines[0].len() > 1);
+        assert!(highlighter.highlight(2, "notes.unknown", "plain text", 0, 5).is_none());
+    }
+
+    #[test]
+    fn should_rehighlight_lines_after_an_edit_changing_their_state() {
+        let mut highlighter = highlighter();
+        let before = highlighter.highlight(1, "main.rs", "let a = 1;\nlet b = 2;", 0, 2).unwrap();
+        // Opening a comment changes the state the second line starts in
+        let after = highlighter.highlight(1, "main.rs", "/* a = 1;\nlet b = 2;", 0, 2).unwrap();
+        assert_eq!(texts(&after), vec!["/* a = 1;", "let b = 2;"]);
+        assert_ne!(before[1], after[1]);
+        assert_eq!(after[1].len(), 1);
+    }
+}
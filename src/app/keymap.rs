@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::actions::Action;
+use crate::inputs::key::Key;
+
+/// Modes key bindings are given for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    /// Bindings tried before the key types text. Keys which do not type
+    /// text fall back on the normal bindings.
+    Write,
+}
+
+impl Mode {
+    pub fn all() -> &'static [Mode] {
+        &[Mode::Normal, Mode::Write]
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "normal" => Ok(Mode::Normal),
+            "write" => Ok(Mode::Write),
+            _ => Err(format!("Invalid mode {}, expected normal or write", value)),
+        }
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Mode::Normal => "normal",
+            Mode::Write => "write",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Keys typed one after the other to run an action, e.g. `gg` or `<Space>fs`
+pub type KeySequence = Vec<Key>;
+
+/// Parse a key sequence, written as its keys one after the other, e.g. `gg`,
+/// `<C-w>v` or `<Space>fs`
+pub fn parse_sequence(value: &str) -> Result<KeySequence, String> {
+    let mut keys = vec![];
+    let mut rest = value;
+    while let Some(c) = rest.chars().next() {
+        // A `<` not followed by a name and a `>` is the key itself
+        let name_end = match c {
+            '<' => rest.char_indices().skip(2).find(|(_, c)| *c == '>').map(|(index, _)| index + 1),
+            _ => None,
+        };
+        let end = name_end.unwrap_or(c.len_utf8());
+        keys.push(rest[..end].parse()?);
+        rest = &rest[end..];
+    }
+    if keys.is_empty() {
+        return Err("Empty key sequence".to_owned());
+    }
+    Ok(keys)
+}
+
+/// A key sequence as it is parsed
+pub fn format_sequence(keys: &[Key]) -> String {
+    keys.iter().map(ToString::to_string).collect()
+}
+
+/// Key sequences sharing their first keys share a node, holding the action
+/// they run if they end there
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct KeyTrie {
+    action: Option<Action>,
+    children: HashMap<Key, KeyTrie>,
+}
+
+impl KeyTrie {
+    /// Bind `keys` to `action`, unless they already are to another one
+    fn insert(&mut self, keys: &[Key], action: Action) {
+        match keys.split_first() {
+            Some((first, rest)) => self.children.entry(*first).or_default().insert(rest, action),
+            None => {
+                self.action.get_or_insert(action);
+            },
+        }
+    }
+
+    fn get(&self, keys: &[Key]) -> Option<&KeyTrie> {
+        match keys.split_first() {
+            Some((first, rest)) => self.children.get(first)?.get(rest),
+            None => Some(self),
+        }
+    }
+}
+
+/// What keys typed so far lead to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyMatch {
+    /// Action bound to exactly these keys
+    pub action: Option<Action>,
+    /// Whether longer sequences start with these keys
+    pub is_prefix: bool,
+}
+
+/// What to do with keys typed so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Wait for more keys, or for the timeout
+    Wait,
+    /// Run the action bound to the first keys, as many as given
+    Run(Action, usize),
+    /// No binding starts with the first key
+    Unbound,
+}
+
+/// Key sequences bound to each action, by mode. Within a mode, actions bound
+/// last come first, so that keys bound to several actions run the one bound
+/// last. A trie of the sequences is kept for each mode, to match keys as they
+/// are typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    normal: Vec<(Action, Vec<KeySequence>)>,
+    write: Vec<(Action, Vec<KeySequence>)>,
+    normal_trie: KeyTrie,
+    write_trie: KeyTrie,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            normal: Action::iterator()
+                .map(|action| (*action, action.default_keys().into_iter().map(|key| vec![key]).collect()))
+                .collect(),
+            write: vec![],
+            normal_trie: KeyTrie::default(),
+            write_trie: KeyTrie::default(),
+        };
+        keymap.build_tries();
+        keymap
+    }
+}
+
+impl Keymap {
+    fn bindings(&self, mode: Mode) -> &Vec<(Action, Vec<KeySequence>)> {
+        match mode {
+            Mode::Normal => &self.normal,
+            Mode::Write => &self.write,
+        }
+    }
+
+    fn bindings_mut(&mut self, mode: Mode) -> &mut Vec<(Action, Vec<KeySequence>)> {
+        match mode {
+            Mode::Normal => &mut self.normal,
+            Mode::Write => &mut self.write,
+        }
+    }
+
+    fn trie(&self, mode: Mode) -> &KeyTrie {
+        match mode {
+            Mode::Normal => &self.normal_trie,
+            Mode::Write => &self.write_trie,
+        }
+    }
+
+    fn build_tries(&mut self) {
+        for mode in Mode::all() {
+            let mut trie = KeyTrie::default();
+            for (action, sequences) in self.bindings(*mode).iter() {
+                for keys in sequences.iter() {
+                    trie.insert(keys, *action);
+                }
+            }
+            match mode {
+                Mode::Normal => self.normal_trie = trie,
+                Mode::Write => self.write_trie = trie,
+            }
+        }
+    }
+
+    /// Bind key sequences to `action` in `mode`, in place of those it had there
+    pub fn bind(&mut self, mode: Mode, action: Action, sequences: Vec<KeySequence>) {
+        let bindings = self.bindings_mut(mode);
+        bindings.retain(|(bound, _)| *bound != action);
+        bindings.insert(0, (action, sequences));
+        self.build_tries();
+    }
+
+    /// What `keys` typed one after the other lead to in `mode`
+    pub fn find(&self, mode: Mode, keys: &[Key]) -> KeyMatch {
+        self.trie(mode).get(keys).map_or(KeyMatch::default(), |node| KeyMatch {
+            action: node.action,
+            is_prefix: !node.children.is_empty(),
+        })
+    }
+
+    /// Key sequences bound to `action` in `mode`
+    pub fn keys(&self, mode: Mode, action: Action) -> &[KeySequence] {
+        self.bindings(mode)
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, sequences)| sequences.as_slice())
+    }
+
+    /// Key sequences bound to several actions in the same mode, as errors
+    /// telling which action runs
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = vec![];
+        for mode in Mode::all() {
+            let bindings = self.bindings(*mode);
+            let mut seen: Vec<&KeySequence> = vec![];
+            for (_, sequences) in bindings.iter() {
+                for keys in sequences.iter() {
+                    if seen.contains(&keys) {
+                        continue;
+                    }
+                    seen.push(keys);
+                    let actions: Vec<String> = bindings
+                        .iter()
+                        .filter(|(_, sequences)| sequences.contains(keys))
+                        .map(|(action, _)| action.to_string())
+                        .collect();
+                    if actions.len() > 1 {
+                        conflicts.push(format!(
+                            "Key {} is bound to {} in {} mode, it runs {}",
+                            format_sequence(keys),
+                            actions.join(", "),
+                            mode,
+                            actions[0]
+                        ));
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+/// What to do with the `pending` keys, typed one after the other, matched by
+/// `find`. While they start longer sequences, more keys are waited for until
+/// the timeout. Then the longest sequence they start with is run, and keys
+/// nothing starts with are left unbound.
+pub fn resolve(pending: &[Key], timed_out: bool, find: impl Fn(&[Key]) -> KeyMatch) -> Resolution {
+    let found = find(pending);
+    if found.is_prefix && !timed_out {
+        return Resolution::Wait;
+    }
+    (1..=pending.len())
+        .rev()
+        .find_map(|len| find(&pending[..len]).action.map(|action| Resolution::Run(action, len)))
+        .unwrap_or(Resolution::Unbound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inputs::key::{KeyCode, Modifiers};
+
+    #[test]
+    fn should_have_no_conflicts_by_default() {
+        assert!(Keymap::default().conflicts().is_empty());
+    }
+
+    fn action(keymap: &Keymap, mode: Mode, keys: &[Key]) -> Option<Action> {
+        keymap.find(mode, keys).action
+    }
+
+    #[test]
+    fn should_rebind_keys_by_mode() {
+        let mut keymap = Keymap::default();
+        keymap.bind(Mode::Normal, Action::Quit, vec![vec![Key::ctrl('q')]]);
+        keymap.bind(Mode::Write, Action::EndWriteMode, vec![vec![Key::plain(KeyCode::Esc)]]);
+        assert_eq!(action(&keymap, Mode::Normal, &[Key::ctrl('q')]), Some(Action::Quit));
+        assert_eq!(action(&keymap, Mode::Normal, &[Key::char('q')]), None);
+        assert_eq!(action(&keymap, Mode::Normal, &[Key::plain(KeyCode::Esc)]), None);
+        assert_eq!(action(&keymap, Mode::Write, &[Key::plain(KeyCode::Esc)]), Some(Action::EndWriteMode));
+        assert_eq!(keymap.keys(Mode::Normal, Action::SaveFile), &[vec![Key::ctrl('s')]]);
+    }
+
+    #[test]
+    fn should_report_conflicts_and_run_the_last_bound_action() {
+        let mut keymap = Keymap::default();
+        keymap.bind(Mode::Normal, Action::SaveFile, vec![vec![Key::char('q')], vec![Key::ctrl('s')]]);
+        assert_eq!(action(&keymap, Mode::Normal, &[Key::char('q')]), Some(Action::SaveFile));
+        assert_eq!(
+            keymap.conflicts(),
+            vec!["Key q is bound to Save File, Quit in normal mode, it runs Save File".to_owned()]
+        );
+    }
+
+    #[test]
+    fn should_parse_key_sequences() {
+        assert_eq!(parse_sequence("gg"), Ok(vec![Key::char('g'), Key::char('g')]));
+        assert_eq!(parse_sequence("<Space>fs"), Ok(vec![Key::plain(KeyCode::Space), Key::char('f'), Key::char('s')]));
+        assert_eq!(parse_sequence("<C-w>v"), Ok(vec![Key::ctrl('w'), Key::char('v')]));
+        assert_eq!(parse_sequence("<<>"), Ok(vec![Key::char('<')]));
+        assert_eq!(parse_sequence("<"), Ok(vec![Key::char('<')]));
+        let ctrl_alt_x = Key::new(KeyCode::Char('x'), Modifiers::CTRL | Modifiers::ALT);
+        assert_eq!(parse_sequence("<C-A-x>g"), Ok(vec![ctrl_alt_x, Key::char('g')]));
+        let keys = vec![Key::alt('x'), Key::char('<'), Key::char('>'), Key::ctrl('>'), Key::plain(KeyCode::F(5))];
+        assert_eq!(parse_sequence(&format_sequence(&keys)), Ok(keys));
+        assert!(parse_sequence("").is_err());
+        assert!(parse_sequence("<Nope>").is_err());
+    }
+
+    #[test]
+    fn should_match_sequences_and_their_prefixes() {
+        let mut keymap = Keymap::default();
+        let g = Key::char('g');
+        keymap.bind(Mode::Normal, Action::ScrollUp, vec![vec![g, g]]);
+        keymap.bind(Mode::Normal, Action::NextFile, vec![vec![g]]);
+        assert_eq!(keymap.find(Mode::Normal, &[g]), KeyMatch { action: Some(Action::NextFile), is_prefix: true });
+        assert_eq!(keymap.find(Mode::Normal, &[g, g]), KeyMatch { action: Some(Action::ScrollUp), is_prefix: false });
+        assert_eq!(keymap.find(Mode::Normal, &[Key::char('x'), g]), KeyMatch::default());
+
+        let find = |keys: &[Key]| keymap.find(Mode::Normal, keys);
+        // A prefix bound on its own waits, until the timeout runs it
+        assert_eq!(resolve(&[g], false, find), Resolution::Wait);
+        assert_eq!(resolve(&[g], true, find), Resolution::Run(Action::NextFile, 1));
+        assert_eq!(resolve(&[g, g], false, find), Resolution::Run(Action::ScrollUp, 2));
+        // Typing on past it runs the prefix, leaving the other keys
+        assert_eq!(resolve(&[g, Key::char('q')], false, find), Resolution::Run(Action::NextFile, 1));
+        assert_eq!(resolve(&[Key::char('x'), g], false, find), Resolution::Unbound);
+    }
+}
@@ -0,0 +1,264 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use log::error;
+
+/// Files bigger than this are opened in large-file mode
+pub const LARGE_FILE_THRESHOLD: u64 = 16 * 1024 * 1024;
+/// The index only remembers where every this many lines start, which bounds
+/// its memory while keeping any line at most this many lines of reading away
+const LINES_PER_CHECKPOINT: usize = 64;
+/// Longest part of a single line that is ever materialized
+const MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// A read-only view of a file too big to be held in memory. Lines are paged
+/// in from disk on demand while a line index is built in the background.
+#[derive(Clone)]
+pub struct LargeFile {
+    inner: Arc<LargeFileInner>,
+}
+
+struct LargeFileInner {
+    path: String,
+    len: u64,
+    /// Byte offset of every `LINES_PER_CHECKPOINT`th line, starting with line 0
+    checkpoints: RwLock<Vec<u64>>,
+    /// Lines indexed so far
+    line_count: AtomicU64,
+    indexed_bytes: AtomicU64,
+    indexed: AtomicBool,
+    /// Lines materialized for the last requested viewport
+    viewport: Mutex<Option<(usize, usize, Vec<String>)>>,
+}
+
+impl LargeFile {
+    /// Open `path` and start indexing its lines on a background thread
+    pub fn open(path: &str, len: u64) -> Self {
+        let inner = Arc::new(LargeFileInner {
+            path: path.to_owned(),
+            len,
+            checkpoints: RwLock::new(vec![0]),
+            line_count: AtomicU64::new(0),
+            indexed_bytes: AtomicU64::new(0),
+            indexed: AtomicBool::new(false),
+            viewport: Mutex::new(None),
+        });
+        let indexer = Arc::clone(&inner);
+        std::thread::spawn(move || {
+            if let Err(err) = indexer.index() {
+                error!("📄 Failed to index {}: {}", indexer.path, err);
+            }
+            indexer.indexed.store(true, Ordering::Relaxed);
+        });
+        Self { inner }
+    }
+
+    /// Number of lines, as far as indexed yet
+    pub fn line_count(&self) -> usize {
+        self.inner.line_count.load(Ordering::Relaxed) as usize
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.inner.indexed.load(Ordering::Relaxed)
+    }
+
+    /// Indexing progress, in percent
+    pub fn indexing_progress(&self) -> u8 {
+        if self.inner.len == 0 {
+            return 100;
+        }
+        (self.inner.indexed_bytes.load(Ordering::Relaxed) * 100 / self.inner.len) as u8
+    }
+
+    /// Read up to `count` lines starting at line `start`. Only the lines asked
+    /// for are read from disk, and the last viewport is kept around since it
+    /// is asked for again on every frame.
+    pub fn lines(&self, start: usize, count: usize) -> Vec<String> {
+        let mut viewport = self.inner.viewport.lock().unwrap();
+        if let Some((cached_start, cached_count, lines)) = viewport.as_ref() {
+            // A partial viewport may still grow while the index is being built
+            if *cached_start == start && *cached_count == count && (lines.len() == count || self.is_indexed()) {
+                return lines.clone();
+            }
+        }
+        let lines = match self.inner.read_lines(start, count) {
+            Ok(lines) => lines,
+            Err(err) => {
+                error!("📄 Failed to read {}: {}", self.inner.path, err);
+                vec![]
+            },
+        };
+        *viewport = Some((start, count, lines.clone()));
+        lines
+    }
+
+    /// Read line `index`, taking it from the last viewport if it is in there.
+    /// Unlike `lines`, this leaves the viewport cache alone.
+    pub fn line(&self, index: usize) -> Option<String> {
+        if let Some((start, _, lines)) = self.inner.viewport.lock().unwrap().as_ref() {
+            if let Some(line) = index.checked_sub(*start).and_then(|offset| lines.get(offset)) {
+                return Some(line.clone());
+            }
+        }
+        match self.inner.read_lines(index, 1) {
+            Ok(mut lines) => lines.pop(),
+            Err(err) => {
+                error!("📄 Failed to read {}: {}", self.inner.path, err);
+                None
+            },
+        }
+    }
+
+    /// Lines from `start` on, going towards the first line instead when not
+    /// `forward`. They are read a block at a time as they are taken, leaving
+    /// the viewport cache alone.
+    pub fn lines_from(&self, start: usize, forward: bool) -> Box<dyn Iterator<Item = String> + '_> {
+        let read_block = move |block_start: usize, count: usize| match self.inner.read_lines(block_start, count) {
+            Ok(lines) => lines,
+            Err(err) => {
+                error!("📄 Failed to read {}: {}", self.inner.path, err);
+                vec![]
+            },
+        };
+        if forward {
+            let blocks = (start..).step_by(LINES_PER_CHECKPOINT);
+            let lines = blocks.map(move |block| read_block(block, LINES_PER_CHECKPOINT));
+            Box::new(lines.take_while(|lines| !lines.is_empty()).flatten())
+        } else {
+            let first_block = start / LINES_PER_CHECKPOINT;
+            Box::new((0..=first_block).rev().flat_map(move |block| {
+                let count = if block == first_block { start % LINES_PER_CHECKPOINT + 1 } else { LINES_PER_CHECKPOINT };
+                read_block(block * LINES_PER_CHECKPOINT, count).into_iter().rev()
+            }))
+        }
+    }
+}
+
+impl LargeFileInner {
+    fn index(self: &Arc<Self>) -> std::io::Result<()> {
+        let mut reader = BufReader::with_capacity(1 << 20, File::open(&self.path)?);
+        let mut offset: u64 = 0;
+        let mut lines: u64 = 0;
+        let mut last_byte = b'\n';
+        loop {
+            // Stop early once the file was closed
+            if Arc::strong_count(self) == 1 {
+                return Ok(());
+            }
+            let buffer = reader.fill_buf()?;
+            if buffer.is_empty() {
+                break;
+            }
+            let mut checkpoints = vec![];
+            for (position, byte) in buffer.iter().enumerate() {
+                if *byte == b'\n' {
+                    lines += 1;
+                    if (lines as usize).is_multiple_of(LINES_PER_CHECKPOINT) {
+                        checkpoints.push(offset + position as u64 + 1);
+                    }
+                }
+            }
+            let read = buffer.len();
+            last_byte = buffer[read - 1];
+            reader.consume(read);
+            offset += read as u64;
+
+            self.checkpoints.write().unwrap().extend(checkpoints);
+            self.line_count.store(lines, Ordering::Relaxed);
+            self.indexed_bytes.store(offset, Ordering::Relaxed);
+        }
+        // A last line without a line break still counts
+        if last_byte != b'\n' {
+            self.line_count.store(lines + 1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn read_lines(&self, start: usize, count: usize) -> std::io::Result<Vec<String>> {
+        let checkpoint = match self.checkpoints.read().unwrap().get(start / LINES_PER_CHECKPOINT) {
+            Some(checkpoint) => *checkpoint,
+            None => return Ok(vec![]),
+        };
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(checkpoint))?;
+        let mut reader = BufReader::new(file);
+
+        let mut line: Vec<u8> = vec![];
+        for _ in 0..start % LINES_PER_CHECKPOINT {
+            if !read_line_capped(&mut reader, &mut line)? {
+                return Ok(vec![]);
+            }
+        }
+        let mut lines = vec![];
+        while lines.len() < count && read_line_capped(&mut reader, &mut line)? {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            lines.push(String::from_utf8_lossy(&line).into_owned());
+        }
+        Ok(lines)
+    }
+}
+
+/// Read the next line into `line`, keeping at most `MAX_LINE_BYTES` of it.
+/// Returns whether there was a line left to read.
+fn read_line_capped(reader: &mut impl BufRead, line: &mut Vec<u8>) -> std::io::Result<bool> {
+    line.clear();
+    let mut found_any = false;
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(found_any);
+        }
+        found_any = true;
+        let (chunk, done) = match buffer.iter().position(|byte| *byte == b'\n') {
+            Some(end) => (&buffer[..end], Some(end + 1)),
+            None => (buffer, None),
+        };
+        let room = MAX_LINE_BYTES.saturating_sub(line.len());
+        line.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        match done {
+            Some(consumed) => {
+                reader.consume(consumed);
+                return Ok(true);
+            },
+            None => {
+                let consumed = buffer.len();
+                reader.consume(consumed);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_page_in_lines_from_the_index() {
+        let path = std::env::temp_dir().join("rust_text_editor_large_file_test.txt");
+        let content: String = (0..1000).map(|i| format!("line {}\r\n", i)).collect();
+        std::fs::write(&path, &content).unwrap();
+
+        let large_file = LargeFile::open(path.to_str().unwrap(), content.len() as u64);
+        while !large_file.is_indexed() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(large_file.line_count(), 1000);
+        assert_eq!(large_file.indexing_progress(), 100);
+        assert_eq!(large_file.lines(130, 2), vec!["line 130", "line 131"]);
+        assert_eq!(large_file.line(131), Some("line 131".to_owned()));
+        assert_eq!(large_file.line(500), Some("line 500".to_owned()));
+        assert_eq!(large_file.line(1000), None);
+        assert_eq!(large_file.lines_from(998, true).collect::<Vec<_>>(), vec!["line 998", "line 999"]);
+        assert_eq!(large_file.lines_from(130, false).nth(70), Some("line 60".to_owned()));
+        assert_eq!(large_file.lines_from(1, false).collect::<Vec<_>>(), vec!["line 1", "line 0"]);
+        assert!(matches!(*large_file.inner.viewport.lock().unwrap(), Some((130, 2, _))));
+        assert_eq!(large_file.lines(999, 5), vec!["line 999"]);
+        assert!(large_file.lines(1000, 5).is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
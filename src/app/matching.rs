@@ -0,0 +1,112 @@
+use std::ops::Range;
+
+/// Brackets matched with `%`, as opening and closing pairs
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// Whether the bracket matching `c` comes after it, if `c` is a bracket
+pub fn scans_forward(c: char) -> Option<bool> {
+    BRACKETS.iter().find_map(|(open, close)| match c {
+        _ if c == *open => Some(true),
+        _ if c == *close => Some(false),
+        _ => None,
+    })
+}
+
+/// Position of the bracket matching the one at `column` of the first of
+/// `lines`, as how many lines away it is and its column. `lines` go towards
+/// the end of the text from an opening bracket and towards the start from a
+/// closing one, see `scans_forward`, and are only taken until the match.
+pub fn matching_bracket<S: AsRef<str>>(lines: impl IntoIterator<Item = S>, column: usize) -> Option<(usize, usize)> {
+    let mut lines = lines.into_iter();
+    let first = lines.next()?;
+    let c = first.as_ref().chars().nth(column)?;
+    let forward = scans_forward(c)?;
+    let &(open, close) = BRACKETS.iter().find(|(open, close)| c == *open || c == *close)?;
+    let (nested, matching) = if forward { (open, close) } else { (close, open) };
+    let mut depth = 0;
+    for (distance, text) in std::iter::once(first).chain(lines).enumerate() {
+        let chars: Vec<char> = text.as_ref().chars().collect();
+        let columns = match distance {
+            0 if forward => column..chars.len(),
+            0 => 0..column + 1,
+            _ => 0..chars.len(),
+        };
+        for step in columns.clone() {
+            let col = if forward { step } else { columns.start + columns.end - 1 - step };
+            if chars[col] == nested {
+                depth += 1;
+            } else if chars[col] == matching {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((distance, col));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The word the character at `column` of `line` is part of, if any
+pub fn word_at(line: &str, column: usize) -> Option<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if !chars.get(column).is_some_and(|c| is_word_char(*c)) {
+        return None;
+    }
+    let start = chars[..column].iter().rposition(|c| !is_word_char(*c)).map_or(0, |index| index + 1);
+    let end = chars[column..].iter().position(|c| !is_word_char(*c)).map_or(chars.len(), |index| column + index);
+    Some(chars[start..end].iter().collect())
+}
+
+/// Columns of every whole-word occurrence of `word` in `line`
+pub fn word_occurrences(line: &str, word: &str) -> Vec<Range<usize>> {
+    let chars: Vec<char> = line.chars().collect();
+    let word: Vec<char> = word.chars().collect();
+    let mut occurrences = vec![];
+    if word.is_empty() || word.len() > chars.len() {
+        return occurrences;
+    }
+    let mut start = 0;
+    while start + word.len() <= chars.len() {
+        let end = start + word.len();
+        let is_whole =
+            (start == 0 || !is_word_char(chars[start - 1])) && chars.get(end).is_none_or(|c| !is_word_char(*c));
+        if chars[start..end] == word[..] && is_whole {
+            occurrences.push(start..end);
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+    occurrences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_nested_brackets_across_lines() {
+        let lines = ["fn f(a: [u8; 2]) {", "    (a[0])", "}"];
+        assert_eq!(matching_bracket(lines, 4), Some((0, 15)));
+        assert_eq!(matching_bracket(lines, 15), Some((0, 4)));
+        assert_eq!(matching_bracket(lines, 17), Some((2, 0)));
+        assert_eq!(matching_bracket(lines.iter().rev(), 0), Some((2, 17)));
+        assert_eq!(matching_bracket(&lines[1..], 4), Some((0, 9)));
+        assert_eq!(matching_bracket(lines, 0), None);
+        assert_eq!(matching_bracket(["(("], 0), None);
+        assert_eq!(scans_forward('}'), Some(false));
+        assert_eq!(scans_forward('x'), None);
+    }
+
+    #[test]
+    fn should_find_whole_words() {
+        assert_eq!(word_at("let value = values;", 5), Some("value".to_owned()));
+        assert_eq!(word_at("let value = values;", 3), None);
+        assert_eq!(word_occurrences("value = values + value", "value"), vec![0..5, 17..22]);
+        assert_eq!(word_occurrences("é_x é_x", "é_x"), vec![0..3, 4..7]);
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+/// Messages kept for `:messages`, older ones are dropped
+pub const HISTORY_SIZE: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// How long a message stays on the message line. Errors stay longer, so
+    /// that they are not missed.
+    fn lifetime(&self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(4),
+            Severity::Warning => Duration::from_secs(6),
+            Severity::Error => Duration::from_secs(10),
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// A notice or an error for the user, as opposed to the logs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub severity: Severity,
+    pub text: String,
+    pub at: Instant,
+}
+
+/// Messages shown to the user, the last one on the message line until it
+/// expires, and all of them in the history
+#[derive(Debug, Clone, Default)]
+pub struct Messages {
+    history: VecDeque<Message>,
+    /// Set once the last message was dismissed before it expired
+    dismissed: bool,
+}
+
+impl Messages {
+    pub fn push(&mut self, severity: Severity, text: String, at: Instant) {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(Message { severity, text, at });
+        self.dismissed = false;
+    }
+
+    /// The message to show on the message line at `now`, if the last one did
+    /// not expire yet
+    pub fn current(&self, now: Instant) -> Option<&Message> {
+        let is_shown = |message: &&Message| now.saturating_duration_since(message.at) < message.severity.lifetime();
+        self.history.back().filter(|message| !self.dismissed && is_shown(message))
+    }
+
+    /// Clear the message line, keeping the message in the history
+    pub fn dismiss(&mut self) {
+        self.dismissed = true;
+    }
+
+    /// Every message kept, oldest first
+    pub fn history(&self) -> &VecDeque<Message> {
+        &self.history
+    }
+}
+
+/// The full-screen history of messages, while it is open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessagesView {
+    /// First line shown
+    pub scroll: usize,
+}
+
+impl MessagesView {
+    /// Scroll by `delta` lines, keeping some of the `line_count` lines in view
+    pub fn scroll(&mut self, delta: i32, line_count: usize) {
+        let scroll = (self.scroll as i64 + delta as i64).max(0) as usize;
+        self.scroll = scroll.min(line_count.saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_expire_messages_by_severity() {
+        let start = Instant::now();
+        let mut messages = Messages::default();
+        assert!(messages.current(start).is_none());
+
+        messages.push(Severity::Info, "Saved".to_owned(), start);
+        assert_eq!(messages.current(start).map(|message| message.text.as_str()), Some("Saved"));
+        assert!(messages.current(start + Duration::from_secs(5)).is_none());
+
+        messages.push(Severity::Error, "Failed".to_owned(), start);
+        assert!(messages.current(start + Duration::from_secs(5)).is_some());
+        messages.dismiss();
+        assert!(messages.current(start).is_none());
+        assert_eq!(messages.history().len(), 2);
+    }
+
+    #[test]
+    fn should_keep_a_bounded_history() {
+        let start = Instant::now();
+        let mut messages = Messages::default();
+        for index in 0..HISTORY_SIZE + 3 {
+            messages.push(Severity::Warning, index.to_string(), start);
+        }
+        assert_eq!(messages.history().len(), HISTORY_SIZE);
+        assert_eq!(messages.history().front().map(|message| message.text.as_str()), Some("3"));
+    }
+}
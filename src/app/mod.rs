@@ -1,16 +1,72 @@
-use log::{debug, error, warn};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info, warn};
 
 use self::actions::Actions;
+use self::commands::{Command, UserCommand};
+use self::editing::ViewState;
+use self::help::HelpOverlay;
+use self::highlight::Highlighter;
+use self::keymap::{Keymap, Mode, Resolution};
+use self::messages::{Messages, MessagesView, Severity};
 use self::open_files_data::OpenFilesData;
+use self::options::{Assignment, EditorOption};
+use self::palette::{Palette, PaletteItem};
+use self::panels::Panels;
+use self::prompt::{Prompt, PromptAnswer, PromptKind};
+use self::settings::Settings;
 use self::state::AppState;
+use self::theme::Theme;
+use self::windows::{FocusDirection, SplitDirection, Window, Windows};
 use crate::app::actions::Action;
-use crate::inputs::key::Key;
-use crate::io::IoEvent;
+use crate::inputs::key::{Key, KeyCode, Modifiers};
+use crate::io::session::{Session, SessionWindow};
+use crate::io::{layout, session, swap, IoEvent};
 
 pub mod open_files_data;
+pub mod options;
+pub mod overview;
+pub mod palette;
+pub mod panels;
 pub mod actions;
+pub mod commands;
+pub mod diff;
+pub mod editing;
+pub mod help;
+pub mod hex_buffer;
+pub mod highlight;
+pub mod keymap;
+pub mod large_file;
+pub mod matching;
+pub mod messages;
+pub mod paths;
+pub mod prompt;
+pub mod settings;
 pub mod state;
+pub mod theme;
 pub mod ui;
+pub mod whitespace;
+pub mod windows;
+pub mod wrap;
+
+/// How often unsaved edits are journaled to swap files
+const SWAP_JOURNAL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often open files are checked for changes made by other programs
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Weight a window grows or shrinks by at once, out of 10 for each window
+const WINDOW_RESIZE_STEP: i32 = 2;
+/// Columns the help panel and rows the log panel are resized by at once
+const HELP_RESIZE_STEP: i32 = 4;
+const LOGS_RESIZE_STEP: i32 = 2;
+/// How long the cursor stays put before other occurrences of the word under
+/// it are highlighted
+const WORD_HIGHLIGHT_DELAY: Duration = Duration::from_millis(500);
+/// Lines the full-screen help and messages scroll by a page at a time
+const HELP_PAGE: i32 = 10;
+
+/// Where the cursor is: in which text file, if any, and at which position
+type CursorPlace = (Option<u64>, (usize, usize));
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum AppReturn {
@@ -27,6 +83,44 @@ pub struct App {
     /// State
     is_loading: bool,
     state: AppState,
+    /// Questions waiting for the user, the first one is displayed
+    prompts: VecDeque<Prompt>,
+    last_journal: Instant,
+    last_disk_check: Instant,
+    /// Where the cursor was on the last tick, and since when it is there
+    cursor_seen: CursorPlace,
+    cursor_seen_at: Instant,
+    /// Word under the cursor once it stayed put for a while, with where the
+    /// cursor was then
+    highlighted_word: Option<(CursorPlace, String)>,
+    /// Keys typed so far of a binding of several, and since when they wait for
+    /// the next one
+    pending_keys: Vec<Key>,
+    pending_since: Instant,
+    /// Set once the app should exit after an async action, e.g. saving all files
+    exit_requested: bool,
+    /// Command being typed after `:`, while the command line is open
+    command_line: Option<String>,
+    /// Last pattern searched for with `:find`
+    last_search: Option<String>,
+    settings: Settings,
+    /// Windows the body is split into. The focused one shows the selected
+    /// file, with its view kept live in the open files data.
+    windows: Windows,
+    highlighter: Highlighter,
+    theme: Theme,
+    /// The help and log panels, as the user left them
+    panels: Panels,
+    /// Full-screen help, while it is open
+    help_overlay: Option<HelpOverlay>,
+    /// Command palette, while it is open
+    palette: Option<Palette>,
+    /// Commands defined in config files
+    user_commands: Vec<UserCommand>,
+    /// Notices and errors for the user, apart from the logs
+    messages: Messages,
+    /// History of the messages, while it is open
+    messages_view: Option<MessagesView>,
 }
 
 impl App {
@@ -34,120 +128,575 @@ impl App {
         let actions = vec![Action::Quit].into();
         let is_loading = false;
         let state = AppState::default();
+        let theme = Theme::initial();
+        let mut highlighter = Highlighter::default();
+        highlighter.set_styles(&theme.syntax);
 
         Self {
             io_tx,
             actions,
             is_loading,
             state,
+            prompts: VecDeque::new(),
+            last_journal: Instant::now(),
+            last_disk_check: Instant::now(),
+            cursor_seen: (None, (0, 0)),
+            cursor_seen_at: Instant::now(),
+            highlighted_word: None,
+            pending_keys: vec![],
+            pending_since: Instant::now(),
+            exit_requested: false,
+            command_line: None,
+            last_search: None,
+            settings: Settings::default(),
+            windows: Windows::new(Window {
+                file_id: 0,
+                view: Default::default(),
+            }),
+            highlighter,
+            theme,
+            panels: Panels::default(),
+            help_overlay: None,
+            palette: None,
+            user_commands: vec![],
+            messages: Messages::default(),
+            messages_view: None,
         }
     }
 
     /// Handle a user action
     pub async fn do_action(&mut self, key: Key) -> AppReturn {
-        if let Some(value) = self.attempt_write(key) {
+        if let Some(value) = self.attempt_answer(key).await {
             value
-        } else if let Some(action) = self.actions.find(key) {
-            debug!("Run action [{:?}]", action);
-            match action {
-                Action::Quit => AppReturn::Exit,
-                // Write o clock
-                Action::BeginWriteMode => {
-                    self.dispatch(IoEvent::ToggleWriteMode(true)).await;
-                    AppReturn::Continue
-                }
-                // No more writing
-                Action::EndWriteMode => {
-                    self.dispatch(IoEvent::ToggleWriteMode(false)).await;
-                    AppReturn::Continue
-                },
-                // Open file
-                Action::OpenFile => {
-                    self.dispatch(IoEvent::OpenFile).await;
-                    AppReturn::Continue
-                },
-                // Save file
-                Action::SaveFile => {
-                    self.dispatch(IoEvent::SaveFile).await;
-                    AppReturn::Continue
-                },
-                // Next file
-                Action::NextFile => {
-                    self.dispatch(IoEvent::NextFile).await;
-                    AppReturn::Continue
-                },
-                // Previous file
-                Action::PreviousFile => {
-                    self.dispatch(IoEvent::PreviousFile).await;
-                    AppReturn::Continue
-                },
-                // Close file
-                Action::CloseFile => {
-                    self.dispatch(IoEvent::CloseFile).await;
-                    AppReturn::Continue
-                },
-                // Scroll down
-                Action::ScrollDown => {
-                    self.dispatch(IoEvent::ScrollDown).await;
-                    AppReturn::Continue
-                },
-                // Scroll up
-                Action::ScrollUp => {
-                    self.dispatch(IoEvent::ScrollUp).await;
-                    AppReturn::Continue
-                },
-                // Scroll left
-                Action::ScrollLeft => {
-                    self.dispatch(IoEvent::ScrollLeft).await;
-                    AppReturn::Continue
+        } else if let Some(value) = self.attempt_messages_view(key) {
+            value
+        } else if let Some(value) = self.attempt_help_overlay(key) {
+            value
+        } else if let Some(value) = self.attempt_palette(key).await {
+            value
+        } else if let Some(value) = self.attempt_command_line(key).await {
+            value
+        } else {
+            self.pending_keys.push(key);
+            self.pending_since = Instant::now();
+            self.resolve_pending_keys(false).await
+        }
+    }
+
+    /// Mode whose bindings keys are matched against
+    fn key_mode(&self) -> Mode {
+        if self.state.is_write_mode() {
+            Mode::Write
+        } else {
+            Mode::Normal
+        }
+    }
+
+    /// Run what the keys typed so far lead to, unless they may still start a
+    /// longer binding. Once `timed_out`, they are no longer waited on.
+    async fn resolve_pending_keys(&mut self, timed_out: bool) -> AppReturn {
+        while !self.pending_keys.is_empty() {
+            let result = match self.actions.resolve(self.key_mode(), &self.pending_keys, timed_out) {
+                Resolution::Wait => return AppReturn::Continue,
+                Resolution::Run(action, len) => {
+                    self.pending_keys.drain(..len);
+                    self.run_action(action).await
                 },
-                // Scroll right
-                Action::ScrollRight => {
-                    self.dispatch(IoEvent::ScrollRight).await;
-                    AppReturn::Continue
+                Resolution::Unbound => {
+                    let key = self.pending_keys.remove(0);
+                    self.run_unbound_key(key).await
                 },
+            };
+            if result == AppReturn::Exit {
+                return result;
             }
+        }
+        AppReturn::Continue
+    }
+
+    /// A key no binding of the mode starts with. In write mode it types text,
+    /// or else runs the normal binding of that key alone, if any.
+    async fn run_unbound_key(&mut self, key: Key) -> AppReturn {
+        if let Some(value) = self.attempt_write(key) {
+            value
+        } else if let Some(action) = self.actions.find(Mode::Normal, &[key]).action {
+            self.run_action(action).await
         } else {
             warn!("No action accociated to {}", key);
             AppReturn::Continue
         }
     }
 
-    fn attempt_write(&mut self, key: Key) -> Option<AppReturn> {
-        if self.state.is_write_mode() {
-            let mut curr_text = self.state.get_text();
-            match key {
-                Key::Backspace => {
-                    curr_text.pop();
-                    self.state.replace_text(&curr_text);
-                    Some(AppReturn::Continue)
-                },
+    /// Run an action, picked by its key or from the palette
+    pub async fn run_action(&mut self, action: Action) -> AppReturn {
+        debug!("Run action [{:?}]", action);
+        match action {
+            // Quit, unless that would lose unsaved changes
+            Action::Quit => {
+                let file_paths = self.modified_file_paths();
+                if file_paths.is_empty() {
+                    AppReturn::Exit
+                } else {
+                    warn!("Unsaved changes in {}", file_paths.join(", "));
+                    self.show_prompt(Prompt::new(PromptKind::QuitWithUnsavedChanges { file_paths }));
+                    AppReturn::Continue
+                }
+            },
+            // Write o clock
+            Action::BeginWriteMode => {
+                self.dispatch(IoEvent::ToggleWriteMode(true)).await;
+                AppReturn::Continue
+            }
+            // No more writing
+            Action::EndWriteMode => {
+                self.dispatch(IoEvent::ToggleWriteMode(false)).await;
+                AppReturn::Continue
+            },
+            // Open file
+            Action::OpenFile => {
+                self.dispatch(IoEvent::OpenFile).await;
+                AppReturn::Continue
+            },
+            // Save file
+            Action::SaveFile => {
+                self.dispatch(IoEvent::SaveFile).await;
+                AppReturn::Continue
+            },
+            // Next file
+            Action::NextFile => {
+                self.dispatch(IoEvent::NextFile).await;
+                AppReturn::Continue
+            },
+            // Previous file
+            Action::PreviousFile => {
+                self.dispatch(IoEvent::PreviousFile).await;
+                AppReturn::Continue
+            },
+            // Close file
+            Action::CloseFile => {
+                let files_data = self.open_files_data_mut();
+                let index = files_data.get_currently_selected_file_index();
+                if files_data.is_modified(index) {
+                    let file_path = files_data.get_currently_selected_file_path().unwrap_or_default().to_owned();
+                    self.show_prompt(Prompt::new(PromptKind::CloseWithUnsavedChanges { file_path }));
+                } else {
+                    self.dispatch(IoEvent::CloseFile).await;
+                }
+                AppReturn::Continue
+            },
+            // Scroll down
+            Action::ScrollDown => {
+                self.dispatch(IoEvent::ScrollDown).await;
+                AppReturn::Continue
+            },
+            // Scroll up
+            Action::ScrollUp => {
+                self.dispatch(IoEvent::ScrollUp).await;
+                AppReturn::Continue
+            },
+            // Scroll left
+            Action::ScrollLeft => {
+                self.dispatch(IoEvent::ScrollLeft).await;
+                AppReturn::Continue
+            },
+            // Scroll right
+            Action::ScrollRight => {
+                self.dispatch(IoEvent::ScrollRight).await;
+                AppReturn::Continue
+            },
+            // Open the command line
+            Action::EnterCommand => {
+                self.command_line = Some(String::new());
+                self.messages.dismiss();
+                AppReturn::Continue
+            },
+            // Reorder tabs
+            Action::MoveTabLeft => {
+                self.dispatch(IoEvent::MoveTabLeft).await;
+                AppReturn::Continue
+            },
+            Action::MoveTabRight => {
+                self.dispatch(IoEvent::MoveTabRight).await;
+                AppReturn::Continue
+            },
+            // Windows
+            Action::SplitWindow => self.window_action(|windows| windows.split(SplitDirection::Horizontal)),
+            Action::SplitWindowVertically => self.window_action(|windows| windows.split(SplitDirection::Vertical)),
+            Action::CloseWindow => self.window_action(Windows::close),
+            Action::GrowWindow => self.window_action(|windows| windows.resize(WINDOW_RESIZE_STEP)),
+            Action::ShrinkWindow => self.window_action(|windows| windows.resize(-WINDOW_RESIZE_STEP)),
+            Action::FocusWindowLeft => self.window_action(|windows| windows.focus(FocusDirection::Left)),
+            Action::FocusWindowDown => self.window_action(|windows| windows.focus(FocusDirection::Down)),
+            Action::FocusWindowUp => self.window_action(|windows| windows.focus(FocusDirection::Up)),
+            Action::FocusWindowRight => self.window_action(|windows| windows.focus(FocusDirection::Right)),
+            // Help & panels
+            Action::ShowHelp => {
+                self.help_overlay = Some(HelpOverlay::default());
+                AppReturn::Continue
+            },
+            Action::ToggleHelpPanel => self.panel_action(|panels| panels.help.toggle()),
+            Action::ToggleLogPanel => self.panel_action(|panels| panels.logs.toggle()),
+            Action::ShrinkHelpPanel => self.panel_action(|panels| panels.resize_help(-HELP_RESIZE_STEP)),
+            Action::GrowHelpPanel => self.panel_action(|panels| panels.resize_help(HELP_RESIZE_STEP)),
+            Action::ShrinkLogPanel => self.panel_action(|panels| panels.resize_logs(-LOGS_RESIZE_STEP)),
+            Action::GrowLogPanel => self.panel_action(|panels| panels.resize_logs(LOGS_RESIZE_STEP)),
+            // Jump between brackets
+            Action::JumpToMatchingBracket => {
+                if let Err(err) = self.state.jump_to_matching_bracket() {
+                    self.notify(Severity::Warning, err);
+                }
+                AppReturn::Continue
+            },
+            // Command palette
+            Action::CommandPalette => {
+                self.palette = Some(Palette::new(&self.actions, self.key_mode(), &self.user_commands));
+                AppReturn::Continue
+            },
+        }
+    }
 
-                Key::Enter => {
-                    curr_text.push('\n');
-                    self.state.replace_text(&curr_text);
-                    Some(AppReturn::Continue)
-                },
+    /// Periodic housekeeping, run on every tick of the UI loop
+    pub async fn on_tick(&mut self) -> AppReturn {
+        let place = self.cursor_place();
+        if place != self.cursor_seen {
+            self.cursor_seen = place;
+            self.cursor_seen_at = Instant::now();
+        } else if self.cursor_seen_at.elapsed() >= WORD_HIGHLIGHT_DELAY
+            && self.highlighted_word.as_ref().map(|(seen, _)| *seen) != Some(place)
+        {
+            self.highlighted_word = self.state.get_word_under_cursor().map(|word| (place, word));
+        }
+        if !self.pending_keys.is_empty()
+            && self.pending_since.elapsed() >= self.settings.key_timeout
+            && self.resolve_pending_keys(true).await == AppReturn::Exit
+        {
+            return AppReturn::Exit;
+        }
+        if self.last_journal.elapsed() >= SWAP_JOURNAL_INTERVAL {
+            self.last_journal = Instant::now();
+            if let AppState::Initialized { files_data, .. } = &self.state {
+                if files_data.needs_journal() {
+                    self.dispatch_background(IoEvent::JournalSwapFiles).await;
+                }
+            }
+        }
+        if self.last_disk_check.elapsed() >= DISK_CHECK_INTERVAL && self.state.is_initialized() {
+            self.last_disk_check = Instant::now();
+            self.dispatch_background(IoEvent::CheckExternalChanges).await;
+        }
+        AppReturn::Continue
+    }
 
-                Key::Space => {
-                    curr_text.push(' ');
-                    self.state.replace_text(&curr_text);
-                    Some(AppReturn::Continue)
+    /// While a prompt is displayed, keys pick one of its answers
+    async fn attempt_answer(&mut self, key: Key) -> Option<AppReturn> {
+        let prompt = self.prompts.front_mut()?;
+        if let Some(answer) = prompt.find(key) {
+            debug!("Answer prompt [{:?}]", answer);
+            match answer {
+                PromptAnswer::QuitWithoutSaving => return Some(AppReturn::Exit),
+                PromptAnswer::Cancel => {
+                    self.prompts.pop_front();
                 },
+                _ => self.dispatch(IoEvent::AnswerPrompt(answer)).await,
+            }
+        } else {
+            match key.unmodified() {
+                Some(KeyCode::Down) => prompt.scroll_diff(1),
+                Some(KeyCode::Up) => prompt.scroll_diff(-1),
+                _ => warn!("No answer accociated to {}", key),
+            }
+        }
+        Some(AppReturn::Continue)
+    }
+
+    /// While the help is shown full-screen, keys search and scroll it
+    fn attempt_help_overlay(&mut self, key: Key) -> Option<AppReturn> {
+        let overlay = self.help_overlay.as_mut()?;
+        // A line per entry, and one heading each section
+        let sections = help::search(help::sections(&self.actions), &overlay.query);
+        let line_count = sections.iter().map(|section| section.entries.len() + 1).sum();
+        match key.unmodified() {
+            Some(KeyCode::Esc | KeyCode::F(1)) => self.help_overlay = None,
+            Some(KeyCode::Up) => overlay.scroll(-1, line_count),
+            Some(KeyCode::Down) => overlay.scroll(1, line_count),
+            Some(KeyCode::PageUp) => overlay.scroll(-HELP_PAGE, line_count),
+            Some(KeyCode::PageDown) => overlay.scroll(HELP_PAGE, line_count),
+            Some(KeyCode::Backspace) => overlay.pop(),
+            Some(KeyCode::Space) => overlay.push(' '),
+            Some(KeyCode::Char(c)) => overlay.push(c),
+            _ => {},
+        }
+        Some(AppReturn::Continue)
+    }
+
+    /// While the history of messages is shown, keys scroll it, newest first
+    fn attempt_messages_view(&mut self, key: Key) -> Option<AppReturn> {
+        let view = self.messages_view.as_mut()?;
+        let line_count = self.messages.history().len();
+        match key.unmodified() {
+            Some(KeyCode::Esc | KeyCode::Char('q')) => self.messages_view = None,
+            Some(KeyCode::Up) => view.scroll(-1, line_count),
+            Some(KeyCode::Down) => view.scroll(1, line_count),
+            Some(KeyCode::PageUp) => view.scroll(-HELP_PAGE, line_count),
+            Some(KeyCode::PageDown) => view.scroll(HELP_PAGE, line_count),
+            _ => {},
+        }
+        Some(AppReturn::Continue)
+    }
 
-                Key::Char(key_char) => {
-                    curr_text.push(key_char);
-                    self.state.replace_text(&curr_text);
-                    Some(AppReturn::Continue)
+    /// While the palette is open, keys narrow it down and pick an entry, which
+    /// runs like any action. Ex commands open in the command line instead, to
+    /// be given their arguments, while user-defined ones run as they are.
+    async fn attempt_palette(&mut self, key: Key) -> Option<AppReturn> {
+        let palette = self.palette.as_mut()?;
+        // The keys opening the palette close it again
+        let opening_keys = self.actions.keys_typed_in(palette.mode(), Action::CommandPalette);
+        let bound = opening_keys.iter().any(|keys| **keys == [key]);
+        match (key.modifiers, key.code) {
+            _ if bound => self.palette = None,
+            (Modifiers::NONE, KeyCode::Esc) => self.palette = None,
+            (Modifiers::NONE, KeyCode::Enter) => match self.palette.take().and_then(|palette| palette.chosen()) {
+                Some(PaletteItem::Action(action)) => return Some(self.run_action(action).await),
+                Some(PaletteItem::Command(usage)) => {
+                    self.command_line = Some(format!("{} ", PaletteItem::command_name(usage)));
+                    self.messages.dismiss();
+                },
+                Some(PaletteItem::UserCommand(command)) => {
+                    debug!("Run command [{}]", command);
+                    self.dispatch(IoEvent::RunCommand(command)).await;
                 },
+                None => self.notify(Severity::Warning, "No entry of the palette matches"),
+            },
+            (Modifiers::NONE, KeyCode::Up) | (Modifiers::CTRL, KeyCode::Char('k')) => palette.select(-1),
+            (Modifiers::NONE, KeyCode::Down | KeyCode::Tab) | (Modifiers::CTRL, KeyCode::Char('j')) => {
+                palette.select(1)
+            },
+            (Modifiers::NONE, KeyCode::Backspace) => palette.pop(),
+            (Modifiers::NONE, KeyCode::Space) => palette.push(' '),
+            (Modifiers::NONE, KeyCode::Char(c)) => palette.push(c),
+            _ => {},
+        }
+        Some(AppReturn::Continue)
+    }
+
+    /// While the command line is open, keys edit the command until it is run
+    async fn attempt_command_line(&mut self, key: Key) -> Option<AppReturn> {
+        let command_line = self.command_line.as_mut()?;
+        match key.unmodified() {
+            Some(KeyCode::Esc) => self.command_line = None,
+            Some(KeyCode::Enter) => {
+                let line = self.command_line.take().unwrap_or_default();
+                let command = match self.user_commands.iter().find(|user_command| user_command.name == line.trim()) {
+                    Some(user_command) => Ok(user_command.command.clone()),
+                    None => Command::parse(&line),
+                };
+                match command {
+                    Ok(command) => {
+                        debug!("Run command [{}]", command);
+                        self.dispatch(IoEvent::RunCommand(command)).await;
+                    },
+                    Err(err) => self.notify(Severity::Error, err),
+                }
+            },
+            Some(KeyCode::Backspace) if command_line.is_empty() => self.command_line = None,
+            Some(KeyCode::Backspace) => {
+                command_line.pop();
+            },
+            Some(KeyCode::Space) => command_line.push(' '),
+            Some(KeyCode::Char(c)) => command_line.push(c),
+            _ => {},
+        }
+        Some(AppReturn::Continue)
+    }
+
+    /// In the hex view, typing overwrites the hex digits under the cursor
+    fn attempt_write_hex(&mut self, key: Key) -> Option<AppReturn> {
+        let files_data = self.open_files_data_mut();
+        match key.unmodified() {
+            Some(KeyCode::Char(c)) => {
+                if let Err(err) = files_data.overwrite_nibble(c) {
+                    self.notify(Severity::Warning, err);
+                }
+            },
+            Some(KeyCode::Backspace) => {
+                if let Some(hex_buffer) = files_data.get_currently_selected_hex_buffer_mut() {
+                    hex_buffer.back_nibble();
+                }
+            },
+            Some(KeyCode::Enter | KeyCode::Space) => {
+                self.notify(Severity::Warning, "Only hex digits can be typed in the hex view")
+            },
+            _ => return None,
+        }
+        Some(AppReturn::Continue)
+    }
 
-                _ => None,
+    fn attempt_write(&mut self, key: Key) -> Option<AppReturn> {
+        if self.state.is_write_mode() && self.state.is_read_only() && key.types_text() {
+            self.notify(Severity::Warning, "This file is opened read-only");
+            Some(AppReturn::Continue)
+        } else if self.state.is_write_mode() && self.state.is_hex_view() {
+            self.attempt_write_hex(key)
+        } else if self.state.is_write_mode() {
+            match key.unmodified() {
+                Some(KeyCode::Backspace) => self.state.delete_char_before(),
+                Some(KeyCode::Enter) => self.state.insert_char('\n'),
+                Some(KeyCode::Space) => self.state.insert_char(' '),
+                Some(KeyCode::Char(key_char)) => self.state.insert_char(key_char),
+                _ => return None,
             }
+            Some(AppReturn::Continue)
         } else {
             None
         }
     }
 
+    /// Change the windows, keeping the focused one in sync with the selected file
+    fn window_action(&mut self, action: impl FnOnce(&mut Windows) -> Result<(), String>) -> AppReturn {
+        self.store_focused_window();
+        if let Err(err) = action(&mut self.windows) {
+            self.notify(Severity::Warning, format!("🪟 {}", err));
+        }
+        self.load_focused_window();
+        AppReturn::Continue
+    }
+
+    /// Index of the open file shown in the window at `index`, with the view it
+    /// is shown through. A window whose file was closed shows the selected
+    /// file instead.
+    pub fn window_file(&self, index: usize) -> Option<(usize, ViewState)> {
+        let files_data = match &self.state {
+            AppState::Initialized { files_data, .. } => files_data,
+            AppState::Init => return None,
+        };
+        let selected = files_data.get_currently_selected_file_index();
+        match files_data.find_file_by_id(self.windows.get(index).file_id) {
+            Some(file_index) if index != self.windows.focused() => Some((file_index, self.windows.get(index).view)),
+            _ => Some((selected, files_data.get_view(selected))),
+        }
+    }
+
+    /// Keep `view` as the view of the window at `index`, e.g. once it was
+    /// fitted to the window's area
+    pub fn set_window_view(&mut self, index: usize, view: ViewState) {
+        let focused = index == self.windows.focused();
+        if let AppState::Initialized { files_data, .. } = &mut self.state {
+            if focused {
+                files_data.set_view(files_data.get_currently_selected_file_index(), view);
+            } else if files_data.find_file_by_id(self.windows.get(index).file_id).is_some() {
+                self.windows.get_mut(index).view = view;
+            }
+        }
+    }
+
+    /// Remember the file and view of the focused window, which are kept live
+    /// in the open files data while it has the focus
+    fn store_focused_window(&mut self) {
+        if let AppState::Initialized { files_data, .. } = &self.state {
+            let index = files_data.get_currently_selected_file_index();
+            if let Some(file_id) = files_data.get_file_id(index) {
+                let window = self.windows.get_mut(self.windows.focused());
+                window.file_id = file_id;
+                window.view = files_data.get_view(index);
+            }
+        }
+    }
+
+    /// Select the file of the focused window, with its view. A window whose
+    /// file was closed shows the selected file instead.
+    fn load_focused_window(&mut self) {
+        if let AppState::Initialized { files_data, .. } = &mut self.state {
+            let window = self.windows.get(self.windows.focused());
+            if let Some(index) = files_data.find_file_by_id(window.file_id) {
+                files_data.select_file(index);
+                files_data.set_view(index, window.view);
+            }
+        }
+    }
+
+    /// Change the panels, which show again once there is room for them
+    fn panel_action(&mut self, action: impl FnOnce(&mut Panels)) -> AppReturn {
+        action(&mut self.panels);
+        AppReturn::Continue
+    }
+
+    /// Define a command, in place of any of the same name
+    pub fn define_command(&mut self, user_command: UserCommand) {
+        self.user_commands.retain(|defined| defined.name != user_command.name);
+        self.user_commands.push(user_command);
+    }
+
+    pub fn panels(&self) -> &Panels {
+        &self.panels
+    }
+
+    pub fn set_panels(&mut self, panels: Panels) {
+        self.panels = panels;
+    }
+
+    fn cursor_place(&self) -> CursorPlace {
+        (self.state.get_source().map(|(file_id, _, _)| file_id), self.state.get_cursor())
+    }
+
+    /// Word to highlight the other occurrences of, as long as the cursor did
+    /// not move off it since
+    pub fn highlighted_word(&self) -> Option<&str> {
+        self.highlighted_word
+            .as_ref()
+            .filter(|(place, _)| *place == self.cursor_place())
+            .map(|(_, word)| word.as_str())
+    }
+
+    /// Tell the user something on the message line, keeping it in the
+    /// history of messages and in the logs
+    pub fn notify(&mut self, severity: Severity, text: impl Into<String>) {
+        let text = text.into();
+        match severity {
+            Severity::Info => info!("{}", text),
+            Severity::Warning => warn!("{}", text),
+            Severity::Error => error!("{}", text),
+        }
+        self.messages.push(severity, text, Instant::now());
+    }
+
+    pub fn messages(&self) -> &Messages {
+        &self.messages
+    }
+
+    pub fn messages_view(&self) -> Option<&MessagesView> {
+        self.messages_view.as_ref()
+    }
+
+    pub fn show_messages(&mut self) {
+        self.messages_view = Some(MessagesView::default());
+    }
+
+    pub fn palette(&self) -> Option<&Palette> {
+        self.palette.as_ref()
+    }
+
+    pub fn help_overlay(&self) -> Option<&HelpOverlay> {
+        self.help_overlay.as_ref()
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Switch to `theme`, highlighting files again in its styles
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.highlighter.set_styles(&theme.syntax);
+        self.theme = theme;
+    }
+
+    pub fn windows(&self) -> &Windows {
+        &self.windows
+    }
+
+    pub fn windows_mut(&mut self) -> &mut Windows {
+        &mut self.windows
+    }
+
     /// Send a network event to the IO thread
     pub async fn dispatch(&mut self, action: IoEvent) {
         // `is_loading` will be set to false again after the async action has finished in io/handler.rs
@@ -158,9 +707,26 @@ impl App {
         };
     }
 
+    /// Send an IO event for housekeeping the user should not wait on, so
+    /// without flagging the app as loading
+    async fn dispatch_background(&mut self, action: IoEvent) {
+        if let Err(e) = self.io_tx.send(action).await {
+            error!("Error from dispatch {}", e);
+        };
+    }
+
     pub fn actions(&self) -> &Actions {
         &self.actions
     }
+
+    /// Keys typed so far of a binding of several
+    pub fn pending_keys(&self) -> &[Key] {
+        &self.pending_keys
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.actions.set_keymap(keymap);
+    }
     
     pub fn state(&self) -> &AppState {
         &self.state
@@ -174,13 +740,93 @@ impl App {
         }
     }
 
+    /// The prompt currently displayed, if any
+    pub fn prompt(&self) -> Option<&Prompt> {
+        self.prompts.front()
+    }
+
+    pub fn show_prompt(&mut self, prompt: Prompt) {
+        self.prompts.push_back(prompt);
+    }
+
+    pub fn take_prompt(&mut self) -> Option<Prompt> {
+        self.prompts.pop_front()
+    }
+
+    /// Put a prompt back in front, e.g. after showing its diff
+    pub fn restore_prompt(&mut self, prompt: Prompt) {
+        self.prompts.push_front(prompt);
+    }
+
+    /// Paths of the open files with unsaved changes
+    pub fn modified_file_paths(&self) -> Vec<String> {
+        match &self.state {
+            AppState::Initialized { files_data, .. } => files_data
+                .get_modified_file_indices()
+                .into_iter()
+                .map(|index| {
+                    files_data
+                        .get_open_file_paths()
+                        .get(index)
+                        .cloned()
+                        .unwrap_or_else(|| "untitled".to_owned())
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Command being typed, while the command line is open
+    pub fn command_line(&self) -> Option<&String> {
+        self.command_line.as_ref()
+    }
+
+    pub fn last_search(&self) -> Option<&String> {
+        self.last_search.as_ref()
+    }
+
+    pub fn set_last_search(&mut self, pattern: &str) {
+        self.last_search = Some(pattern.to_owned());
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// Apply `assignment` to an option other than the theme, telling its new
+    /// value
+    pub fn set_option(&mut self, option: EditorOption, assignment: &Assignment) -> Result<String, String> {
+        options::set(&mut self.settings, &mut self.panels, option, assignment)?;
+        Ok(format!("{}={}", option, self.option_value(option)))
+    }
+
+    pub fn option_value(&self, option: EditorOption) -> String {
+        options::value(&self.settings, &self.panels, option).unwrap_or_else(|| self.theme.name.clone())
+    }
+
+    pub fn state_mut(&mut self) -> &mut AppState {
+        &mut self.state
+    }
+
+    pub fn request_exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    pub fn is_exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
     pub fn is_loading(&self) -> bool {
         self.is_loading
     }
 
     pub fn initialized(&mut self) {
         // Update contextual actions
-        self.actions = Action::values().into();
+        self.actions = Actions::new(Action::values(), self.actions.keymap().clone());
         self.state = AppState::initialized()
     }
 
@@ -200,7 +846,62 @@ impl App {
         self.state.scroll_vertical(delta)
     }
 
-    pub fn reset_scroll(&mut self) {
-        self.state.reset_scroll();
+    /// The open files with their views, the windows showing them and the
+    /// panels, as a session to restore later
+    pub fn to_session(&self) -> Session {
+        let files_data = match &self.state {
+            AppState::Initialized { files_data, .. } => files_data,
+            AppState::Init => return Session::default(),
+        };
+        let mut session = files_data.to_session();
+        session.windows = (0..self.windows.len())
+            .filter_map(|index| self.window_file(index))
+            .map(|(file_index, view)| SessionWindow {
+                path: files_data.get_open_file_paths().get(file_index).cloned().unwrap_or_default(),
+                scroll_offset: view.scroll_offset,
+                cursor: view.cursor,
+            })
+            .collect();
+        session.splits = Some(self.windows.splits());
+        session.panels = Some(self.panels);
+        session
+    }
+
+    /// Show `windows`, opened from a session, selecting the file of the focused one
+    pub fn set_windows(&mut self, windows: Windows) {
+        self.windows = windows;
+        self.load_focused_window();
+    }
+
+    /// Update the session of the working directory on exit, if it has one
+    pub fn update_directory_session(&self) {
+        if !matches!(self.state, AppState::Initialized { .. }) {
+            return;
+        }
+        if let Some(path) = session::directory_session_path().filter(|path| path.exists()) {
+            if let Err(err) = session::write(&path, &self.to_session()) {
+                error!("Failed to update session {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    /// Remember the layout of the panels for the next session
+    pub fn save_layout(&self) {
+        if let Some(path) = layout::layout_path() {
+            if let Err(err) = layout::write(&path, &self.panels) {
+                error!("Failed to save layout {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    /// Remove the swap files written by this session, on a clean exit
+    pub fn remove_swap_files(&self) {
+        if let AppState::Initialized { files_data, .. } = &self.state {
+            for file_path in files_data.owned_swap_file_paths() {
+                if let Err(err) = swap::remove(&file_path) {
+                    error!("Failed to remove swap file of {}: {}", file_path, err);
+                }
+            }
+        }
     }
 }
@@ -1,10 +1,102 @@
 use std::path::Path;
 
-#[derive(Clone)]
+use super::diff;
+use super::editing::{self, ViewState};
+use super::hex_buffer::{self, HexBuffer};
+use super::large_file::{LargeFile, LARGE_FILE_THRESHOLD};
+use super::paths;
+use crate::io::disk_state::{hash_bytes, DiskState};
+use crate::io::session::{Session, SessionFile};
+use crate::io::swap::SwapJob;
+
+/// How the content of an open file is held
+#[derive(Clone, Default)]
+enum FileMode {
+    /// Fully loaded in `file_contents`
+    #[default]
+    Text,
+    /// Paged in from disk on demand, read-only. Its `file_contents` entry stays empty.
+    Large(LargeFile),
+    /// Binary content edited in the hex view. Its `file_contents` entry stays
+    /// empty, and it is journaled to its swap file as a hex dump.
+    Hex(HexBuffer),
+}
+
+/// Bookkeeping kept alongside the content of every open file
+#[derive(Clone, Default)]
+struct FileState {
+    /// Hash of the content as last read from or written to disk
+    saved_hash: u64,
+    /// Length in bytes of the content as last read or written
+    saved_len: usize,
+    /// Hash of every line of the content as last read or written, to tell
+    /// the lines changed since
+    saved_lines: Vec<u64>,
+    /// Lines changed since the last read or write, as of a revision
+    changed_lines: Option<(u64, Vec<usize>)>,
+    /// Lines containing a pattern, as of a revision
+    matching_lines: Option<(u64, String, Vec<usize>)>,
+    /// Lines with a diagnostic, as last given by whatever checks the file
+    diagnostic_lines: Vec<usize>,
+    /// Whether the content differs from what was last read or written, kept
+    /// up to date on every edit so that undoing changes makes the file clean
+    modified: bool,
+    /// Bumped on every edit of the content
+    revision: u64,
+    /// Revision whose swap file work was last scheduled
+    journaled_revision: u64,
+    /// Whether the swap file next to the file was written by this session
+    owns_swap: bool,
+    /// The file on disk as of the last load or save, `None` if it is not on disk
+    disk_state: Option<DiskState>,
+    mode: FileMode,
+    /// Scroll offset and cursor, remembered while other files are selected
+    view: ViewState,
+    /// Identifies the file for as long as it is open, whatever its index
+    id: u64,
+}
+
+impl FileState {
+    fn new(content: &[u8]) -> Self {
+        Self {
+            saved_hash: hash_bytes(content),
+            saved_len: content.len(),
+            saved_lines: line_hashes(content),
+            ..Self::default()
+        }
+    }
+
+    /// Content of another length cannot be the saved one, so only edits
+    /// leaving the length as saved hash the content again
+    fn edited(&mut self, content: &[u8]) {
+        self.revision += 1;
+        self.modified = content.len() != self.saved_len || hash_bytes(content) != self.saved_hash;
+    }
+
+    fn saved(&mut self, content: &[u8]) {
+        self.saved_hash = hash_bytes(content);
+        self.saved_len = content.len();
+        self.saved_lines = line_hashes(content);
+        self.changed_lines = None;
+        self.modified = false;
+    }
+}
+
+/// Hash of every line of text content, none for binary content
+fn line_hashes(content: &[u8]) -> Vec<u64> {
+    std::str::from_utf8(content)
+        .map(|text| text.lines().map(|line| hash_bytes(line.as_bytes())).collect())
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Default)]
 pub struct OpenFilesData {
     file_paths: Vec<String>,
     file_contents: Vec<String>,
+    file_states: Vec<FileState>,
     currently_selected_file_index: usize,
+    /// Identifier of the last file opened
+    last_file_id: u64,
 }
 
 impl OpenFilesData {
@@ -12,37 +104,81 @@ impl OpenFilesData {
         Self {
             file_paths: vec![],
             file_contents: vec![],
+            file_states: vec![],
             currently_selected_file_index: 0,
+            last_file_id: 0,
         }
     }
 
     pub fn open_file(&mut self, file_path: &str) -> Result<(), String> {
-        if self.file_paths.contains(&file_path.to_owned()) {
-            return Err(format!("File {} already opened", file_path));
+        let file_path = &paths::canonical_path(file_path);
+        if self.file_paths.contains(file_path) {
+            Err(format!("File {} already opened", file_path))
+        } else if !Path::new(file_path).exists() {
+            Err(format!("File {} does not exist", file_path))
+        } else if let Some(len) = std::fs::metadata(file_path)
+            .ok()
+            .map(|metadata| metadata.len())
+            .filter(|len| *len > LARGE_FILE_THRESHOLD)
+        {
+            // Too big to be read whole, so external changes are told by the metadata only
+            self.file_paths.push(file_path.to_owned());
+            self.file_contents.push(String::new());
+            self.last_file_id += 1;
+            self.file_states.push(FileState {
+                mode: FileMode::Large(LargeFile::open(file_path, len)),
+                disk_state: std::fs::metadata(file_path).ok().map(|metadata| DiskState::from_metadata(&metadata)),
+                id: self.last_file_id,
+                ..FileState::default()
+            });
+            self.currently_selected_file_index = self.file_paths.len() - 1;
+            Ok(())
         } else {
-            if !Path::new(file_path).exists() {
-                Err(format!("File {} does not exist", file_path))
-            } else {
-                let file_content = std::fs::read_to_string(file_path);
-                if let Ok(file_content) = file_content {
-                    self.file_paths.push(file_path.to_owned());
-                    self.file_contents.push(file_content);
-                    self.currently_selected_file_index = self.file_paths.len() - 1;
-                    Ok(())
+            let file_bytes = std::fs::read(file_path);
+            if let Ok(file_bytes) = file_bytes {
+                let mut file_state = FileState::new(&file_bytes);
+                self.last_file_id += 1;
+                file_state.id = self.last_file_id;
+                file_state.disk_state = std::fs::metadata(file_path)
+                    .ok()
+                    .map(|metadata| DiskState::new(&metadata, &file_bytes));
+                let file_content = if hex_buffer::is_binary(&file_bytes) {
+                    file_state.mode = FileMode::Hex(HexBuffer::new(file_bytes));
+                    String::new()
                 } else {
-                    Err(format!("Error while reading file {}", file_path))
-                }
+                    String::from_utf8(file_bytes).unwrap_or_default()
+                };
+                self.file_paths.push(file_path.to_owned());
+                self.file_states.push(file_state);
+                self.file_contents.push(file_content);
+                self.currently_selected_file_index = self.file_paths.len() - 1;
+                Ok(())
+            } else {
+                Err(format!("Error while reading file {}", file_path))
             }
         }
     }
 
     pub fn close_file(&mut self) -> Result<(), String> {
-        if self.file_paths.is_empty() {
-            return Err("No file to close".to_owned());
+        self.close_file_at(self.currently_selected_file_index)
+    }
+
+    /// Close the file at `index`. The selection moves to the previous file
+    /// when it was the one closed, and stays on its file otherwise.
+    pub fn close_file_at(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.file_paths.len() {
+            Err("No file to close".to_owned())
         } else {
-            self.file_paths.remove(self.currently_selected_file_index);
-            self.file_contents.remove(self.currently_selected_file_index);
-            self.select_previous_file();
+            self.file_paths.remove(index);
+            if index < self.file_contents.len() {
+                self.file_contents.remove(index);
+                self.file_states.remove(index);
+            }
+            if index == self.currently_selected_file_index {
+                self.select_previous_file();
+            } else if index < self.currently_selected_file_index {
+                self.currently_selected_file_index -= 1;
+            }
             Ok(())
         }
     }
@@ -51,16 +187,152 @@ impl OpenFilesData {
         &self.file_paths
     }
 
+    /// Names of the open files, with as many parent directories as it takes
+    /// to tell them apart
     pub fn get_open_file_names(&self) -> Vec<String> {
-        self.file_paths.iter()
-            .map(|file_path| file_path.split("/").last().unwrap().to_owned())
-            .collect::<Vec<String>>()
+        paths::display_names(&self.file_paths)
     }
 
     pub fn get_open_file_contents(&self) -> &Vec<String> {
         &self.file_contents
     }
 
+    /// The file at `index`, if it is opened in large-file mode
+    pub fn get_large_file(&self, index: usize) -> Option<&LargeFile> {
+        match self.file_states.get(index).map(|file_state| &file_state.mode) {
+            Some(FileMode::Large(large_file)) => Some(large_file),
+            _ => None,
+        }
+    }
+
+    pub fn get_currently_selected_large_file(&self) -> Option<&LargeFile> {
+        self.get_large_file(self.currently_selected_file_index)
+    }
+
+    /// The file at `index`, if it is edited in the hex view
+    pub fn get_hex_buffer(&self, index: usize) -> Option<&HexBuffer> {
+        match self.file_states.get(index).map(|file_state| &file_state.mode) {
+            Some(FileMode::Hex(hex_buffer)) => Some(hex_buffer),
+            _ => None,
+        }
+    }
+
+    pub fn get_hex_buffer_mut(&mut self, index: usize) -> Option<&mut HexBuffer> {
+        match self.file_states.get_mut(index).map(|file_state| &mut file_state.mode) {
+            Some(FileMode::Hex(hex_buffer)) => Some(hex_buffer),
+            _ => None,
+        }
+    }
+
+    pub fn get_currently_selected_hex_buffer(&self) -> Option<&HexBuffer> {
+        self.get_hex_buffer(self.currently_selected_file_index)
+    }
+
+    pub fn get_currently_selected_hex_buffer_mut(&mut self) -> Option<&mut HexBuffer> {
+        self.get_hex_buffer_mut(self.currently_selected_file_index)
+    }
+
+    /// Overwrite the hex digit under the cursor of the currently selected file
+    pub fn overwrite_nibble(&mut self, digit: char) -> Result<(), String> {
+        let file_state = self
+            .file_states
+            .get_mut(self.currently_selected_file_index)
+            .ok_or_else(|| "No file to edit".to_owned())?;
+        // Taken out for a moment, so that the bytes are hashed where they are
+        // rather than copied on every keystroke
+        let mut mode = std::mem::take(&mut file_state.mode);
+        let result = match &mut mode {
+            FileMode::Hex(hex_buffer) => {
+                let result = hex_buffer.overwrite_nibble(digit);
+                if result.is_ok() {
+                    file_state.edited(hex_buffer.bytes());
+                }
+                result
+            },
+            _ => Err("Not in the hex view".to_owned()),
+        };
+        file_state.mode = mode;
+        result
+    }
+
+    /// Whether the currently selected file can not be edited
+    pub fn is_currently_selected_file_read_only(&self) -> bool {
+        self.get_currently_selected_large_file().is_some()
+    }
+
+    /// `CRLF` or `LF` for text files, `None` when not known without reading
+    /// the whole file or for binary files
+    pub fn get_currently_selected_line_ending(&self) -> Option<&'static str> {
+        match self.file_states.get(self.currently_selected_file_index).map(|file_state| &file_state.mode) {
+            Some(FileMode::Large(_)) | Some(FileMode::Hex(_)) => None,
+            _ => {
+                let content = self.file_contents.get(self.currently_selected_file_index)?;
+                Some(if content.contains("\r\n") { "CRLF" } else { "LF" })
+            },
+        }
+    }
+
+    /// Number of lines of the file at `index`
+    pub fn get_line_count(&self, index: usize) -> usize {
+        if let Some(hex_buffer) = self.get_hex_buffer(index) {
+            return hex_buffer.row_count();
+        }
+        match self.get_large_file(index) {
+            Some(large_file) => large_file.line_count(),
+            None => self.file_contents.get(index).map_or(0, |content| content.lines().count()),
+        }
+    }
+
+    pub fn get_currently_selected_line_count(&self) -> usize {
+        self.get_line_count(self.currently_selected_file_index)
+    }
+
+    /// Up to `count` lines of the file at `index` starting at line `start`,
+    /// without materializing any of the other lines
+    pub fn get_lines(&self, index: usize, start: usize, count: usize) -> Vec<String> {
+        match self.get_large_file(index) {
+            Some(large_file) => large_file.lines(start, count),
+            None => self
+                .file_contents
+                .get(index)
+                .map(|content| content.lines().skip(start).take(count).map(str::to_owned).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn get_currently_selected_lines(&self, start: usize, count: usize) -> Vec<String> {
+        self.get_lines(self.currently_selected_file_index, start, count)
+    }
+
+    /// Line `line` of the file at `index`, without replacing the viewport
+    /// cache of a large file
+    pub fn get_line(&self, index: usize, line: usize) -> Option<String> {
+        match self.get_large_file(index) {
+            Some(large_file) => large_file.line(line),
+            None => self.file_contents.get(index)?.lines().nth(line).map(str::to_owned),
+        }
+    }
+
+    pub fn get_currently_selected_line(&self, line: usize) -> Option<String> {
+        self.get_line(self.currently_selected_file_index, line)
+    }
+
+    /// Lines of the file at `index` from `line` on, going towards the first
+    /// line instead when not `forward`. They are only read as they are taken,
+    /// so that scans stopping early stay cheap.
+    pub fn get_lines_from(&self, index: usize, line: usize, forward: bool) -> Box<dyn Iterator<Item = String> + '_> {
+        if let Some(large_file) = self.get_large_file(index) {
+            return large_file.lines_from(line, forward);
+        }
+        let content = self.file_contents.get(index).map_or("", String::as_str);
+        if forward {
+            Box::new(content.lines().skip(line).map(str::to_owned))
+        } else {
+            let end = content.split_inclusive('\n').take(line + 1).map(str::len).sum();
+            Box::new(content[..end].lines().rev().map(str::to_owned))
+        }
+    }
+
     pub fn get_currently_selected_file_content(&self) -> String {
         if self.currently_selected_file_index < self.file_contents.len() {
             self.file_contents[self.currently_selected_file_index].clone()
@@ -70,30 +342,146 @@ impl OpenFilesData {
     }
 
     pub fn replace_currently_selected_file_content(&mut self, new_content: &str) {
-        if self.currently_selected_file_index < self.file_contents.len() {
-            self.file_contents[self.currently_selected_file_index] = new_content.to_owned();
-        } else {
-            self.file_contents.push(new_content.to_owned());
+        let index = self.ensure_currently_selected_content();
+        self.file_contents[index] = new_content.to_owned();
+        let file_state = &mut self.file_states[index];
+        file_state.edited(self.file_contents[index].as_bytes());
+        file_state.view.cursor = editing::clamp_cursor(&self.file_contents[index], file_state.view.cursor);
+    }
+
+    /// Insert `c` at the cursor of the currently selected file
+    pub fn insert_char(&mut self, c: char) {
+        let index = self.ensure_currently_selected_content();
+        let file_state = &mut self.file_states[index];
+        editing::insert_char(&mut self.file_contents[index], &mut file_state.view.cursor, c);
+        file_state.edited(self.file_contents[index].as_bytes());
+    }
+
+    /// Delete the character before the cursor of the currently selected file
+    pub fn delete_char_before(&mut self) {
+        let index = self.ensure_currently_selected_content();
+        let file_state = &mut self.file_states[index];
+        if editing::delete_char_before(&mut self.file_contents[index], &mut file_state.view.cursor) {
+            file_state.edited(self.file_contents[index].as_bytes());
         }
     }
 
-    pub fn get_currently_selected_file_path(&mut self) -> String {
-        if self.currently_selected_file_index < self.file_paths.len() {
-            self.file_paths[self.currently_selected_file_index].clone()
-        } else {
-            let random_file_name: String = names::Generator::default().next().unwrap();
-            self.file_paths.push(random_file_name.clone());
-            random_file_name
+    /// Index of the currently selected file, creating a brand new file with a
+    /// random name and nothing of it on disk yet when none is open. Only
+    /// editing or saving does this, never merely looking at the selected file.
+    pub fn ensure_currently_selected_content(&mut self) -> usize {
+        if self.currently_selected_file_index >= self.file_contents.len() {
+            if self.file_paths.len() == self.file_contents.len() {
+                let random_file_name: String = names::Generator::default().next().unwrap();
+                self.file_paths.push(random_file_name);
+            }
+            self.file_contents.push(String::new());
+            self.last_file_id += 1;
+            self.file_states.push(FileState {
+                id: self.last_file_id,
+                ..FileState::new(b"")
+            });
+            self.currently_selected_file_index = self.file_contents.len() - 1;
+        }
+        self.currently_selected_file_index
+    }
+
+    /// Content of the file at `index` as it is journaled: its text, or the hex
+    /// dump of binary content. `None` for large files, which are read-only.
+    pub fn get_journal_content(&self, index: usize) -> Option<String> {
+        match &self.file_states.get(index)?.mode {
+            FileMode::Text => self.file_contents.get(index).cloned(),
+            FileMode::Hex(hex_buffer) => Some(hex_buffer::dump(hex_buffer.bytes())),
+            FileMode::Large(_) => None,
+        }
+    }
+
+    /// `content` read from disk for the file at `index`, shown the way
+    /// `get_journal_content` shows the file's own to diff against it
+    pub fn render_disk_content(&self, index: usize, content: &[u8]) -> String {
+        match self.file_states.get(index).map(|file_state| &file_state.mode) {
+            Some(FileMode::Hex(_)) => hex_buffer::dump(content),
+            _ => String::from_utf8_lossy(content).into_owned(),
         }
     }
 
-    pub fn get_currently_selected_file_name(&mut self) -> String {
-        let path = self.get_currently_selected_file_path();
-        path.split("/").last().unwrap().to_owned()
+    /// Identifier, path and whole text of the file at `index` when it is held
+    /// in memory as text
+    pub fn get_source(&self, index: usize) -> Option<(u64, &str, &str)> {
+        let file_state = self.file_states.get(index)?;
+        match file_state.mode {
+            FileMode::Text => Some((file_state.id, self.file_paths.get(index)?, &self.file_contents[index])),
+            _ => None,
+        }
+    }
+
+    pub fn get_currently_selected_source(&self) -> Option<(u64, &str, &str)> {
+        self.get_source(self.currently_selected_file_index)
+    }
+
+    /// Identifiers of all open files
+    pub fn get_file_ids(&self) -> Vec<u64> {
+        self.file_states.iter().map(|file_state| file_state.id).collect()
+    }
+
+    /// Identifier of the file at `index`, which stays the same while it is open
+    pub fn get_file_id(&self, index: usize) -> Option<u64> {
+        self.file_states.get(index).map(|file_state| file_state.id)
+    }
+
+    /// Index of the open file with the given identifier
+    pub fn find_file_by_id(&self, id: u64) -> Option<usize> {
+        self.file_states.iter().position(|file_state| file_state.id == id)
+    }
+
+    /// Scroll offset and cursor of the file at `index`
+    pub fn get_view(&self, index: usize) -> ViewState {
+        self.file_states.get(index).map(|file_state| file_state.view).unwrap_or_default()
+    }
+
+    pub fn set_view(&mut self, index: usize, mut view: ViewState) {
+        if let Some(file_state) = self.file_states.get_mut(index) {
+            if let (FileMode::Text, Some(content)) = (&file_state.mode, self.file_contents.get(index)) {
+                view.cursor = editing::clamp_cursor(content, view.cursor);
+            }
+            file_state.view = view;
+        }
+    }
+
+    /// Number of lines the cursor of the currently selected file can be on,
+    /// including an empty last line after a trailing line break
+    pub fn get_currently_selected_cursor_line_count(&self) -> usize {
+        match self.get_currently_selected_large_file() {
+            Some(large_file) => large_file.line_count(),
+            None => self
+                .file_contents
+                .get(self.currently_selected_file_index)
+                .map_or(1, |content| editing::line_count(content)),
+        }
+    }
+
+    /// Number of characters of the given line of the currently selected file
+    pub fn get_currently_selected_line_len(&self, line: usize) -> usize {
+        match self.get_currently_selected_large_file() {
+            Some(large_file) => large_file.line(line).map_or(0, |line| line.chars().count()),
+            None => self
+                .file_contents
+                .get(self.currently_selected_file_index)
+                .map_or(0, |content| editing::line_len(content, line)),
+        }
+    }
+
+    /// Path of the currently selected file, `None` when no file is open
+    pub fn get_currently_selected_file_path(&self) -> Option<&str> {
+        self.file_paths.get(self.currently_selected_file_index).map(String::as_str)
+    }
+
+    pub fn get_currently_selected_file_name(&self) -> Option<String> {
+        self.get_open_file_names().into_iter().nth(self.currently_selected_file_index)
     }
 
     pub fn select_next_file(&mut self) {
-        if self.file_contents.len() > 0 {
+        if !self.file_contents.is_empty() {
             self.currently_selected_file_index = (self.currently_selected_file_index + 1) % self.file_contents.len();
         } else {
             self.currently_selected_file_index = 0;
@@ -101,7 +489,7 @@ impl OpenFilesData {
     }
 
     pub fn select_previous_file(&mut self) {
-        if self.file_contents.len() > 0 {
+        if !self.file_contents.is_empty() {
             self.currently_selected_file_index = (self.currently_selected_file_index + self.file_paths.len() - 1) % self.file_paths.len();
         } else {
             self.currently_selected_file_index = 0;
@@ -109,15 +497,353 @@ impl OpenFilesData {
     }
 
     pub fn save_file(&mut self) -> Result<(), String> {
-        let file_content = self.file_contents[self.currently_selected_file_index].clone();
-        if self.currently_selected_file_index < self.file_paths.len() {
-            let file_path = self.file_paths[self.currently_selected_file_index].clone();
-            std::fs::write(&file_path, file_content).map_err(|e| format!("Error while writing file {}: {}", file_path, e))
+        self.save_file_at(self.currently_selected_file_index)
+    }
+
+    pub fn save_file_at(&mut self, index: usize) -> Result<(), String> {
+        let file_content: Vec<u8> = match self.file_states.get(index).map(|file_state| &file_state.mode) {
+            Some(FileMode::Large(_)) => return Err("Large files are opened read-only".to_owned()),
+            Some(FileMode::Hex(hex_buffer)) => hex_buffer.bytes().to_vec(),
+            _ => self.file_contents[index].clone().into_bytes(),
+        };
+        let file_path = if index < self.file_paths.len() {
+            self.file_paths[index].clone()
         } else {
             let random_file_name: String = names::Generator::default().next().unwrap();
             // Take file path input from user
             self.file_paths.push(random_file_name.clone());
-            std::fs::write(&random_file_name, file_content).map_err(|e| format!("Error while writing file {}: {}", random_file_name, e))
+            random_file_name
+        };
+        std::fs::write(&file_path, &file_content).map_err(|e| format!("Error while writing file {}: {}", file_path, e))?;
+        // A brand new file only gets its identity once it is on disk
+        self.file_paths[index] = paths::canonical_path(&file_path);
+        let file_state = &mut self.file_states[index];
+        file_state.saved(&file_content);
+        file_state.journaled_revision = file_state.revision;
+        file_state.owns_swap = false;
+        file_state.disk_state = std::fs::metadata(&file_path)
+            .ok()
+            .map(|metadata| DiskState::new(&metadata, &file_content));
+        Ok(())
+    }
+
+    /// Lines of the file at `index` changed since it was last read or written,
+    /// worked out again only once it was edited since last time
+    pub fn get_changed_lines(&mut self, index: usize) -> Vec<usize> {
+        let (file_state, content) = match (self.file_states.get_mut(index), self.file_contents.get(index)) {
+            (Some(file_state), Some(content)) if matches!(file_state.mode, FileMode::Text) && file_state.modified => {
+                (file_state, content)
+            },
+            _ => return vec![],
+        };
+        match &file_state.changed_lines {
+            Some((revision, lines)) if *revision == file_state.revision => lines.clone(),
+            _ => {
+                let lines: Vec<u64> = content.lines().map(|line| hash_bytes(line.as_bytes())).collect();
+                let changed = diff::changed_lines(&file_state.saved_lines, &lines);
+                file_state.changed_lines = Some((file_state.revision, changed.clone()));
+                changed
+            },
+        }
+    }
+
+    /// Lines of the file at `index` containing `pattern`, as `:find` matches
+    /// them, worked out again only once it was edited or the pattern changed.
+    /// Large files are not searched through whole.
+    pub fn get_matching_lines(&mut self, index: usize, pattern: &str) -> Vec<usize> {
+        let (file_state, content) = match (self.file_states.get_mut(index), self.file_contents.get(index)) {
+            (Some(file_state), Some(content)) if matches!(file_state.mode, FileMode::Text) && !pattern.is_empty() => {
+                (file_state, content)
+            },
+            _ => return vec![],
+        };
+        match &file_state.matching_lines {
+            Some((revision, cached, lines)) if *revision == file_state.revision && cached == pattern => lines.clone(),
+            _ => {
+                let matching: Vec<usize> = content
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| line.contains(pattern))
+                    .map(|(index, _)| index)
+                    .collect();
+                file_state.matching_lines = Some((file_state.revision, pattern.to_owned(), matching.clone()));
+                matching
+            },
+        }
+    }
+
+    /// Lines of the file at `index` with a diagnostic, as last set
+    pub fn get_diagnostic_lines(&self, index: usize) -> &[usize] {
+        self.file_states.get(index).map_or(&[], |file_state| &file_state.diagnostic_lines)
+    }
+
+    /// Sets the lines of the file at `index` with a diagnostic, replacing
+    /// those set before
+    pub fn set_diagnostic_lines(&mut self, index: usize, lines: Vec<usize>) {
+        if let Some(file_state) = self.file_states.get_mut(index) {
+            file_state.diagnostic_lines = lines;
+        }
+    }
+
+    /// Whether the file at `index` has edits that are not saved to disk
+    pub fn is_modified(&self, index: usize) -> bool {
+        self.file_states.get(index).is_some_and(|file_state| file_state.modified)
+    }
+
+    /// Indices of the files with edits that are not saved to disk
+    pub fn get_modified_file_indices(&self) -> Vec<usize> {
+        (0..self.file_states.len()).filter(|index| self.is_modified(*index)).collect()
+    }
+
+    pub fn get_currently_selected_file_index(&self) -> usize {
+        self.currently_selected_file_index
+    }
+
+    /// What the file at `index` looked like on disk when it was loaded or saved
+    pub fn get_disk_state(&self, index: usize) -> Option<&DiskState> {
+        self.file_states.get(index).and_then(|file_state| file_state.disk_state.as_ref())
+    }
+
+    /// Paths of the files on disk, with what they looked like when loaded or saved
+    pub fn get_disk_states(&self) -> Vec<(String, DiskState)> {
+        self.file_states
+            .iter()
+            .zip(self.file_paths.iter())
+            .filter_map(|(file_state, file_path)| {
+                file_state.disk_state.clone().map(|disk_state| (file_path.clone(), disk_state))
+            })
+            .collect()
+    }
+
+    /// Acknowledge the current state of the file at `index` on disk, e.g. once
+    /// the user chose to keep their own version over an external change
+    pub fn set_disk_state(&mut self, index: usize, disk_state: Option<DiskState>) {
+        if let Some(file_state) = self.file_states.get_mut(index) {
+            file_state.disk_state = disk_state;
         }
     }
-}
\ No newline at end of file
+
+    /// Replace the content of the file at `index` with what is now on disk
+    pub fn reload_file(&mut self, index: usize, content: Vec<u8>, disk_state: DiskState) {
+        if let (Some(file_content), Some(file_state)) =
+            (self.file_contents.get_mut(index), self.file_states.get_mut(index))
+        {
+            file_state.saved(&content);
+            file_state.revision += 1;
+            file_state.disk_state = Some(disk_state);
+            match &mut file_state.mode {
+                FileMode::Hex(hex_buffer) => hex_buffer.set_bytes(content),
+                _ => {
+                    *file_content = String::from_utf8_lossy(&content).into_owned();
+                    file_state.view.cursor = editing::clamp_cursor(file_content, file_state.view.cursor);
+                },
+            }
+        }
+    }
+
+    /// Open the large file at `index` again, indexing its lines anew after it
+    /// was changed on disk
+    pub fn reload_large_file(&mut self, index: usize, disk_state: DiskState) {
+        if let (Some(file_path), Some(file_state)) = (self.file_paths.get(index), self.file_states.get_mut(index)) {
+            if let FileMode::Large(large_file) = &mut file_state.mode {
+                *large_file = LargeFile::open(file_path, disk_state.size());
+                file_state.revision += 1;
+                file_state.disk_state = Some(disk_state);
+            }
+        }
+    }
+
+    /// Index of the open file with the given path
+    pub fn find_file(&self, file_path: &str) -> Option<usize> {
+        let file_path = paths::canonical_path(file_path);
+        self.file_paths.iter().position(|path| *path == file_path)
+    }
+
+    /// Replace the content of the file at `index` with content recovered from
+    /// its swap file, as `get_journal_content` wrote it. The swap file is then
+    /// considered part of this session.
+    pub fn recover_file(&mut self, index: usize, content: &str) -> Result<(), String> {
+        let (file_content, file_state) = match (self.file_contents.get_mut(index), self.file_states.get_mut(index)) {
+            (Some(file_content), Some(file_state)) => (file_content, file_state),
+            _ => return Err(format!("No file at position {}", index + 1)),
+        };
+        match file_state.mode {
+            FileMode::Text => {
+                *file_content = content.to_owned();
+                file_state.edited(content.as_bytes());
+                file_state.view.cursor = editing::clamp_cursor(content, file_state.view.cursor);
+            },
+            FileMode::Hex(_) => {
+                let bytes = hex_buffer::parse_dump(content)?;
+                file_state.edited(&bytes);
+                if let FileMode::Hex(hex_buffer) = &mut file_state.mode {
+                    hex_buffer.set_bytes(bytes);
+                }
+            },
+            FileMode::Large(_) => return Err("Large files are opened read-only".to_owned()),
+        }
+        file_state.journaled_revision = file_state.revision;
+        file_state.owns_swap = true;
+        Ok(())
+    }
+
+    /// Whether any file was edited since its swap file work was last scheduled
+    pub fn needs_journal(&self) -> bool {
+        self.file_states.iter().any(|file_state| {
+            file_state.revision != file_state.journaled_revision && !matches!(file_state.mode, FileMode::Large(_))
+        })
+    }
+
+    /// Collect the swap file work needed to bring every journal up to date:
+    /// files with unsaved edits get their content journaled, while files that
+    /// went back to their saved content get their stale swap file removed.
+    pub fn take_swap_jobs(&mut self) -> Vec<SwapJob> {
+        let mut jobs = vec![];
+        for index in 0..self.file_states.len() {
+            let file_state = &self.file_states[index];
+            if file_state.revision == file_state.journaled_revision || matches!(file_state.mode, FileMode::Large(_)) {
+                continue;
+            }
+            let file_path = match self.file_paths.get(index) {
+                Some(file_path) => file_path.clone(),
+                None => continue,
+            };
+            let content = if file_state.modified { self.get_journal_content(index) } else { None };
+            let file_state = &mut self.file_states[index];
+            file_state.journaled_revision = file_state.revision;
+            if let Some(content) = content {
+                file_state.owns_swap = true;
+                jobs.push(SwapJob::Write { file_path, content });
+            } else if file_state.owns_swap {
+                file_state.owns_swap = false;
+                jobs.push(SwapJob::Remove { file_path });
+            }
+        }
+        jobs
+    }
+
+    /// Paths of the open files whose swap file was written by this session
+    pub fn owned_swap_file_paths(&self) -> Vec<String> {
+        self.file_states
+            .iter()
+            .zip(self.file_paths.iter())
+            .filter(|(file_state, _)| file_state.owns_swap)
+            .map(|(_, file_path)| file_path.clone())
+            .collect()
+    }
+
+    /// The open files that are on disk, with their views, as a session to
+    /// restore later. Paths are made absolute so that the session can be
+    /// restored from anywhere.
+    pub fn to_session(&self) -> Session {
+        let mut session = Session::default();
+        for (index, file_path) in self.file_paths.iter().enumerate() {
+            let path = match std::fs::canonicalize(file_path) {
+                Ok(path) => path.to_string_lossy().into_owned(),
+                Err(_) => continue,
+            };
+            if index == self.currently_selected_file_index {
+                session.selected = session.files.len();
+            }
+            let view = self.get_view(index);
+            session.files.push(SessionFile {
+                path,
+                scroll_offset: view.scroll_offset,
+                cursor: view.cursor,
+                hex_cursor: match self.file_states.get(index).map(|file_state| &file_state.mode) {
+                    Some(FileMode::Hex(hex_buffer)) => Some(hex_buffer.cursor()),
+                    _ => None,
+                },
+            });
+        }
+        session
+    }
+
+    /// Move the file at `from` to `to`, shifting the files in between. The
+    /// moved file stays selected if it was.
+    pub fn move_file(&mut self, from: usize, to: usize) -> Result<(), String> {
+        let file_count = self.file_contents.len().min(self.file_paths.len());
+        if from >= file_count || to >= file_count {
+            return Err(format!("No tab at position {}", from.max(to) + 1));
+        }
+        let file_path = self.file_paths.remove(from);
+        self.file_paths.insert(to, file_path);
+        let file_content = self.file_contents.remove(from);
+        self.file_contents.insert(to, file_content);
+        let file_state = self.file_states.remove(from);
+        self.file_states.insert(to, file_state);
+        let selected = self.currently_selected_file_index;
+        self.currently_selected_file_index = if selected == from {
+            to
+        } else if from < selected && selected <= to {
+            selected - 1
+        } else if to <= selected && selected < from {
+            selected + 1
+        } else {
+            selected
+        };
+        Ok(())
+    }
+
+    /// Select the file at `index`
+    pub fn select_file(&mut self, index: usize) {
+        if index < self.file_paths.len() {
+            self.currently_selected_file_index = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_journal_and_recover_hex_buffers() {
+        let path = std::env::temp_dir().join(format!("rust_text_editor_hex_journal_test_{}.bin", std::process::id()));
+        std::fs::write(&path, [0x00, 0xff, 0x10]).unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let mut files_data = OpenFilesData::new();
+        files_data.open_file(&path).unwrap();
+        files_data.overwrite_nibble('a').unwrap();
+        assert!(files_data.needs_journal());
+        let content = match files_data.take_swap_jobs().as_slice() {
+            [SwapJob::Write { content, .. }] => content.clone(),
+            jobs => panic!("expected a single write, got {:?}", jobs),
+        };
+        assert!(!files_data.needs_journal());
+
+        let mut recovered = OpenFilesData::new();
+        recovered.open_file(&path).unwrap();
+        recovered.recover_file(0, &content).unwrap();
+        assert_eq!(recovered.get_hex_buffer(0).map(HexBuffer::bytes), Some(&[0xa0, 0xff, 0x10][..]));
+        assert!(recovered.is_modified(0));
+        assert_eq!(recovered.owned_swap_file_paths(), vec![files_data.get_open_file_paths()[0].clone()]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn should_close_files_other_than_the_selected_one() {
+        let paths: Vec<String> = (0..3)
+            .map(|index| {
+                let name = format!("rust_text_editor_close_test_{}_{}.txt", std::process::id(), index);
+                let path = std::env::temp_dir().join(name);
+                std::fs::write(&path, index.to_string()).unwrap();
+                path.to_string_lossy().into_owned()
+            })
+            .collect();
+        let mut files_data = OpenFilesData::new();
+        for path in paths.iter() {
+            files_data.open_file(path).unwrap();
+        }
+        files_data.select_file(2);
+        files_data.close_file_at(0).unwrap();
+        assert_eq!(files_data.get_currently_selected_file_content(), "2");
+        files_data.close_file_at(1).unwrap();
+        assert_eq!(files_data.get_currently_selected_file_content(), "1");
+        assert!(files_data.close_file_at(1).is_err());
+        for path in paths.iter() {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use super::panels::Panels;
+use super::settings::{Settings, WrapMode};
+
+/// An option of the editor, set with `:set` or in a config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorOption {
+    Numbers,
+    Wrap,
+    TabWidth,
+    List,
+    ListChars,
+    Guides,
+    Scrollbar,
+    Minimap,
+    HelpPanel,
+    LogPanel,
+    KeyTimeout,
+    Theme,
+}
+
+impl EditorOption {
+    pub fn all() -> &'static [EditorOption] {
+        &[
+            EditorOption::Numbers,
+            EditorOption::Wrap,
+            EditorOption::TabWidth,
+            EditorOption::List,
+            EditorOption::ListChars,
+            EditorOption::Guides,
+            EditorOption::Scrollbar,
+            EditorOption::Minimap,
+            EditorOption::HelpPanel,
+            EditorOption::LogPanel,
+            EditorOption::KeyTimeout,
+            EditorOption::Theme,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditorOption::Numbers => "numbers",
+            EditorOption::Wrap => "wrap",
+            EditorOption::TabWidth => "tabwidth",
+            EditorOption::List => "list",
+            EditorOption::ListChars => "listchars",
+            EditorOption::Guides => "guides",
+            EditorOption::Scrollbar => "scrollbar",
+            EditorOption::Minimap => "minimap",
+            EditorOption::HelpPanel => "helppanel",
+            EditorOption::LogPanel => "logpanel",
+            EditorOption::KeyTimeout => "keytimeout",
+            EditorOption::Theme => "theme",
+        }
+    }
+
+    /// Short name, as for the commands of the same name
+    fn alias(&self) -> Option<&'static str> {
+        match self {
+            EditorOption::Numbers => Some("nu"),
+            EditorOption::TabWidth => Some("ts"),
+            EditorOption::ListChars => Some("lcs"),
+            _ => None,
+        }
+    }
+
+    /// Whether the option is on or off, so that it can be toggled
+    pub fn is_switch(&self) -> bool {
+        matches!(
+            self,
+            EditorOption::Wrap
+                | EditorOption::List
+                | EditorOption::Guides
+                | EditorOption::Scrollbar
+                | EditorOption::Minimap
+                | EditorOption::HelpPanel
+                | EditorOption::LogPanel
+        )
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::all()
+            .iter()
+            .find(|option| option.name() == name || option.alias() == Some(name))
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::all().iter().map(EditorOption::name).collect();
+                format!("Unknown option {}, expected one of {}", name, names.join(", "))
+            })
+    }
+}
+
+impl Display for EditorOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// What `:set` does to an option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Assignment {
+    Set(String),
+    On,
+    Off,
+    Toggle,
+    /// Only tell its value
+    Show,
+}
+
+impl Assignment {
+    /// `on` or `off`, or a toggle without a value, as taken by the commands
+    /// switching an option
+    pub fn from_switch(value: Option<bool>) -> Self {
+        match value {
+            Some(true) => Assignment::On,
+            Some(false) => Assignment::Off,
+            None => Assignment::Toggle,
+        }
+    }
+}
+
+/// Parse the argument of `:set`, written `name=value` or `name value` to set
+/// an option, `name`, `noname` or `name!` to switch one on, off or the other
+/// way, and `name?` to tell its value. Options which are not switches are
+/// told by their name alone.
+pub fn parse_assignment(argument: &str) -> Result<(EditorOption, Assignment), String> {
+    let argument = argument.trim();
+    if let Some((name, value)) = argument.split_once(['=', ' ']) {
+        return Ok((EditorOption::from_name(name.trim())?, Assignment::Set(value.trim().to_owned())));
+    }
+    if let Some(name) = argument.strip_suffix('?') {
+        return Ok((EditorOption::from_name(name)?, Assignment::Show));
+    }
+    if let Some(name) = argument.strip_suffix('!') {
+        return Ok((EditorOption::from_name(name)?, Assignment::Toggle));
+    }
+    let option = match argument.strip_prefix("no").map(EditorOption::from_name) {
+        Some(Ok(option)) if option.is_switch() => return Ok((option, Assignment::Off)),
+        _ => EditorOption::from_name(argument)?,
+    };
+    if option.is_switch() {
+        Ok((option, Assignment::On))
+    } else {
+        Ok((option, Assignment::Show))
+    }
+}
+
+/// Parse the value of a switch, written `on` or `off` as in the commands or
+/// `true` or `false` as in TOML
+pub fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        _ => Err(format!("Invalid switch {}, expected on or off", value)),
+    }
+}
+
+fn switch(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// The switch behind an option, if it is one
+fn switch_mut<'a>(settings: &'a mut Settings, panels: &'a mut Panels, option: EditorOption) -> Option<&'a mut bool> {
+    match option {
+        EditorOption::List => Some(&mut settings.list),
+        EditorOption::Guides => Some(&mut settings.indent_guides),
+        EditorOption::Scrollbar => Some(&mut settings.scrollbar),
+        EditorOption::Minimap => Some(&mut settings.minimap),
+        EditorOption::HelpPanel => Some(&mut panels.help.visible),
+        EditorOption::LogPanel => Some(&mut panels.logs.visible),
+        _ => None,
+    }
+}
+
+/// Value of an option, as written to set it. The theme is not known here.
+pub fn value(settings: &Settings, panels: &Panels, option: EditorOption) -> Option<String> {
+    let value = match option {
+        EditorOption::Numbers => settings.line_numbers.to_string(),
+        EditorOption::Wrap => settings.wrap.to_string(),
+        EditorOption::TabWidth => settings.tab_width.to_string(),
+        EditorOption::List => switch(settings.list).to_owned(),
+        EditorOption::ListChars => settings.list_chars.to_string(),
+        EditorOption::Guides => switch(settings.indent_guides).to_owned(),
+        EditorOption::Scrollbar => switch(settings.scrollbar).to_owned(),
+        EditorOption::Minimap => switch(settings.minimap).to_owned(),
+        EditorOption::HelpPanel => switch(panels.help.visible).to_owned(),
+        EditorOption::LogPanel => switch(panels.logs.visible).to_owned(),
+        EditorOption::KeyTimeout => settings.key_timeout.as_millis().to_string(),
+        EditorOption::Theme => return None,
+    };
+    Some(value)
+}
+
+/// Parse a tab width, from 1 to 16 columns
+pub fn parse_tab_width(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|width| (1..=16).contains(width))
+        .ok_or_else(|| format!("Invalid tab width {}, expected a number from 1 to 16", value))
+}
+
+/// Parse how long keys starting a longer binding wait, in milliseconds up
+/// to 10 seconds
+fn parse_key_timeout(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<u64>()
+        .ok()
+        .filter(|millis| *millis <= 10_000)
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("Invalid key timeout {}, expected milliseconds from 0 to 10000", value))
+}
+
+/// Apply `assignment` to an option other than the theme, which is loaded
+/// from disk. Wrapping is a mode, switched on as soft wrapping.
+pub fn set(
+    settings: &mut Settings,
+    panels: &mut Panels,
+    option: EditorOption,
+    assignment: &Assignment,
+) -> Result<(), String> {
+    if option == EditorOption::Wrap {
+        settings.wrap = match assignment {
+            Assignment::Set(value) => value.parse()?,
+            Assignment::On => WrapMode::Soft,
+            Assignment::Off => WrapMode::Off,
+            Assignment::Toggle => settings.wrap.toggled(),
+            Assignment::Show => settings.wrap,
+        };
+        return Ok(());
+    }
+    if let Some(switch) = switch_mut(settings, panels, option) {
+        *switch = match assignment {
+            Assignment::Set(value) => parse_switch(value)?,
+            Assignment::On => true,
+            Assignment::Off => false,
+            Assignment::Toggle => !*switch,
+            Assignment::Show => *switch,
+        };
+        return Ok(());
+    }
+    let value = match assignment {
+        Assignment::Set(value) => value,
+        Assignment::Show => return Ok(()),
+        _ => return Err(format!("{} is not a switch, set it with {}=<value>", option, option)),
+    };
+    match option {
+        EditorOption::Numbers => settings.line_numbers = value.parse()?,
+        EditorOption::TabWidth => settings.tab_width = parse_tab_width(value)?,
+        EditorOption::ListChars => settings.list_chars = value.parse()?,
+        EditorOption::KeyTimeout => settings.key_timeout = parse_key_timeout(value)?,
+        _ => return Err(format!("{} cannot be set here", option)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::settings::LineNumbers;
+
+    #[test]
+    fn should_parse_assignments() {
+        assert_eq!(parse_assignment("ts=8"), Ok((EditorOption::TabWidth, Assignment::Set("8".to_owned()))));
+        assert_eq!(
+            parse_assignment("numbers relative"),
+            Ok((EditorOption::Numbers, Assignment::Set("relative".to_owned())))
+        );
+        assert_eq!(parse_assignment("list"), Ok((EditorOption::List, Assignment::On)));
+        assert_eq!(parse_assignment("nolist"), Ok((EditorOption::List, Assignment::Off)));
+        assert_eq!(parse_assignment("minimap!"), Ok((EditorOption::Minimap, Assignment::Toggle)));
+        assert_eq!(parse_assignment("wrap?"), Ok((EditorOption::Wrap, Assignment::Show)));
+        assert_eq!(parse_assignment("numbers"), Ok((EditorOption::Numbers, Assignment::Show)));
+        assert!(parse_assignment("nonumbers").is_err());
+        assert!(parse_assignment("colour=red").is_err());
+    }
+
+    #[test]
+    fn should_set_options() {
+        let mut settings = Settings::default();
+        let mut panels = Panels::default();
+        set(&mut settings, &mut panels, EditorOption::TabWidth, &Assignment::Set("2".to_owned())).unwrap();
+        assert_eq!(settings.tab_width, 2);
+        set(&mut settings, &mut panels, EditorOption::Numbers, &Assignment::Set("hybrid".to_owned())).unwrap();
+        assert_eq!(settings.line_numbers, LineNumbers::Hybrid);
+        set(&mut settings, &mut panels, EditorOption::LogPanel, &Assignment::Set("false".to_owned())).unwrap();
+        assert!(!panels.logs.visible);
+        set(&mut settings, &mut panels, EditorOption::Wrap, &Assignment::Toggle).unwrap();
+        assert_eq!(value(&settings, &panels, EditorOption::Wrap), Some("off".to_owned()));
+        set(&mut settings, &mut panels, EditorOption::KeyTimeout, &Assignment::Set("500".to_owned())).unwrap();
+        assert_eq!(value(&settings, &panels, EditorOption::KeyTimeout), Some("500".to_owned()));
+
+        assert!(set(&mut settings, &mut panels, EditorOption::TabWidth, &Assignment::Set("40".to_owned())).is_err());
+        assert!(set(&mut settings, &mut panels, EditorOption::Numbers, &Assignment::Toggle).is_err());
+        assert!(set(&mut settings, &mut panels, EditorOption::KeyTimeout, &Assignment::Set("1s".to_owned())).is_err());
+        assert_eq!(settings.tab_width, 2);
+    }
+}
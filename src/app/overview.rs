@@ -0,0 +1,152 @@
+use std::ops::Range;
+
+/// Columns of text summed up by each dot of the minimap
+pub const MINIMAP_COLUMNS_PER_DOT: usize = 4;
+
+/// Something worth finding in the file, marked on the scrollbar. Later kinds
+/// win over earlier ones on a shared row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mark {
+    /// Line changed since the file was last read or written
+    Modified,
+    /// Line matching the last search
+    SearchMatch,
+    /// Line with a diagnostic, e.g. an error reported by a checker
+    Diagnostic,
+}
+
+/// A row of the scrollbar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScrollbarRow {
+    /// Whether the row is part of the thumb, standing for the lines in view
+    pub thumb: bool,
+    pub mark: Option<Mark>,
+}
+
+/// Row of a `height` rows high track a line of a `line_count` lines file falls on
+fn row_of(line: usize, line_count: usize, height: usize) -> usize {
+    (line * height / line_count.max(1)).min(height.saturating_sub(1))
+}
+
+/// Rows of a scrollbar `height` rows high for a file of `line_count` lines,
+/// the lines of `view` in view, with `marks` given by line
+pub fn scrollbar(line_count: usize, view: Range<usize>, marks: &[(usize, Mark)], height: usize) -> Vec<ScrollbarRow> {
+    let mut rows = vec![ScrollbarRow::default(); height];
+    if height == 0 {
+        return rows;
+    }
+    let line_count = line_count.max(view.end).max(1);
+    let thumb_start = row_of(view.start, line_count, height);
+    // The thumb always shows, however small the view is in the file
+    let thumb_end = (view.end * height).div_ceil(line_count).clamp(thumb_start + 1, height);
+    for row in rows[thumb_start..thumb_end].iter_mut() {
+        row.thumb = true;
+    }
+    for (line, mark) in marks.iter() {
+        let row = &mut rows[row_of(*line, line_count, height)];
+        row.mark = row.mark.max(Some(*mark));
+    }
+    rows
+}
+
+/// Bit of a braille character for the dot at `column` (0 or 1) and `row`
+/// (0 to 3) of its cell
+fn braille_bit(column: usize, row: usize) -> u32 {
+    match (column, row) {
+        (0, 3) => 0x40,
+        (1, 3) => 0x80,
+        (0, row) => 1 << row,
+        (_, row) => 1 << (row + 3),
+    }
+}
+
+/// The whole of a file drawn in braille, `width` characters wide and up to
+/// `height` high. Every dot stands for `MINIMAP_COLUMNS_PER_DOT` columns of a
+/// run of lines, set when any of them has text there. Rows are returned with
+/// whether they stand for lines of `view`.
+pub fn minimap<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    line_count: usize,
+    view: Range<usize>,
+    width: usize,
+    height: usize,
+    tab_width: usize,
+) -> Vec<(String, bool)> {
+    let dot_rows = height * 4;
+    if dot_rows == 0 || width == 0 {
+        return vec![];
+    }
+    // Short files get a line per dot row, from the top
+    let lines_per_dot = line_count.div_ceil(dot_rows).max(1);
+    let visible_columns = width * 2 * MINIMAP_COLUMNS_PER_DOT;
+    let mut cells = vec![0u32; width * height];
+    for (index, line) in lines.enumerate() {
+        let dot_row = index / lines_per_dot;
+        if dot_row >= dot_rows {
+            break;
+        }
+        let mut column = 0;
+        for c in line.chars() {
+            if column >= visible_columns {
+                break;
+            }
+            if !c.is_whitespace() {
+                let dot_column = column / MINIMAP_COLUMNS_PER_DOT;
+                cells[dot_row / 4 * width + dot_column / 2] |= braille_bit(dot_column % 2, dot_row % 4);
+            }
+            column += if c == '\t' { tab_width.max(1) - column % tab_width.max(1) } else { 1 };
+        }
+    }
+    let lines_per_row = lines_per_dot * 4;
+    let used_rows = line_count.max(1).div_ceil(lines_per_row).min(height);
+    (0..used_rows)
+        .map(|row| {
+            let text = cells[row * width..(row + 1) * width]
+                .iter()
+                .map(|bits| char::from_u32(0x2800 + bits).unwrap_or(' '))
+                .collect();
+            let lines = row * lines_per_row..(row + 1) * lines_per_row;
+            (text, lines.start < view.end && view.start < lines.end)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_place_the_thumb_and_marks() {
+        let rows = scrollbar(100, 50..60, &[(0, Mark::Modified), (99, Mark::SearchMatch)], 10);
+        let thumb: Vec<usize> = (0..10).filter(|row| rows[*row].thumb).collect();
+        assert_eq!(thumb, vec![5]);
+        assert_eq!(rows[0].mark, Some(Mark::Modified));
+        assert_eq!(rows[9].mark, Some(Mark::SearchMatch));
+
+        // The search match wins over the change on the same row
+        let rows = scrollbar(100, 0..10, &[(3, Mark::SearchMatch), (4, Mark::Modified)], 10);
+        assert_eq!(rows[0], ScrollbarRow { thumb: true, mark: Some(Mark::SearchMatch) });
+
+        // Diagnostics win over both
+        let marks = [(20, Mark::Diagnostic), (21, Mark::SearchMatch), (22, Mark::Modified), (90, Mark::Diagnostic)];
+        let rows = scrollbar(100, 0..10, &marks, 10);
+        assert_eq!(rows[2].mark, Some(Mark::Diagnostic));
+        assert_eq!(rows[9].mark, Some(Mark::Diagnostic));
+        assert_eq!(rows[5].mark, None);
+
+        // A file fitting in the view is all thumb
+        assert!(scrollbar(5, 0..20, &[], 4).iter().all(|row| row.thumb));
+    }
+
+    #[test]
+    fn should_draw_the_file_in_braille() {
+        let lines = ["abcdefgh", "", "\tx", "    ", "a"];
+        let rows = minimap(lines.iter().copied(), lines.len(), 4..5, 2, 3, 4);
+        // A line per dot row: the first cell holds lines 0 to 3, the second 4
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0], ("\u{2829}\u{2800}".to_owned(), false));
+        assert_eq!(rows[1], ("\u{2801}\u{2800}".to_owned(), true));
+        let rows = minimap(lines.iter().copied(), lines.len(), 0..5, 2, 3, 4);
+        assert!(rows.iter().all(|(_, in_view)| *in_view));
+    }
+}
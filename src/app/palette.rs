@@ -0,0 +1,210 @@
+use super::actions::{Action, Actions};
+use super::commands::{Command, UserCommand};
+use super::keymap::{self, Mode};
+
+/// What an entry of the palette runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteItem {
+    Action(Action),
+    /// An ex command, by its usage. As most take arguments, it is opened in
+    /// the command line to be completed.
+    Command(&'static str),
+    /// A user-defined command, run as it is
+    UserCommand(Command),
+}
+
+impl PaletteItem {
+    /// Name of an ex command, from its usage
+    pub fn command_name(usage: &str) -> &str {
+        usage.split_whitespace().next().unwrap_or(usage)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub item: PaletteItem,
+    pub label: String,
+    /// Keys bound to the entry, if any
+    pub keys: String,
+}
+
+/// Fuzzy finder over every action, ex command and user-defined command,
+/// opened with `Ctrl-Shift-p`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    query: String,
+    /// Index of the selected entry among the matches
+    selected: usize,
+    entries: Vec<PaletteEntry>,
+    /// Mode the palette was opened from, whose keys it shows
+    mode: Mode,
+}
+
+impl Palette {
+    pub fn new(actions: &Actions, mode: Mode, user_commands: &[UserCommand]) -> Self {
+        let mut entries: Vec<PaletteEntry> = actions
+            .actions()
+            .iter()
+            .map(|action| PaletteEntry {
+                item: PaletteItem::Action(*action),
+                label: action.to_string(),
+                keys: actions
+                    .keys_typed_in(mode, *action)
+                    .into_iter()
+                    .map(|keys| keymap::format_sequence(keys))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            })
+            .collect();
+        entries.extend(Command::usages().iter().map(|usage| PaletteEntry {
+            item: PaletteItem::Command(usage),
+            label: format!(":{}", usage),
+            keys: String::new(),
+        }));
+        entries.extend(user_commands.iter().map(|user_command| PaletteEntry {
+            item: PaletteItem::UserCommand(user_command.command.clone()),
+            label: format!(":{} ({})", user_command.name, user_command.command),
+            keys: String::new(),
+        }));
+        Self {
+            query: String::new(),
+            selected: 0,
+            entries,
+            mode,
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Entries matching the query, best first
+    pub fn matches(&self) -> Vec<&PaletteEntry> {
+        let mut scored: Vec<(i64, &PaletteEntry)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| fuzzy_score(&self.query, &entry.label).map(|score| (score, entry)))
+            .collect();
+        // Stable, so that equally good entries keep their order
+        scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+        scored.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    /// The item of the selected entry, if any matches
+    pub fn chosen(&self) -> Option<PaletteItem> {
+        self.matches().get(self.selected).map(|entry| entry.item.clone())
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    /// Move the selection by `delta` entries, wrapping around the matches
+    pub fn select(&mut self, delta: i32) {
+        let count = self.matches().len() as i64;
+        if count > 0 {
+            self.selected = (self.selected as i64 + delta as i64).rem_euclid(count) as usize;
+        }
+    }
+}
+
+/// How well `text` matches `pattern`, if it holds every character of it in
+/// order, ignoring case. Characters matched in a row or at the start of a
+/// word score higher, and gaps cost a little.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for c in pattern.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let index = position + text[position..].iter().position(|t| *t == c)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !text[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (index - position).min(3) as i64;
+        previous = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inputs::key::{Key, KeyCode};
+
+    #[test]
+    fn should_score_fuzzy_matches() {
+        assert_eq!(fuzzy_score("", "Save File"), Some(0));
+        assert!(fuzzy_score("sf", "Save File").is_some());
+        assert!(fuzzy_score("fs", "Save File").is_none());
+        // Word starts and runs beat scattered characters
+        assert!(fuzzy_score("sav", "Save File") > fuzzy_score("sav", "Split Vertically"));
+        assert!(fuzzy_score("wl", "Window Left") > fuzzy_score("wl", "Show Help"));
+    }
+
+    #[test]
+    fn should_list_actions_and_commands_best_first() {
+        let actions: Actions = vec![Action::Quit, Action::SaveFile, Action::NextFile].into();
+        let user_commands = vec![UserCommand::new("wide", "tabwidth 8").unwrap()];
+        let mut palette = Palette::new(&actions, Mode::Normal, &user_commands);
+        assert_eq!(palette.matches().len(), 3 + Command::usages().len() + 1);
+
+        "save".chars().for_each(|c| palette.push(c));
+        assert_eq!(palette.chosen(), Some(PaletteItem::Action(Action::SaveFile)));
+        assert_eq!(palette.matches()[0].keys, "<C-s>");
+
+        palette.pop();
+        palette.pop();
+        palette.pop();
+        palette.pop();
+        "thm".chars().for_each(|c| palette.push(c));
+        assert_eq!(palette.chosen(), Some(PaletteItem::Command("theme [name]")));
+        palette.select(-1);
+        assert_eq!(palette.selected(), palette.matches().len() - 1);
+
+        while !palette.query().is_empty() {
+            palette.pop();
+        }
+        "wide".chars().for_each(|c| palette.push(c));
+        assert_eq!(palette.chosen(), Some(PaletteItem::UserCommand(Command::TabWidth(8))));
+        assert_eq!(palette.matches()[0].label, ":wide (tabwidth 8)");
+    }
+
+    #[test]
+    fn should_show_the_keys_of_the_mode_opened_from() {
+        let mut actions: Actions = vec![Action::Quit, Action::SaveFile, Action::EndWriteMode].into();
+        let mut keymap = actions.keymap().clone();
+        keymap.bind(Mode::Write, Action::EndWriteMode, vec![vec![Key::plain(KeyCode::Esc)]]);
+        actions.set_keymap(keymap);
+        let keys = |mode: Mode, action: Action| {
+            let palette = Palette::new(&actions, mode, &[]);
+            let entry = palette.entries.iter().find(|entry| entry.item == PaletteItem::Action(action)).cloned();
+            entry.map(|entry| entry.keys).unwrap_or_default()
+        };
+        assert_eq!(keys(Mode::Normal, Action::Quit), "q");
+        // `q` types text in write mode
+        assert_eq!(keys(Mode::Write, Action::Quit), "");
+        assert_eq!(keys(Mode::Write, Action::SaveFile), "<C-s>");
+        assert_eq!(keys(Mode::Normal, Action::EndWriteMode), "<C-w>");
+        assert_eq!(keys(Mode::Write, Action::EndWriteMode), "<Esc> <C-w>");
+    }
+}
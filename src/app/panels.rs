@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+/// Width of the help panel, until resized
+pub const HELP_WIDTH: u16 = 32;
+pub const HELP_MIN_WIDTH: u16 = 20;
+pub const HELP_MAX_WIDTH: u16 = 80;
+/// Height of the log panel, until resized
+pub const LOGS_HEIGHT: u16 = 12;
+/// Fewer log lines than this are not worth showing
+pub const LOGS_MIN_HEIGHT: u16 = 5;
+pub const LOGS_MAX_HEIGHT: u16 = 40;
+
+/// A panel around the body, which can be hidden and resized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Panel {
+    pub visible: bool,
+    /// Columns of the help panel, rows of the log panel
+    pub size: u16,
+}
+
+impl Panel {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Grow the panel by `delta`, or shrink it when negative, within `min..=max`.
+    /// A hidden panel is shown again at its new size.
+    pub fn resize(&mut self, delta: i32, min: u16, max: u16) {
+        self.size = (self.size as i32 + delta).clamp(min as i32, max as i32) as u16;
+        self.visible = true;
+    }
+}
+
+/// The help and log panels, remembered across sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Panels {
+    /// Right of the body
+    pub help: Panel,
+    /// Below the status line
+    pub logs: Panel,
+}
+
+impl Default for Panels {
+    fn default() -> Self {
+        Self {
+            help: Panel {
+                visible: true,
+                size: HELP_WIDTH,
+            },
+            logs: Panel {
+                visible: true,
+                size: LOGS_HEIGHT,
+            },
+        }
+    }
+}
+
+impl Panels {
+    pub fn resize_help(&mut self, delta: i32) {
+        self.help.resize(delta, HELP_MIN_WIDTH, HELP_MAX_WIDTH);
+    }
+
+    pub fn resize_logs(&mut self, delta: i32) {
+        self.logs.resize(delta, LOGS_MIN_HEIGHT, LOGS_MAX_HEIGHT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_resize_panels_within_bounds() {
+        let mut panels = Panels::default();
+        panels.logs.toggle();
+        assert!(!panels.logs.visible);
+        panels.resize_logs(-100);
+        assert_eq!(panels.logs, Panel { visible: true, size: LOGS_MIN_HEIGHT });
+        panels.resize_help(100);
+        assert_eq!(panels.help.size, HELP_MAX_WIDTH);
+    }
+
+    #[test]
+    fn should_default_panels_missing_from_the_layout_file() {
+        let panels: Panels = toml::from_str("[logs]\nvisible = false\nsize = 8\n").unwrap();
+        assert_eq!(panels.help, Panels::default().help);
+        assert_eq!(panels.logs, Panel { visible: false, size: 8 });
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+/// The path identifying the file at `file_path`: absolute, with symlinks and
+/// `.`/`..` resolved, so that the same file is never opened twice. Paths of
+/// files not on disk yet are kept as they are.
+pub fn canonical_path(file_path: &str) -> String {
+    std::fs::canonicalize(file_path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| file_path.to_owned())
+}
+
+/// Short names to tell files apart: their file name, with as many parent
+/// directories as it takes for no two names to be the same, e.g. `app/mod.rs`
+/// and `io/mod.rs`
+pub fn display_names(file_paths: &[String]) -> Vec<String> {
+    let components: Vec<Vec<String>> = file_paths
+        .iter()
+        .map(|file_path| {
+            Path::new(file_path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                .collect()
+        })
+        .collect();
+    let mut depths = vec![1; file_paths.len()];
+    loop {
+        let names: Vec<String> = components
+            .iter()
+            .zip(depths.iter())
+            .map(|(components, depth)| {
+                let start = components.len().saturating_sub(*depth);
+                components[start..].iter().collect::<PathBuf>().to_string_lossy().into_owned()
+            })
+            .collect();
+        let mut deepened = false;
+        for (index, name) in names.iter().enumerate() {
+            let is_ambiguous = names.iter().enumerate().any(|(other, other_name)| other != index && other_name == name);
+            if is_ambiguous && depths[index] < components[index].len() {
+                depths[index] += 1;
+                deepened = true;
+            }
+        }
+        if !deepened {
+            return names;
+        }
+    }
+}
+
+/// Kind of content of the file at `file_path`, from its extension
+pub fn file_type(file_path: &str) -> String {
+    let path = Path::new(file_path);
+    let extension = match path.extension() {
+        Some(extension) => extension.to_string_lossy().to_lowercase(),
+        None if path.file_name().is_some_and(|name| name == "Makefile") => return "make".to_owned(),
+        None => return "text".to_owned(),
+    };
+    let file_type = match extension.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" => "javascript",
+        "ts" => "typescript",
+        "md" => "markdown",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        "sh" | "bash" => "shell",
+        "yml" | "yaml" => "yaml",
+        "txt" => "text",
+        other => other,
+    };
+    file_type.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_disambiguate_names_by_shortest_parent_suffix() {
+        let file_paths = [
+            "/src/app/mod.rs".to_owned(),
+            "/src/io/mod.rs".to_owned(),
+            "/src/lib.rs".to_owned(),
+            "/a/x/main.rs".to_owned(),
+            "/b/x/main.rs".to_owned(),
+        ];
+        assert_eq!(
+            display_names(&file_paths),
+            vec!["app/mod.rs", "io/mod.rs", "lib.rs", "a/x/main.rs", "b/x/main.rs"]
+        );
+    }
+
+    #[test]
+    fn should_tell_file_types_from_extensions() {
+        assert_eq!(file_type("src/main.rs"), "rust");
+        assert_eq!(file_type("Cargo.toml"), "toml");
+        assert_eq!(file_type("README.MD"), "markdown");
+        assert_eq!(file_type("Makefile"), "make");
+        assert_eq!(file_type("LICENSE"), "text");
+    }
+
+    #[test]
+    fn should_resolve_relative_paths_to_the_same_file() {
+        let path = std::env::temp_dir().join("rust_text_editor_paths_test.txt");
+        std::fs::write(&path, "").unwrap();
+        let dir = std::env::temp_dir();
+        let dotted = dir.join(".").join("rust_text_editor_paths_test.txt");
+        assert_eq!(
+            canonical_path(&dotted.to_string_lossy()),
+            canonical_path(&path.to_string_lossy())
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(canonical_path("no/such/file"), "no/such/file");
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt::{self, Display};
+
+use super::diff::DiffLine;
+use crate::inputs::key::{Key, KeyCode};
+
+/// A choice offered by a prompt, picked by pressing its key
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PromptAnswer {
+    Recover,
+    Diff,
+    DeleteSwap,
+    Keep,
+    Reload,
+    KeepMine,
+    SaveAllAndQuit,
+    QuitWithoutSaving,
+    SaveAndClose,
+    CloseWithoutSaving,
+    Cancel,
+}
+
+impl PromptAnswer {
+    /// Key selecting this answer
+    pub fn key(&self) -> Key {
+        match self {
+            PromptAnswer::Recover => Key::char('r'),
+            PromptAnswer::Diff => Key::char('d'),
+            PromptAnswer::DeleteSwap => Key::char('x'),
+            PromptAnswer::Keep => Key::char('k'),
+            PromptAnswer::Reload => Key::char('r'),
+            PromptAnswer::KeepMine => Key::char('k'),
+            PromptAnswer::SaveAllAndQuit => Key::char('s'),
+            PromptAnswer::QuitWithoutSaving => Key::char('q'),
+            PromptAnswer::SaveAndClose => Key::char('s'),
+            PromptAnswer::CloseWithoutSaving => Key::char('d'),
+            PromptAnswer::Cancel => Key::plain(KeyCode::Esc),
+        }
+    }
+}
+
+impl Display for PromptAnswer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match self {
+            PromptAnswer::Recover => "Recover",
+            PromptAnswer::Diff => "Diff",
+            PromptAnswer::DeleteSwap => "Delete Swap",
+            PromptAnswer::Keep => "Keep Swap",
+            PromptAnswer::Reload => "Reload",
+            PromptAnswer::KeepMine => "Keep Mine",
+            PromptAnswer::SaveAllAndQuit => "Save All and Quit",
+            PromptAnswer::QuitWithoutSaving => "Quit Without Saving",
+            PromptAnswer::SaveAndClose => "Save and Close",
+            PromptAnswer::CloseWithoutSaving => "Close Without Saving",
+            PromptAnswer::Cancel => "Cancel",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// The situation a prompt asks the user to resolve
+#[derive(Debug, Clone)]
+pub enum PromptKind {
+    /// A swap file left behind by an earlier session was found for an opened file
+    RecoverSwap {
+        file_path: String,
+        swap_content: String,
+    },
+    /// An open file with unsaved changes was changed on disk by another program.
+    /// `saving` tells whether this was found while about to save over it.
+    ExternalChange {
+        file_path: String,
+        disk_content: String,
+        saving: bool,
+    },
+    /// Quitting while these files have unsaved changes
+    QuitWithUnsavedChanges { file_paths: Vec<String> },
+    /// Closing a file with unsaved changes
+    CloseWithUnsavedChanges { file_path: String },
+}
+
+/// A modal question shown on top of the editor. While it is displayed, keys
+/// are used to pick one of its answers.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    kind: PromptKind,
+    diff: Option<Vec<DiffLine>>,
+    scroll: u16,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self {
+            kind,
+            diff: None,
+            scroll: 0,
+        }
+    }
+
+    pub fn kind(&self) -> &PromptKind {
+        &self.kind
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.kind {
+            PromptKind::RecoverSwap { .. } => "Swap File Found",
+            PromptKind::ExternalChange { .. } => "File Changed on Disk",
+            PromptKind::QuitWithUnsavedChanges { .. } | PromptKind::CloseWithUnsavedChanges { .. } => {
+                "Unsaved Changes"
+            },
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match &self.kind {
+            PromptKind::RecoverSwap { file_path, .. } => format!(
+                "{} has unsaved changes from a session that did not exit cleanly.",
+                file_path
+            ),
+            PromptKind::ExternalChange { file_path, saving: false, .. } => format!(
+                "{} was changed by another program while it has unsaved changes here.",
+                file_path
+            ),
+            PromptKind::ExternalChange { file_path, saving: true, .. } => format!(
+                "{} was changed by another program since it was loaded, saving would overwrite it.",
+                file_path
+            ),
+            PromptKind::QuitWithUnsavedChanges { file_paths } => {
+                format!("Unsaved changes would be lost in: {}", file_paths.join(", "))
+            },
+            PromptKind::CloseWithUnsavedChanges { file_path } => {
+                format!("{} has unsaved changes that would be lost.", file_path)
+            },
+        }
+    }
+
+    /// Explains which side of the diff is which
+    pub fn diff_legend(&self) -> &'static str {
+        match self.kind {
+            PromptKind::RecoverSwap { .. } => "- file on disk, + swap file",
+            PromptKind::ExternalChange { .. } => "- your changes, + file on disk",
+            PromptKind::QuitWithUnsavedChanges { .. } | PromptKind::CloseWithUnsavedChanges { .. } => "",
+        }
+    }
+
+    /// Answers offered, in display order
+    pub fn answers(&self) -> &'static [PromptAnswer] {
+        match self.kind {
+            PromptKind::RecoverSwap { .. } => &[
+                PromptAnswer::Recover,
+                PromptAnswer::Diff,
+                PromptAnswer::DeleteSwap,
+                PromptAnswer::Keep,
+            ],
+            PromptKind::ExternalChange { .. } => &[
+                PromptAnswer::Reload,
+                PromptAnswer::Diff,
+                PromptAnswer::KeepMine,
+            ],
+            PromptKind::QuitWithUnsavedChanges { .. } => &[
+                PromptAnswer::SaveAllAndQuit,
+                PromptAnswer::QuitWithoutSaving,
+                PromptAnswer::Cancel,
+            ],
+            PromptKind::CloseWithUnsavedChanges { .. } => &[
+                PromptAnswer::SaveAndClose,
+                PromptAnswer::CloseWithoutSaving,
+                PromptAnswer::Cancel,
+            ],
+        }
+    }
+
+    /// Given a key, find the corresponding answer
+    pub fn find(&self, key: Key) -> Option<PromptAnswer> {
+        self.answers().iter().copied().find(|answer| answer.key() == key)
+    }
+
+    /// Diff between the buffer and the content the prompt is about, once requested
+    pub fn diff(&self) -> Option<&Vec<DiffLine>> {
+        self.diff.as_ref()
+    }
+
+    pub fn set_diff(&mut self, diff: Vec<DiffLine>) {
+        self.diff = Some(diff);
+        self.scroll = 0;
+    }
+
+    pub fn scroll(&self) -> u16 {
+        self.scroll
+    }
+
+    pub fn scroll_diff(&mut self, delta: i32) {
+        let len = self.diff.as_ref().map_or(0, Vec::len) as i32;
+        self.scroll = (self.scroll as i32 + delta).clamp(0, (len - 1).max(0)) as u16;
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How lines are numbered in the gutter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineNumbers {
+    /// No gutter
+    Off,
+    #[default]
+    Absolute,
+    /// Distance to the cursor line
+    Relative,
+    /// Relative, except for the cursor line showing its absolute number
+    Hybrid,
+}
+
+impl LineNumbers {
+    /// Number shown in the gutter next to the 0-based `line`
+    pub fn number(&self, line: usize, cursor_line: usize) -> Option<usize> {
+        match self {
+            LineNumbers::Off => None,
+            LineNumbers::Absolute => Some(line + 1),
+            LineNumbers::Relative => Some(line.abs_diff(cursor_line)),
+            LineNumbers::Hybrid if line == cursor_line => Some(line + 1),
+            LineNumbers::Hybrid => Some(line.abs_diff(cursor_line)),
+        }
+    }
+}
+
+impl FromStr for LineNumbers {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "off" => Ok(LineNumbers::Off),
+            "absolute" => Ok(LineNumbers::Absolute),
+            "relative" => Ok(LineNumbers::Relative),
+            "hybrid" => Ok(LineNumbers::Hybrid),
+            _ => Err(format!("Invalid line numbers {}, expected off, absolute, relative or hybrid", value)),
+        }
+    }
+}
+
+impl Display for LineNumbers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LineNumbers::Off => "off",
+            LineNumbers::Absolute => "absolute",
+            LineNumbers::Relative => "relative",
+            LineNumbers::Hybrid => "hybrid",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How lines wider than the body are shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Continued on the rows below, indented like the line
+    #[default]
+    Soft,
+    /// Cut at the edge of the body, which scrolls horizontally
+    Off,
+}
+
+impl WrapMode {
+    pub fn toggled(&self) -> Self {
+        match self {
+            WrapMode::Soft => WrapMode::Off,
+            WrapMode::Off => WrapMode::Soft,
+        }
+    }
+}
+
+impl FromStr for WrapMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "soft" => Ok(WrapMode::Soft),
+            "off" => Ok(WrapMode::Off),
+            _ => Err(format!("Invalid wrap {}, expected soft or off", value)),
+        }
+    }
+}
+
+impl Display for WrapMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WrapMode::Soft => "soft",
+            WrapMode::Off => "off",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Glyphs whitespace is shown with in list mode, written like
+/// `tab:→ ,trail:·,nbsp:⍽,eol:¬`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListChars {
+    /// First cell of a tab, and the cells filling the rest of its width
+    pub tab: (char, char),
+    /// Spaces at the end of a line
+    pub trail: char,
+    /// Non-breaking spaces
+    pub nbsp: char,
+    /// After the last character of a line, if anything
+    pub eol: Option<char>,
+}
+
+impl Default for ListChars {
+    fn default() -> Self {
+        Self {
+            tab: ('→', ' '),
+            trail: '·',
+            nbsp: '⍽',
+            eol: Some('¬'),
+        }
+    }
+}
+
+impl FromStr for ListChars {
+    type Err = String;
+
+    /// Glyphs not given are left out, except for the tab which is then shown
+    /// as spaces
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid list chars {}, expected e.g. tab:→ ,trail:·,nbsp:⍽,eol:¬", value);
+        let mut list_chars = Self {
+            tab: (' ', ' '),
+            trail: ' ',
+            nbsp: '\u{a0}',
+            eol: None,
+        };
+        for item in value.split(',') {
+            let (name, glyphs) = item.split_once(':').ok_or_else(invalid)?;
+            let glyphs: Vec<char> = glyphs.chars().collect();
+            match (name, glyphs.as_slice()) {
+                ("tab", [first, rest]) => list_chars.tab = (*first, *rest),
+                ("trail", [glyph]) => list_chars.trail = *glyph,
+                ("nbsp", [glyph]) => list_chars.nbsp = *glyph,
+                ("eol", [glyph]) => list_chars.eol = Some(*glyph),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(list_chars)
+    }
+}
+
+impl Display for ListChars {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tab:{}{},trail:{},nbsp:{}", self.tab.0, self.tab.1, self.trail, self.nbsp)?;
+        if let Some(eol) = self.eol {
+            write!(f, ",eol:{}", eol)?;
+        }
+        Ok(())
+    }
+}
+
+/// User preferences on how the editor looks and behaves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub line_numbers: LineNumbers,
+    pub wrap: WrapMode,
+    /// Columns tabs are expanded to a multiple of
+    pub tab_width: usize,
+    /// Whether whitespace is shown with the `list_chars` glyphs
+    pub list: bool,
+    pub list_chars: ListChars,
+    /// Whether indentation levels are marked with vertical lines
+    pub indent_guides: bool,
+    /// Whether the right border of the body shows where the view is in the file
+    pub scrollbar: bool,
+    /// Whether the whole file is drawn in miniature right of the body
+    pub minimap: bool,
+    /// How long keys starting a longer binding wait for the next one
+    pub key_timeout: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            line_numbers: LineNumbers::default(),
+            wrap: WrapMode::default(),
+            tab_width: 4,
+            list: false,
+            list_chars: ListChars::default(),
+            indent_guides: false,
+            scrollbar: true,
+            minimap: false,
+            key_timeout: Duration::from_millis(1000),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_number_lines_relative_to_the_cursor() {
+        assert_eq!(LineNumbers::Absolute.number(4, 9), Some(5));
+        assert_eq!(LineNumbers::Relative.number(4, 9), Some(5));
+        assert_eq!(LineNumbers::Relative.number(9, 9), Some(0));
+        assert_eq!(LineNumbers::Hybrid.number(9, 9), Some(10));
+        assert_eq!(LineNumbers::Hybrid.number(12, 9), Some(3));
+        assert_eq!(LineNumbers::Off.number(4, 9), None);
+        assert_eq!("hybrid".parse(), Ok(LineNumbers::Hybrid));
+    }
+
+    #[test]
+    fn should_parse_list_chars() {
+        let list_chars: ListChars = "tab:>-,eol:$".parse().unwrap();
+        assert_eq!(list_chars.tab, ('>', '-'));
+        assert_eq!(list_chars.eol, Some('$'));
+        assert_eq!(list_chars.trail, ' ');
+        assert_eq!(ListChars::default().to_string().parse(), Ok(ListChars::default()));
+        assert!("tab:>".parse::<ListChars>().is_err());
+    }
+}
@@ -1,11 +1,16 @@
+use super::editing::{self, ViewState};
+use super::hex_buffer::{HexBuffer, BYTES_PER_ROW};
+use super::large_file::LargeFile;
 use super::open_files_data::OpenFilesData;
+use super::settings::{Settings, WrapMode};
+use super::{matching, whitespace, wrap};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum AppState {
+    #[default]
     Init,
     Initialized {
         write_mode: bool,
-        scroll_offset: (u16, u16),
         files_data: OpenFilesData,
     },
 }
@@ -14,7 +19,6 @@ impl AppState {
     pub fn initialized() -> Self {
         Self::Initialized {
             write_mode: false,
-            scroll_offset: (0, 0),
             files_data: OpenFilesData::new(),
         }
     }
@@ -35,107 +39,461 @@ impl AppState {
 
     pub fn get_text(&self) -> String {
         match self {
-            Self::Initialized { files_data, write_mode,  .. } => {
-                if !*write_mode {
-                    let mut out: String = files_data.get_currently_selected_file_content();
-                    out.push_str(" (input mode)");
-                    out
-                } else {
-                    files_data.get_currently_selected_file_content()
-                }
-            },
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_file_content(),
             _ => "".to_owned(),
         }
     }
 
+    /// Identifier, path and text of the selected file when it is held in memory
+    /// as text, for highlighting
+    pub fn get_source(&self) -> Option<(u64, &str, &str)> {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_source(),
+            _ => None,
+        }
+    }
+
+    /// Identifiers of all open files
+    pub fn get_file_ids(&self) -> Vec<u64> {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_file_ids(),
+            _ => vec![],
+        }
+    }
+
+    /// Up to `count` lines of the selected file starting at line `start`, the
+    /// only ones materialized for rendering
+    pub fn get_lines(&self, start: usize, count: usize) -> Vec<String> {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_lines(start, count),
+            _ => vec![],
+        }
+    }
+
+    pub fn get_line(&self, line: usize) -> Option<String> {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_line(line),
+            _ => None,
+        }
+    }
+
+    pub fn get_line_count(&self) -> usize {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_line_count(),
+            _ => 0,
+        }
+    }
+
+    /// The selected file, if it is opened in large-file mode
+    pub fn get_large_file(&self) -> Option<&LargeFile> {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_large_file(),
+            _ => None,
+        }
+    }
+
+    /// The selected file, if it is edited in the hex view
+    pub fn get_hex_buffer(&self) -> Option<&HexBuffer> {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_hex_buffer(),
+            _ => None,
+        }
+    }
+
+    pub fn get_hex_buffer_mut(&mut self) -> Option<&mut HexBuffer> {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_hex_buffer_mut(),
+            _ => None,
+        }
+    }
+
+    pub fn is_hex_view(&self) -> bool {
+        self.get_hex_buffer().is_some()
+    }
+
+    /// Put the cursor on the 1-based `line`, scrolled to the top of the view
+    pub fn goto_line(&mut self, line: usize) -> Result<(), String> {
+        let line_count = self.get_line_count();
+        if line == 0 || line > line_count {
+            return Err(format!("Line {} is out of range (1-{})", line, line_count));
+        }
+        let mut view = self.get_view();
+        view.scroll_offset.1 = line - 1;
+        view.cursor = (line - 1, 0);
+        self.set_view(view);
+        Ok(())
+    }
+
+    /// Position of the bracket matching the one under the cursor
+    pub fn get_matching_bracket(&self) -> Option<(usize, usize)> {
+        self.get_file_view()?.get_matching_bracket()
+    }
+
+    /// Move the cursor to the bracket matching the one under it
+    pub fn jump_to_matching_bracket(&mut self) -> Result<(), String> {
+        let cursor = self.get_matching_bracket().ok_or_else(|| "No matching bracket under the cursor".to_owned())?;
+        let mut view = self.get_view();
+        view.cursor = cursor;
+        self.set_view(view);
+        Ok(())
+    }
+
+    /// The word under the cursor, if any
+    pub fn get_word_under_cursor(&self) -> Option<String> {
+        if self.is_hex_view() {
+            return None;
+        }
+        let (line, column) = self.get_cursor();
+        let text = self.get_line(line)?;
+        matching::word_at(&text, column)
+    }
+
+    /// Move the cursor to the next occurrence of `pattern` on a line after
+    /// the cursor, wrapping around at the end of the file. Returns the 1-based
+    /// line found.
+    pub fn find_text(&mut self, pattern: &str) -> Result<usize, String> {
+        // Lines are looked at a chunk at a time, so that large files are never read whole
+        const CHUNK_LINES: usize = 1024;
+        let line_count = self.get_line_count();
+        let current = self.get_view().cursor.0;
+        let files_data = match self {
+            Self::Initialized { files_data, .. } => files_data,
+            _ => return Err("Not initialized".to_owned()),
+        };
+        let mut found = None;
+        for (from, to) in [(current + 1, line_count), (0, (current + 1).min(line_count))] {
+            let mut start = from;
+            while found.is_none() && start < to {
+                let lines = files_data.get_currently_selected_lines(start, CHUNK_LINES.min(to - start));
+                if lines.is_empty() {
+                    break;
+                }
+                found = lines.iter().enumerate().find_map(|(index, line)| {
+                    line.find(pattern).map(|byte| (start + index, line[..byte].chars().count()))
+                });
+                start += lines.len();
+            }
+        }
+        let (line, column) = found.ok_or_else(|| format!("Pattern not found: {}", pattern))?;
+        self.goto_line(line + 1)?;
+        let mut view = self.get_view();
+        view.cursor.1 = column;
+        self.set_view(view);
+        Ok(line + 1)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.is_currently_selected_file_read_only(),
+            _ => false,
+        }
+    }
+
     pub fn replace_text(&mut self, new_text: &str) {
         if let Self::Initialized { files_data, .. } = self {
             files_data.replace_currently_selected_file_content(new_text);
         }
     }
 
-    pub fn get_path(&mut self) -> String {
+    /// Path of the selected file, `None` when no file is open
+    pub fn get_path(&self) -> Option<&str> {
         match self {
             Self::Initialized { files_data, .. } => files_data.get_currently_selected_file_path(),
-            _ => "..loading..".to_owned(),
+            _ => None,
+        }
+    }
+
+    /// Line ending of the selected file, when it is known
+    pub fn get_line_ending(&self) -> Option<&'static str> {
+        match self {
+            Self::Initialized { files_data, .. } => files_data.get_currently_selected_line_ending(),
+            _ => None,
+        }
+    }
+
+    /// Whether the currently selected file has unsaved changes
+    pub fn is_modified(&self) -> bool {
+        match self {
+            Self::Initialized { files_data, .. } => {
+                files_data.is_modified(files_data.get_currently_selected_file_index())
+            },
+            _ => false,
+        }
+    }
+
+    /// Names of the open files with whether they are modified, for the tab
+    /// bar, and the index of the selected one
+    pub fn get_tabs(&self) -> (Vec<(String, bool)>, usize) {
+        match self {
+            Self::Initialized { files_data, .. } => {
+                let tabs = files_data
+                    .get_open_file_names()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, name)| (name, files_data.is_modified(index)))
+                    .collect();
+                (tabs, files_data.get_currently_selected_file_index())
+            },
+            _ => (vec![], 0),
         }
     }
 
-    pub fn get_all_open_file_names(&self) -> String {
+    /// The selected file as seen through its own view
+    pub fn get_file_view(&self) -> Option<FileView<'_>> {
         match self {
-            Self::Initialized { files_data, .. } => files_data.get_open_file_names().join(", "),
-            _ => "..loading..".to_owned(),
+            Self::Initialized { files_data, .. } => {
+                let index = files_data.get_currently_selected_file_index();
+                Some(FileView::new(files_data, index, files_data.get_view(index)))
+            },
+            _ => None,
         }
     }
 
-    pub fn get_scroll_offset(&self) -> &(u16, u16) {
+    /// Scroll offset and cursor of the selected file
+    pub fn get_view(&self) -> ViewState {
         match self {
-            Self::Initialized { scroll_offset, .. } => scroll_offset,
-            _ => &(0, 0),
+            Self::Initialized { files_data, .. } => files_data.get_view(files_data.get_currently_selected_file_index()),
+            _ => ViewState::default(),
+        }
+    }
+
+    fn set_view(&mut self, view: ViewState) {
+        if let Self::Initialized { files_data, .. } = self {
+            files_data.set_view(files_data.get_currently_selected_file_index(), view);
         }
     }
 
+    pub fn get_scroll_offset(&self) -> (usize, usize) {
+        self.get_view().scroll_offset
+    }
+
+    /// Cursor of the selected file as `(line, column)`
+    pub fn get_cursor(&self) -> (usize, usize) {
+        self.get_view().cursor
+    }
+
+    /// In write mode the cursor moves by `delta` lines. Otherwise the view
+    /// scrolls, dragging the cursor along.
     pub fn scroll_vertical(&mut self, delta: i32) -> Result<(), String> {
-        let text = self.get_text();
-        if let Self::Initialized { scroll_offset, .. } = self {
-            let (x, y) = scroll_offset;
+        // The hex view moves its cursor instead, and scrolls along with it
+        if let Some(hex_buffer) = self.get_hex_buffer_mut() {
+            return hex_buffer.move_cursor(delta as isize * BYTES_PER_ROW as isize);
+        }
+        let line_count = self.get_line_count();
+        let write_mode = self.is_write_mode();
+        let files_data = match self {
+            Self::Initialized { files_data, .. } => files_data,
+            _ => return Err("Not initialized".to_owned()),
+        };
+        let index = files_data.get_currently_selected_file_index();
+        let mut view = files_data.get_view(index);
+        let cursor_line_count = files_data.get_currently_selected_cursor_line_count();
+        let line_len = |line| files_data.get_currently_selected_line_len(line);
+        if write_mode {
+            view.cursor = editing::move_vertical(view.cursor, delta, cursor_line_count, line_len)?;
+        } else {
+            let (x, y) = view.scroll_offset;
             if delta > 0 {
-                if *y < text.lines().count() as u16 {
-                    *scroll_offset = (*x, *y + delta as u16);
-                    Ok(())
+                if y < line_count {
+                    view.scroll_offset = (x, y + delta as usize);
                 } else {
-                    Err("Cannot scroll past end of file".to_owned())
+                    return Err("Cannot scroll past end of file".to_owned());
                 }
             } else if delta < 0 {
-                if *y > 0 {
-                    *scroll_offset = (*x, (*y as i32 + delta).max(0) as u16);
-                    Ok(())
+                if y > 0 {
+                    view.scroll_offset = (x, y.saturating_sub(delta.unsigned_abs() as usize));
                 } else {
-                    Err("Cannot scroll past start of file".to_owned())
+                    return Err("Cannot scroll past start of file".to_owned());
                 }
-            } else {
-                Ok(())
             }
-        } else {
-            Err("Not initialized".to_owned())
+            let line = (view.cursor.0 as i64 + delta as i64).clamp(0, cursor_line_count.max(1) as i64 - 1) as usize;
+            view.cursor = (line, view.cursor.1.min(line_len(line)));
         }
+        files_data.set_view(index, view);
+        Ok(())
     }
 
+    /// In write mode the cursor moves by `delta` characters. Otherwise the
+    /// view scrolls, dragging the cursor along.
     pub fn scroll_horizontal(&mut self, delta: i32) -> Result<(), String> {
-        let text = self.get_text();
-        if let Self::Initialized { scroll_offset, .. } = self {
-            let (x, y) = scroll_offset;
+        if let Some(hex_buffer) = self.get_hex_buffer_mut() {
+            return hex_buffer.move_cursor(delta as isize);
+        }
+        let write_mode = self.is_write_mode();
+        let files_data = match self {
+            Self::Initialized { files_data, .. } => files_data,
+            _ => return Err("Not initialized".to_owned()),
+        };
+        let index = files_data.get_currently_selected_file_index();
+        let mut view = files_data.get_view(index);
+        let line_len = files_data.get_currently_selected_line_len(view.cursor.0);
+        if write_mode {
+            view.cursor = editing::move_horizontal(view.cursor, delta, line_len)?;
+        } else {
+            let (x, y) = view.scroll_offset;
             if delta > 0 {
-                if *x < text.lines().nth(*y as usize).unwrap().len() as u16 {
-                    *scroll_offset = (*x + delta as u16, *y);
-                    Ok(())
+                if x < line_len {
+                    view.scroll_offset = (x + delta as usize, y);
                 } else {
-                    Err("Cannot scroll past end of line".to_owned())
+                    return Err("Cannot scroll past end of line".to_owned());
                 }
             } else if delta < 0 {
-                if *x > 0 {
-                    *scroll_offset = ((*x as i32 + delta).max(0) as u16, *y);
-                    Ok(())
+                if x > 0 {
+                    view.scroll_offset = (x.saturating_sub(delta.unsigned_abs() as usize), y);
                 } else {
-                    Err("Cannot scroll past start of line".to_owned())
+                    return Err("Cannot scroll past start of line".to_owned());
                 }
-            } else {
-                Ok(())
             }
-        } else {
-            Err("Not initialized".to_owned())
+            let column = (view.cursor.1 as i64 + delta as i64).clamp(0, line_len as i64) as usize;
+            view.cursor = (view.cursor.0, column);
         }
+        files_data.set_view(index, view);
+        Ok(())
     }
 
-    pub fn reset_scroll(&mut self) {
-        if let Self::Initialized { scroll_offset, .. } = self {
-            *scroll_offset = (0, 0);
+    /// Insert `c` at the cursor of the selected file
+    pub fn insert_char(&mut self, c: char) {
+        if let Self::Initialized { files_data, .. } = self {
+            files_data.insert_char(c);
         }
     }
-}
 
-impl Default for AppState {
-    fn default() -> Self {
-        Self::Init
+    /// Delete the character before the cursor of the selected file
+    pub fn delete_char_before(&mut self) {
+        if let Self::Initialized { files_data, .. } = self {
+            files_data.delete_char_before();
+        }
     }
 }
+
+/// An open file as shown in a window, which need not be the selected file,
+/// seen through the window's view rather than the file's own
+pub struct FileView<'a> {
+    files_data: &'a OpenFilesData,
+    index: usize,
+    pub view: ViewState,
+}
+
+impl<'a> FileView<'a> {
+    pub fn new(files_data: &'a OpenFilesData, index: usize, view: ViewState) -> Self {
+        Self { files_data, index, view }
+    }
+
+    /// Identifier, path and text of the file when it is held in memory as
+    /// text, for highlighting
+    pub fn get_source(&self) -> Option<(u64, &'a str, &'a str)> {
+        self.files_data.get_source(self.index)
+    }
+
+    /// Up to `count` lines starting at line `start`, the only ones
+    /// materialized for rendering
+    pub fn get_lines(&self, start: usize, count: usize) -> Vec<String> {
+        self.files_data.get_lines(self.index, start, count)
+    }
+
+    pub fn get_line(&self, line: usize) -> Option<String> {
+        self.files_data.get_line(self.index, line)
+    }
+
+    pub fn get_line_count(&self) -> usize {
+        self.files_data.get_line_count(self.index)
+    }
+
+    /// The file, if it is opened in large-file mode
+    pub fn get_large_file(&self) -> Option<&'a LargeFile> {
+        self.files_data.get_large_file(self.index)
+    }
+
+    /// The file, if it is edited in the hex view
+    pub fn get_hex_buffer(&self) -> Option<&'a HexBuffer> {
+        self.files_data.get_hex_buffer(self.index)
+    }
+
+    pub fn is_hex_view(&self) -> bool {
+        self.get_hex_buffer().is_some()
+    }
+
+    pub fn get_scroll_offset(&self) -> (usize, usize) {
+        self.view.scroll_offset
+    }
+
+    /// Cursor as `(line, column)`
+    pub fn get_cursor(&self) -> (usize, usize) {
+        self.view.cursor
+    }
+
+    /// Position of the bracket matching the one under the cursor, looked for
+    /// within `MAX_BRACKET_LINES` lines of it
+    pub fn get_matching_bracket(&self) -> Option<(usize, usize)> {
+        const MAX_BRACKET_LINES: usize = 5000;
+        if self.is_hex_view() {
+            return None;
+        }
+        let (line, column) = self.get_cursor();
+        // This runs on every frame, and mostly there is no bracket under the cursor
+        let forward = matching::scans_forward(self.get_line(line)?.chars().nth(column)?)?;
+        let lines = self.files_data.get_lines_from(self.index, line, forward).take(MAX_BRACKET_LINES + 1);
+        let (distance, found) = matching::matching_bracket(lines, column)?;
+        Some((if forward { line + distance } else { line - distance }, found))
+    }
+
+    /// Scroll just enough for the cursor to be within the `visible_rows` and
+    /// `visible_columns` in view. When soft-wrapped, lines take as many rows as
+    /// they wrap to and the view never scrolls horizontally.
+    pub fn fit_view(&mut self, visible_rows: usize, visible_columns: usize, settings: &Settings) {
+        let mut view = self.view;
+        let (x, y) = &mut view.scroll_offset;
+        let (line, column) = view.cursor;
+        // Lines are laid out in cells as drawn, tabs expanded
+        let layout = |text: &str| {
+            let chars: Vec<(char, ())> = text.chars().map(|c| (c, ())).collect();
+            whitespace::expand(&chars, settings, (), ())
+        };
+        if line < *y {
+            *y = line;
+        } else if visible_rows > 0 && line >= *y + visible_rows {
+            *y = line + 1 - visible_rows;
+        }
+        if settings.wrap == WrapMode::Soft {
+            *x = 0;
+            // Lines take a row at least, so only those from the cursor line
+            // up to a view's height above it can share the view with it
+            if visible_rows > 0 {
+                // The same lines are asked for as are drawn, which keeps the
+                // viewport of a large file cached
+                let wrapped: Vec<_> = self
+                    .get_lines(*y, visible_rows)
+                    .iter()
+                    .take(line + 1 - *y)
+                    .map(|text| {
+                        let cells = layout(text);
+                        let indent = wrap::continuation_indent(cells.indent, visible_columns);
+                        let rows = wrap::wrap_line(&cells.cells, visible_columns, indent, |cell| cell.is_whitespace);
+                        (rows, cells.cursor_cell(column))
+                    })
+                    .collect();
+                let mut heights: Vec<usize> = wrapped.iter().map(|(rows, _)| rows.len()).collect();
+                // The cursor may be on the empty line after a trailing line break
+                heights.resize(line + 1 - *y, 1);
+                let cursor_rows = wrapped
+                    .get(line - *y)
+                    .map_or(1, |(rows, cursor_cell)| wrap::cursor_row(rows, *cursor_cell) + 1);
+                let mut rows = heights[..heights.len() - 1].iter().sum::<usize>() + cursor_rows;
+                let mut top = 0;
+                while rows > visible_rows && top + 1 < heights.len() {
+                    rows -= heights[top];
+                    top += 1;
+                }
+                *y += top;
+            }
+        } else {
+            let cursor_cell = self.get_line(line).map_or(0, |text| layout(&text).cursor_cell(column));
+            if cursor_cell < *x {
+                *x = cursor_cell;
+            } else if visible_columns > 0 && cursor_cell >= *x + visible_columns {
+                *x = cursor_cell + 1 - visible_columns;
+            }
+        }
+        self.view = view;
+    }
+}
\ No newline at end of file
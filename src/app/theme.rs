@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use tui::style::{Color, Modifier, Style};
+
+/// Themes built into the binary, by name
+const BUILT_IN_THEMES: [(&str, &str); 4] = [
+    ("dark", include_str!("../../assets/themes/dark.toml")),
+    ("light", include_str!("../../assets/themes/light.toml")),
+    ("high-contrast", include_str!("../../assets/themes/high-contrast.toml")),
+    ("no-color", include_str!("../../assets/themes/no-color.toml")),
+];
+
+/// Styles of every element of the UI, and of the syntax scopes highlighted in
+/// the body. Themes are written in TOML, styles as a foreground color,
+/// optionally followed by `on` and a background color, and by modifiers, e.g.
+/// `"black on lightcyan bold"`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Text of the body
+    pub text: Style,
+    pub border: Style,
+    /// Border of the focused window, once the body is split
+    pub focused_border: Style,
+    pub cursor: Style,
+    pub gutter: Style,
+    /// Line number of the cursor line
+    pub gutter_cursor_line: Style,
+    /// Background of the line holding the cursor
+    pub cursor_line: Style,
+    /// Bracket matching the one under the cursor
+    pub matching_bracket: Style,
+    /// Other occurrences of the word under the cursor
+    pub word_highlight: Style,
+    /// Whitespace glyphs shown in list mode
+    pub whitespace: Style,
+    pub indent_guide: Style,
+    pub scrollbar_thumb: Style,
+    /// Marks on the scrollbar of changed lines, search matches and diagnostics
+    pub scrollbar_modified: Style,
+    pub scrollbar_search: Style,
+    pub scrollbar_diagnostic: Style,
+    pub minimap: Style,
+    /// Rows of the minimap standing for the lines in view
+    pub minimap_view: Style,
+    pub tab: Style,
+    pub selected_tab: Style,
+    pub modified_tab: Style,
+    pub status: Style,
+    pub write_mode: Style,
+    pub input_mode: Style,
+    pub command_line: Style,
+    /// Messages on the message line and in their history, by severity
+    pub message_info: Style,
+    pub message_warning: Style,
+    pub message_error: Style,
+    pub help_key: Style,
+    pub help_text: Style,
+    /// Selected entry of lists, e.g. of the command palette
+    pub selection: Style,
+    pub prompt: Style,
+    pub prompt_border: Style,
+    pub diff_added: Style,
+    pub diff_removed: Style,
+    pub diff_hunk: Style,
+    pub diff_context: Style,
+    pub hex_offset: Style,
+    pub hex_byte: Style,
+    /// Nibble of the hex cursor being typed over
+    pub hex_nibble: Style,
+    /// Shown when the terminal is too small
+    pub warning: Style,
+    pub logs: Style,
+    pub log_error: Style,
+    pub log_warn: Style,
+    pub log_info: Style,
+    pub log_debug: Style,
+    pub log_trace: Style,
+    /// Styles of the syntax scopes matching each selector, e.g. `"comment"` or
+    /// `"entity.name.function, support.function"`. The most specific match wins.
+    pub syntax: Vec<(String, Style)>,
+}
+
+/// A theme as written in TOML
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    /// Theme the styles not given are taken from
+    inherits: Option<String>,
+    #[serde(default)]
+    ui: BTreeMap<String, String>,
+    #[serde(default)]
+    syntax: BTreeMap<String, String>,
+}
+
+impl Theme {
+    /// The theme used until another is picked: `no-color` when the
+    /// `NO_COLOR` environment variable is set, `dark` otherwise
+    pub fn initial() -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self::built_in(if no_color { "no-color" } else { "dark" }).unwrap_or_default()
+    }
+
+    pub fn built_in_names() -> Vec<&'static str> {
+        BUILT_IN_THEMES.iter().map(|(name, _)| *name).collect()
+    }
+
+    pub fn built_in(name: &str) -> Option<Self> {
+        let (_, source) = BUILT_IN_THEMES.iter().find(|(built_in, _)| *built_in == name)?;
+        Self::parse(name, source).ok()
+    }
+
+    /// Parse a theme written in TOML, named `name`
+    pub fn parse(name: &str, source: &str) -> Result<Self, String> {
+        let file: ThemeFile = toml::from_str(source).map_err(|err| format!("Invalid theme {}: {}", name, err))?;
+        let mut theme = match file.inherits.as_deref() {
+            Some(inherits) if inherits != name => {
+                Self::built_in(inherits).ok_or_else(|| format!("Unknown theme {} inherited by {}", inherits, name))?
+            },
+            Some(_) => return Err(format!("Theme {} cannot inherit itself", name)),
+            None => Self::default(),
+        };
+        theme.name = name.to_owned();
+        for (element, style) in file.ui.iter() {
+            let field = theme
+                .style_mut(element)
+                .ok_or_else(|| format!("Unknown UI element {} in theme {}", element, name))?;
+            *field = parse_style(style)?;
+        }
+        for (selector, style) in file.syntax.iter() {
+            let style = parse_style(style)?;
+            match theme.syntax.iter_mut().find(|(existing, _)| existing == selector) {
+                Some((_, existing)) => *existing = style,
+                None => theme.syntax.push((selector.clone(), style)),
+            }
+        }
+        Ok(theme)
+    }
+
+    fn style_mut(&mut self, element: &str) -> Option<&mut Style> {
+        let style = match element {
+            "text" => &mut self.text,
+            "border" => &mut self.border,
+            "focused_border" => &mut self.focused_border,
+            "cursor" => &mut self.cursor,
+            "gutter" => &mut self.gutter,
+            "gutter_cursor_line" => &mut self.gutter_cursor_line,
+            "cursor_line" => &mut self.cursor_line,
+            "matching_bracket" => &mut self.matching_bracket,
+            "word_highlight" => &mut self.word_highlight,
+            "whitespace" => &mut self.whitespace,
+            "indent_guide" => &mut self.indent_guide,
+            "scrollbar_thumb" => &mut self.scrollbar_thumb,
+            "scrollbar_modified" => &mut self.scrollbar_modified,
+            "scrollbar_search" => &mut self.scrollbar_search,
+            "scrollbar_diagnostic" => &mut self.scrollbar_diagnostic,
+            "minimap" => &mut self.minimap,
+            "minimap_view" => &mut self.minimap_view,
+            "tab" => &mut self.tab,
+            "selected_tab" => &mut self.selected_tab,
+            "modified_tab" => &mut self.modified_tab,
+            "status" => &mut self.status,
+            "write_mode" => &mut self.write_mode,
+            "input_mode" => &mut self.input_mode,
+            "command_line" => &mut self.command_line,
+            "message_info" => &mut self.message_info,
+            "message_warning" => &mut self.message_warning,
+            "message_error" => &mut self.message_error,
+            "help_key" => &mut self.help_key,
+            "help_text" => &mut self.help_text,
+            "selection" => &mut self.selection,
+            "prompt" => &mut self.prompt,
+            "prompt_border" => &mut self.prompt_border,
+            "diff_added" => &mut self.diff_added,
+            "diff_removed" => &mut self.diff_removed,
+            "diff_hunk" => &mut self.diff_hunk,
+            "diff_context" => &mut self.diff_context,
+            "hex_offset" => &mut self.hex_offset,
+            "hex_byte" => &mut self.hex_byte,
+            "hex_nibble" => &mut self.hex_nibble,
+            "warning" => &mut self.warning,
+            "logs" => &mut self.logs,
+            "log_error" => &mut self.log_error,
+            "log_warn" => &mut self.log_warn,
+            "log_info" => &mut self.log_info,
+            "log_debug" => &mut self.log_debug,
+            "log_trace" => &mut self.log_trace,
+            _ => return None,
+        };
+        Some(style)
+    }
+}
+
+/// Where the user theme `name` is looked for: `<config dir>/rust_text_editor/themes/<name>.toml`
+pub fn user_theme_path(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rust_text_editor").join("themes").join(format!("{}.toml", name)))
+}
+
+/// Parse a style such as `"black on lightcyan bold"`
+pub fn parse_style(value: &str) -> Result<Style, String> {
+    let mut style = Style::default();
+    let mut words = value.split_whitespace();
+    while let Some(word) = words.next() {
+        style = match word {
+            "on" => {
+                let background = words.next().ok_or_else(|| format!("Missing background color in style {}", value))?;
+                style.bg(parse_color(background)?)
+            },
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style.add_modifier(Modifier::REVERSED),
+            color => style.fg(parse_color(color)?),
+        };
+    }
+    Ok(style)
+}
+
+/// Parse a named color, a `#rrggbb` one or an index in the 256-color palette
+fn parse_color(value: &str) -> Result<Color, String> {
+    let color = match value {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" => Color::Gray,
+        "darkgray" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => match value.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => {
+                let rgb = u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid color {}", value))?;
+                Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+            },
+            _ => Color::Indexed(value.parse().map_err(|_| format!("Invalid color {}", value))?),
+        },
+    };
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_every_built_in_theme() {
+        for (name, source) in BUILT_IN_THEMES.iter() {
+            let theme = Theme::parse(name, source).unwrap();
+            assert_eq!(theme.name, *name);
+        }
+        let no_color = Theme::built_in("no-color").unwrap();
+        assert!(no_color.syntax.is_empty());
+        assert_eq!(no_color.text, Style::default());
+    }
+
+    #[test]
+    fn should_inherit_styles_not_given_by_user_themes() {
+        let source = "inherits = \"dark\"\n[ui]\ncursor = \"black on #ff8000 bold\"\n[syntax]\ncomment = \"240\"\n";
+        let theme = Theme::parse("mine", source).unwrap();
+        let dark = Theme::built_in("dark").unwrap();
+        assert_eq!(
+            theme.cursor,
+            Style::default().fg(Color::Black).bg(Color::Rgb(255, 128, 0)).add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(theme.text, dark.text);
+        assert_eq!(theme.syntax.len(), dark.syntax.len());
+        assert!(theme.syntax.contains(&("comment".to_owned(), Style::default().fg(Color::Indexed(240)))));
+
+        assert!(Theme::parse("mine", "[ui]\ncursour = \"red\"").is_err());
+        assert!(Theme::parse("mine", "[ui]\ncursor = \"redd\"").is_err());
+    }
+}
@@ -1,13 +1,32 @@
+use std::ops::Range;
+use std::time::Instant;
+
 use tui::backend::Backend;
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
-use tui::style::{Color, Style};
+use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
-use tui::widgets::{Block, BorderType, Borders, Cell, Paragraph, Row, Table, Wrap};
+use tui::widgets::{Block, BorderType, Borders, Cell, Clear, Paragraph, Row, Table, Tabs, Wrap};
 use tui::{Frame};
 use tui_logger::TuiLoggerWidget;
 
 use super::actions::Actions;
-use super::state::AppState;
+use super::diff::DiffKind;
+use super::help::{self, HelpOverlay};
+use super::keymap::{self, Mode};
+use super::hex_buffer::{printable, HexBuffer, BYTES_PER_ROW};
+use super::highlight::{Highlighter, StyledLine};
+use super::large_file::LargeFile;
+use super::matching;
+use super::messages::{Message, Messages, MessagesView, Severity};
+use super::overview::{self, Mark};
+use super::palette::Palette;
+use super::panels::{Panels, LOGS_MIN_HEIGHT};
+use super::paths;
+use super::prompt::Prompt;
+use super::settings::{LineNumbers, Settings, WrapMode};
+use super::state::{AppState, FileView};
+use super::theme::Theme;
+use super::{whitespace, wrap};
 use crate::app::App;
 
 pub fn draw<B>(rect: &mut Frame<B>, app: &mut App)
@@ -15,99 +34,686 @@ where
     B: Backend,
 {
     let size: Rect = rect.size();
-    check_size(&size);
-
-    // Vertical layout
-    let chunks: Vec<Rect> = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-                Constraint::Length(3),
-                Constraint::Min(10),
-                Constraint::Length(12),
-            ]
-            .as_ref(),
-        )
-        .split(size);
+    // Drawing needs the app mutably, so the theme is taken out for the frame
+    let theme = app.theme().clone();
+    if size.width < MIN_BODY_WIDTH || size.height < MIN_BODY_HEIGHT + STATUS_HEIGHT + MESSAGE_HEIGHT {
+        rect.render_widget(draw_too_small(&size, &theme), size);
+        return;
+    }
+    let areas = layout(&size, app.panels());
 
     // Title
-    let title: Paragraph = draw_title(&mut app.state);
-    rect.render_widget(title, chunks[0]);
+    if let Some(area) = areas.title {
+        let tabs: Tabs = draw_tabs(&mut app.state, area.width, &theme);
+        rect.render_widget(tabs, area);
+    }
 
-    // Body & Help 
-    let body_chunks: Vec<Rect> = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Min(20), Constraint::Length(32)].as_ref())
-        .split(chunks[1]);
+    // Body, one window at a time, each showing its own file and view
+    let focused = app.windows().focused();
+    let windows = app.windows_mut().layout(areas.body);
+    let open_file_ids = app.state.get_file_ids();
+    app.highlighter.retain_files(&open_file_ids);
+    let border_style = |index: usize| {
+        if windows.len() > 1 && index == focused {
+            theme.focused_border
+        } else {
+            theme.border
+        }
+    };
+    for &(index, area) in windows.iter() {
+        draw_window(rect, app, index, area, &theme, border_style(index));
+    }
 
-    let body: Paragraph = draw_body(app.is_loading(), app.state());
-    rect.render_widget(body, body_chunks[0]);
+    let pending_keys = keymap::format_sequence(app.pending_keys());
+    let status_line: Paragraph = draw_status_line(&app.state, &pending_keys, areas.status.width, &theme);
+    rect.render_widget(status_line, areas.status);
 
-    let help: Table = draw_help(app.actions());
-    rect.render_widget(help, body_chunks[1]);
+    // Command line, in place of the last message while it is open
+    match app.command_line() {
+        Some(command_line) => rect.render_widget(draw_command_line(command_line, &theme), areas.message),
+        None => {
+            if let Some(message) = app.messages().current(Instant::now()) {
+                rect.render_widget(draw_message(message, &theme), areas.message);
+            }
+        },
+    }
+
+    if let Some(area) = areas.help {
+        let help: Table = draw_help(app.actions(), &theme);
+        rect.render_widget(help, area);
+    }
 
     // Logs
-    let logs: TuiLoggerWidget = draw_logs();
-    rect.render_widget(logs, chunks[2]);
-}
-
-fn draw_title<'a>(state: &mut AppState) -> Paragraph<'a> {
-    let mut title: String = "Rust Text Editor: ".to_owned();
-    title.push_str(&state.get_path());
-    title.push_str(" [");
-    title.push_str(&state.get_all_open_file_names());
-    title.push_str("]");
-    Paragraph::new(title)
-        .style(Style::default().fg(Color::LightCyan))
-        .alignment(Alignment::Center)
+    if let Some(area) = areas.logs {
+        let logs: TuiLoggerWidget = draw_logs(&theme);
+        rect.render_widget(logs, area);
+    }
+
+    // Full-screen help, over everything but prompts
+    if let Some(overlay) = app.help_overlay() {
+        let help: Paragraph = draw_help_overlay(app.actions(), overlay, &theme);
+        rect.render_widget(Clear, size);
+        rect.render_widget(help, size);
+    }
+
+    // History of messages
+    if let Some(view) = app.messages_view() {
+        let messages: Paragraph = draw_messages_view(app.messages(), view, &theme);
+        rect.render_widget(Clear, size);
+        rect.render_widget(messages, size);
+    }
+
+    // Command palette
+    if let Some(palette) = app.palette() {
+        let area: Rect = palette_area(&size);
+        let palette: Paragraph = draw_palette(palette, area.height.saturating_sub(3) as usize, &theme);
+        rect.render_widget(Clear, area);
+        rect.render_widget(palette, area);
+    }
+
+    // Prompt, on top of everything else
+    if let Some(prompt) = app.prompt() {
+        let area: Rect = prompt_area(prompt, &size);
+        let prompt: Paragraph = draw_prompt(prompt, &theme);
+        rect.render_widget(Clear, area);
+        rect.render_widget(prompt, area);
+    }
+}
+
+/// Columns of the minimap, borders included
+const MINIMAP_WIDTH: u16 = 12;
+/// Narrowest window the minimap is shown in, leaving the rest to the body
+const MINIMAP_MIN_WINDOW_WIDTH: u16 = 48;
+
+/// The file of the window at `window`, with the window's view fitted to the area
+fn draw_window<B>(rect: &mut Frame<B>, app: &mut App, window: usize, area: Rect, theme: &Theme, border_style: Style)
+where
+    B: Backend,
+{
+    let (file_index, view) = match app.window_file(window) {
+        Some(file) if !app.is_loading() => file,
+        _ => {
+            let loading = Paragraph::new(Span::raw("..loading")).style(theme.text);
+            let block = draw_body_block(app.state().get_large_file(), border_style);
+            rect.render_widget(loading.block(block), area);
+            return;
+        },
+    };
+    // Only the focused window highlights the word under its cursor
+    let highlighted_word = app.highlighted_word().filter(|_| window == app.windows().focused()).map(str::to_owned);
+    let last_search = app.last_search().cloned();
+    let App {
+        state,
+        settings,
+        highlighter,
+        ..
+    } = app;
+    let files_data = match state {
+        AppState::Initialized { files_data, .. } => files_data,
+        AppState::Init => return,
+    };
+    let visible_rows = area.height.saturating_sub(2) as usize;
+    if let Some(hex_buffer) = files_data.get_hex_buffer_mut(file_index) {
+        hex_buffer.scroll_to_cursor(visible_rows);
+    }
+    let mut marks: Vec<(usize, Mark)> = vec![];
+    if settings.scrollbar {
+        marks.extend(files_data.get_changed_lines(file_index).into_iter().map(|line| (line, Mark::Modified)));
+        if let Some(pattern) = &last_search {
+            let matches = files_data.get_matching_lines(file_index, pattern);
+            marks.extend(matches.into_iter().map(|line| (line, Mark::SearchMatch)));
+        }
+        let diagnostics = files_data.get_diagnostic_lines(file_index);
+        marks.extend(diagnostics.iter().map(|line| (*line, Mark::Diagnostic)));
+    }
+    let mut file = FileView::new(files_data, file_index, view);
+
+    let has_text = file.get_hex_buffer().is_none();
+    // Large files are not read whole, so they get no minimap
+    let show_minimap = settings.minimap && file.get_source().is_some();
+    let (area, minimap_area) = if has_text && show_minimap && area.width >= MINIMAP_MIN_WINDOW_WIDTH {
+        let body = Rect {
+            width: area.width - MINIMAP_WIDTH,
+            ..area
+        };
+        let minimap = Rect {
+            x: body.right(),
+            width: MINIMAP_WIDTH,
+            ..area
+        };
+        (body, Some(minimap))
+    } else {
+        (area, None)
+    };
+    let text_width = area.width.saturating_sub(2 + gutter_width(&file, settings));
+    file.fit_view(visible_rows, text_width as usize, settings);
+    let body: Paragraph = match file.get_hex_buffer() {
+        Some(hex_buffer) => draw_hex_body(hex_buffer, &area, theme, border_style),
+        None => draw_body(&file, settings, highlighter, highlighted_word.as_deref(), &area, theme, border_style),
+    };
+    rect.render_widget(body, area);
+
+    if has_text {
+        let line_count = file.get_line_count();
+        let (_, scroll_y) = file.get_scroll_offset();
+        let view = scroll_y..(scroll_y + visible_rows).min(line_count.max(scroll_y + 1));
+        if settings.scrollbar && area.height > 2 {
+            let rows = overview::scrollbar(line_count, view.clone(), &marks, visible_rows);
+            let scrollbar_area = Rect::new(area.right() - 1, area.y + 1, 1, visible_rows as u16);
+            rect.render_widget(draw_scrollbar(&rows, theme, border_style), scrollbar_area);
+        }
+        if let Some(minimap_area) = minimap_area {
+            let minimap = draw_minimap(&file, view, minimap_area, settings.tab_width, theme, border_style);
+            rect.render_widget(minimap, minimap_area);
+        }
+    }
+    let view = file.view;
+    app.set_window_view(window, view);
+}
+
+/// The track on the right border of the body, with the thumb over it and marks
+fn draw_scrollbar<'a>(rows: &[overview::ScrollbarRow], theme: &Theme, border_style: Style) -> Paragraph<'a> {
+    let lines: Vec<Spans> = rows
+        .iter()
+        .map(|row| {
+            let track_style = if row.thumb { theme.scrollbar_thumb } else { border_style };
+            let span = match row.mark {
+                Some(Mark::Diagnostic) => Span::styled("●", track_style.patch(theme.scrollbar_diagnostic)),
+                Some(Mark::SearchMatch) => Span::styled("◆", track_style.patch(theme.scrollbar_search)),
+                Some(Mark::Modified) => Span::styled("▪", track_style.patch(theme.scrollbar_modified)),
+                None if row.thumb => Span::styled("┃", track_style),
+                None => Span::styled("│", track_style),
+            };
+            Spans::from(span)
+        })
+        .collect();
+    Paragraph::new(lines)
+}
+
+/// The whole file in braille, the rows standing for the lines in view set apart
+fn draw_minimap<'a>(
+    file: &FileView,
+    view: Range<usize>,
+    area: Rect,
+    tab_width: usize,
+    theme: &Theme,
+    border_style: Style,
+) -> Paragraph<'a> {
+    let width = area.width.saturating_sub(2) as usize;
+    let height = area.height.saturating_sub(2) as usize;
+    let rows = match file.get_source() {
+        Some((_, _, text)) => overview::minimap(text.lines(), file.get_line_count(), view, width, height, tab_width),
+        None => vec![],
+    };
+    let lines: Vec<Spans> = rows
+        .into_iter()
+        .map(|(row, in_view)| Spans::from(Span::styled(row, if in_view { theme.minimap_view } else { theme.minimap })))
+        .collect();
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain)
+            .style(border_style),
+    )
+}
+
+/// One tab per open file, scrolled to keep the selected one in view
+fn draw_tabs<'a>(state: &mut AppState, width: u16, theme: &Theme) -> Tabs<'a> {
+    let (tabs, selected) = state.get_tabs();
+    let titles: Vec<String> = tabs
+        .iter()
+        .enumerate()
+        .map(|(index, (name, modified))| format!("{}:{}{}", index + 1, name, if *modified { " [+]" } else { "" }))
+        .collect();
+    let widths: Vec<usize> = titles.iter().map(|title| title.chars().count()).collect();
+    let visible = visible_tabs(&widths, selected, width.saturating_sub(2) as usize);
+
+    let mut block_title = "Rust Text Editor".to_owned();
+    if visible.start > 0 {
+        block_title.push_str(&format!(" ◀ {}", visible.start));
+    }
+    if visible.end < titles.len() {
+        block_title.push_str(&format!(" {} ▶", titles.len() - visible.end));
+    }
+    let modified_style = theme.modified_tab;
+    let titles: Vec<Spans> = titles
+        .into_iter()
+        .zip(tabs.iter())
+        .skip(visible.start)
+        .take(visible.len())
+        .map(|(title, (_, modified))| {
+            if *modified {
+                Spans::from(Span::styled(title, modified_style))
+            } else {
+                Spans::from(title)
+            }
+        })
+        .collect();
+    Tabs::new(titles)
+        .select(selected.saturating_sub(visible.start))
+        .style(theme.tab)
+        .highlight_style(theme.selected_tab)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
-                .border_type(BorderType::Plain),
+                .style(theme.border)
+                .border_type(BorderType::Plain)
+                .title(block_title),
         )
 }
 
-fn check_size(rect: &Rect) {
-    if rect.width < 52 {
-        panic!("Require width >= 52, (got {})", rect.width);
+/// Range of the tabs fitting in `available` columns around the selected
+/// one. Tabs take their width, a space on either side and a divider.
+fn visible_tabs(widths: &[usize], selected: usize, available: usize) -> Range<usize> {
+    if widths.is_empty() {
+        return 0..0;
     }
-    if rect.height < 28 {
-        panic!("Require height >= 28, (got {})", rect.height);
+    let selected = selected.min(widths.len() - 1);
+    let cost = |index: usize| widths[index] + 3;
+    let (mut start, mut end) = (selected, selected + 1);
+    let mut used = cost(selected);
+    loop {
+        let mut grown = false;
+        if end < widths.len() && used + cost(end) <= available {
+            used += cost(end);
+            end += 1;
+            grown = true;
+        }
+        if start > 0 && used + cost(start - 1) <= available {
+            used += cost(start - 1);
+            start -= 1;
+            grown = true;
+        }
+        if !grown {
+            return start..end;
+        }
     }
 }
 
-fn draw_body<'a>(loading: bool, state: &AppState) -> Paragraph<'a> {
-    let initialized_text = if !loading && state.is_initialized() {
-        state.get_text().to_owned()
+/// Smallest terminal the body can be drawn in, with a line of text inside its borders
+const MIN_BODY_WIDTH: u16 = 12;
+const MIN_BODY_HEIGHT: u16 = 3;
+/// Room the body keeps before the panes around it get any
+const BODY_MIN_WIDTH: u16 = 20;
+const BODY_MIN_HEIGHT: u16 = 10;
+const TITLE_HEIGHT: u16 = 3;
+const STATUS_HEIGHT: u16 = 1;
+const MESSAGE_HEIGHT: u16 = 1;
+
+/// Where the panes go. The body always gets its room first: narrow terminals
+/// lose the help pane, while short ones shrink and then lose the logs, and
+/// lose the title last. The help and log panes are only there when the
+/// user has not hidden them.
+struct Areas {
+    title: Option<Rect>,
+    body: Rect,
+    /// Below the body, always shown
+    status: Rect,
+    /// Below the status line, for the last message or the command line
+    message: Rect,
+    help: Option<Rect>,
+    logs: Option<Rect>,
+}
+
+fn layout(size: &Rect, panels: &Panels) -> Areas {
+    let lines_height = STATUS_HEIGHT + MESSAGE_HEIGHT;
+    let show_title = size.height >= TITLE_HEIGHT + BODY_MIN_HEIGHT + lines_height;
+    let room_for_logs =
+        size.height.saturating_sub(BODY_MIN_HEIGHT + lines_height + if show_title { TITLE_HEIGHT } else { 0 });
+    let logs_height = Some(panels.logs.size.min(room_for_logs))
+        .filter(|height| panels.logs.visible && *height >= LOGS_MIN_HEIGHT);
+    let help_width = panels.help.size;
+    let show_help = panels.help.visible && size.width >= help_width + BODY_MIN_WIDTH;
+
+    // Vertical layout
+    let mut constraints = vec![
+        Constraint::Min(BODY_MIN_HEIGHT.min(size.height - lines_height)),
+        Constraint::Length(STATUS_HEIGHT),
+        Constraint::Length(MESSAGE_HEIGHT),
+    ];
+    if show_title {
+        constraints.insert(0, Constraint::Length(TITLE_HEIGHT));
+    }
+    if let Some(logs_height) = logs_height {
+        constraints.push(Constraint::Length(logs_height));
+    }
+    let mut chunks: Vec<Rect> = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(*size);
+    let title = if show_title { Some(chunks.remove(0)) } else { None };
+    let logs = logs_height.map(|_| chunks.remove(3));
+    let status = chunks[1];
+    let message = chunks[2];
+
+    // Body & Help
+    let (body, help) = if show_help {
+        let body_chunks: Vec<Rect> = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(BODY_MIN_WIDTH), Constraint::Length(help_width)].as_ref())
+            .split(chunks[0]);
+        (body_chunks[0], Some(body_chunks[1]))
     } else {
-        "..loading".to_owned()
+        (chunks[0], None)
     };
+    Areas {
+        title,
+        body,
+        status,
+        message,
+        help,
+        logs,
+    }
+}
+
+fn draw_too_small<'a>(size: &Rect, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(format!("Terminal too small ({}x{})", size.width, size.height))
+        .style(theme.warning)
+        .wrap(Wrap { trim: true })
+}
+
+/// Width of the line-number gutter, fitting the biggest number of the buffer
+/// and a space
+fn gutter_width(file: &FileView, settings: &Settings) -> u16 {
+    if settings.line_numbers == LineNumbers::Off || file.is_hex_view() {
+        return 0;
+    }
+    let biggest = file.get_line_count().max(file.get_cursor().0 + 1);
+    biggest.to_string().len() as u16 + 1
+}
+
+fn draw_body<'a>(
+    file: &FileView,
+    settings: &Settings,
+    highlighter: &mut Highlighter,
+    highlighted_word: Option<&str>,
+    area: &Rect,
+    theme: &Theme,
+    border_style: Style,
+) -> Paragraph<'a> {
+    let (scroll_x, scroll_y) = file.get_scroll_offset();
+    let visible_rows = area.height.saturating_sub(2) as usize;
+    let gutter_width = gutter_width(file, settings) as usize;
+    let text_width = (area.width as usize).saturating_sub(2 + gutter_width).max(1);
+    let gutter_style = theme.gutter;
+    let cursor_line_number_style = theme.gutter_cursor_line;
+
+    // Only the lines in view are materialized
+    let highlighted = file
+        .get_source()
+        .and_then(|(file_id, path, text)| highlighter.highlight(file_id, path, text, scroll_y, visible_rows));
+    let mut lines: Vec<StyledLine> = highlighted.unwrap_or_else(|| {
+        let lines = file.get_lines(scroll_y, visible_rows);
+        lines.into_iter().map(|line| vec![(Style::default(), line)]).collect()
+    });
+    let (cursor_line, cursor_column) = file.get_cursor();
+    let matching_bracket = file.get_matching_bracket();
+    // The cursor may be on the empty line after a trailing line break
+    if cursor_line == scroll_y + lines.len() && lines.len() < visible_rows {
+        lines.push(vec![]);
+    }
+    let mut rows: Vec<Spans> = vec![];
+    for (index, line) in lines.into_iter().enumerate() {
+        let line_number = scroll_y + index;
+        let is_cursor_line = line_number == cursor_line;
+        let mut chars: Vec<(char, Style)> = line
+            .iter()
+            .flat_map(|(style, piece)| piece.chars().map(move |c| (c, *style)))
+            .collect();
+        if let Some(word) = highlighted_word {
+            let text: String = chars.iter().map(|(c, _)| c).collect();
+            for occurrence in matching::word_occurrences(&text, word) {
+                if !(is_cursor_line && occurrence.contains(&cursor_column)) {
+                    chars[occurrence].iter_mut().for_each(|(_, style)| *style = style.patch(theme.word_highlight));
+                }
+            }
+        }
+        if let Some((_, column)) = matching_bracket.filter(|(line, _)| *line == line_number) {
+            if let Some((_, style)) = chars.get_mut(column) {
+                *style = style.patch(theme.matching_bracket);
+            }
+        }
+        let layout = whitespace::expand(&chars, settings, theme.whitespace, theme.indent_guide);
+        let cursor_cell = layout.cursor_cell(cursor_column);
+        let line_style = if is_cursor_line { theme.cursor_line } else { Style::default() };
+        let cells: Vec<(char, Style)> =
+            layout.cells.iter().map(|cell| (cell.glyph, cell.style.patch(line_style))).collect();
+        // Lines are wrapped here rather than by the paragraph, so that
+        // continuation rows get a blank gutter and stay aligned, and the
+        // view scrolls by whole lines whatever their height
+        let (line_rows, indent) = match settings.wrap {
+            WrapMode::Soft => {
+                let indent = wrap::continuation_indent(layout.indent, text_width);
+                (wrap::wrap_line(&layout.cells, text_width, indent, |cell| cell.is_whitespace), indent)
+            },
+            WrapMode::Off => {
+                let visible = scroll_x.min(cells.len())..(scroll_x + text_width).min(cells.len());
+                (vec![visible], 0)
+            },
+        };
+        let cursor_row = match settings.wrap {
+            WrapMode::Soft => wrap::cursor_row(&line_rows, cursor_cell),
+            WrapMode::Off => 0,
+        };
+        for (row_index, row) in line_rows.iter().enumerate() {
+            let mut spans: Vec<Span> = vec![];
+            if gutter_width > 0 {
+                let label = match settings.line_numbers.number(line_number, cursor_line) {
+                    Some(number) if row_index == 0 => number.to_string(),
+                    _ => String::new(),
+                };
+                let style = if is_cursor_line { cursor_line_number_style } else { gutter_style };
+                spans.push(Span::styled(format!("{:>width$} ", label, width = gutter_width - 1), style));
+            }
+            if row_index > 0 {
+                spans.push(Span::styled(" ".repeat(indent), line_style));
+            }
+            let has_cursor = is_cursor_line && row_index == cursor_row && cursor_cell >= row.start;
+            let cursor = if has_cursor { Some(cursor_cell - row.start) } else { None };
+            spans.extend(draw_styled_spans(&cells[row.clone()], cursor, theme.cursor));
+            // The cursor line is highlighted to the edge of the body
+            if is_cursor_line {
+                let width: usize = spans.iter().map(Span::width).sum();
+                let rest = (gutter_width + text_width).saturating_sub(width);
+                spans.push(Span::styled(" ".repeat(rest), line_style));
+            }
+            rows.push(Spans::from(spans));
+        }
+        if rows.len() >= visible_rows {
+            break;
+        }
+    }
+
+    Paragraph::new(rows)
+        .style(theme.text)
+        .alignment(Alignment::Left)
+        .block(draw_body_block(file.get_large_file(), border_style))
+}
+
+/// Border of the body, titled with the status of large files
+fn draw_body_block<'a>(large_file: Option<&LargeFile>, border_style: Style) -> Block<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .style(border_style)
+        .border_type(BorderType::Plain);
+    match large_file {
+        Some(large_file) => block.title(draw_large_file_status(large_file)),
+        None => block,
+    }
+}
+
+/// Runs of equally styled characters, with the character under the cursor
+/// highlighted, or a highlighted blank past their end
+fn draw_styled_spans<'a>(chars: &[(char, Style)], cursor: Option<usize>, cursor_style: Style) -> Vec<Span<'a>> {
+    let mut spans: Vec<Span> = vec![];
+    let mut run = String::new();
+    let mut run_style = Style::default();
+    for (column, (c, style)) in chars.iter().enumerate() {
+        let style = if cursor == Some(column) { cursor_style } else { *style };
+        if style != run_style && !run.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut run), run_style));
+        }
+        run_style = style;
+        run.push(*c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, run_style));
+    }
+    if cursor.is_some_and(|column| column >= chars.len()) {
+        spans.push(Span::styled(" ", cursor_style));
+    }
+    spans
+}
+
+/// Offset, hex and ASCII columns of the bytes in view, with the cursor highlighted
+fn draw_hex_body<'a>(hex_buffer: &HexBuffer, area: &Rect, theme: &Theme, border_style: Style) -> Paragraph<'a> {
+    let offset_style = theme.hex_offset;
+    let byte_style = theme.hex_byte;
+    let cursor_style = theme.cursor;
+    let nibble_style = theme.hex_nibble;
+
+    let bytes = hex_buffer.bytes();
+    let visible_rows = area.height.saturating_sub(2) as usize;
+    let mut text: Vec<Spans> = vec![];
+    for row in hex_buffer.top_row()..(hex_buffer.top_row() + visible_rows).min(hex_buffer.row_count()) {
+        let start = row * BYTES_PER_ROW;
+        let row_bytes = &bytes[start..(start + BYTES_PER_ROW).min(bytes.len())];
+        let mut spans: Vec<Span> = vec![Span::styled(format!("{:08x}  ", start), offset_style)];
+        for column in 0..BYTES_PER_ROW {
+            let offset = start + column;
+            match row_bytes.get(column) {
+                Some(byte) if offset == hex_buffer.cursor() => {
+                    let digits = format!("{:02x}", byte);
+                    let (high, low) = digits.split_at(1);
+                    let (high_style, low_style) = if hex_buffer.is_low_nibble() {
+                        (cursor_style, nibble_style)
+                    } else {
+                        (nibble_style, cursor_style)
+                    };
+                    spans.push(Span::styled(high.to_owned(), high_style));
+                    spans.push(Span::styled(low.to_owned(), low_style));
+                },
+                Some(byte) => spans.push(Span::styled(format!("{:02x}", byte), byte_style)),
+                None => spans.push(Span::raw("  ")),
+            }
+            spans.push(Span::raw(if column == BYTES_PER_ROW / 2 - 1 { "  " } else { " " }));
+        }
+        spans.push(Span::styled(" |", offset_style));
+        for (column, byte) in row_bytes.iter().enumerate() {
+            let style = if start + column == hex_buffer.cursor() { cursor_style } else { byte_style };
+            spans.push(Span::styled(printable(*byte).to_string(), style));
+        }
+        spans.push(Span::styled("|", offset_style));
+        text.push(Spans::from(spans));
+    }
 
-    // Split text into lines
-    let text: Vec<Spans> = initialized_text.lines()
-                            .map(|line| Spans::from(Span::raw(line.to_owned())))
-                            .collect();
     Paragraph::new(text)
-        .style(Style::default().fg(Color::LightCyan))
         .alignment(Alignment::Left)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
-                .border_type(BorderType::Plain),
-        ).wrap(Wrap { trim: true } )
-        .scroll(state.get_scroll_offset().clone())
+                .style(border_style)
+                .border_type(BorderType::Plain)
+                .title(format!(
+                    "Hex View: {:#010x} / {:#010x} bytes",
+                    hex_buffer.cursor(),
+                    bytes.len()
+                )),
+        )
+}
+
+/// Shown in place of the path while no file is open
+const NO_FILE_NAME: &str = "[No Name]";
+
+/// Mode, file and cursor position on the left, keys typed so far of a binding
+/// and file metadata on the right
+fn draw_status_line<'a>(state: &AppState, pending_keys: &str, width: u16, theme: &Theme) -> Paragraph<'a> {
+    let (mode, mode_style) = if state.is_write_mode() {
+        (" WRITE ", theme.write_mode)
+    } else {
+        (" INPUT ", theme.input_mode)
+    };
+    let status_style = theme.status;
+
+    let path = state.get_path().unwrap_or(NO_FILE_NAME);
+    let mut left = format!(" {}", path);
+    if state.is_modified() {
+        left.push_str(" [+]");
+    }
+    if state.is_read_only() {
+        left.push_str(" [read-only]");
+    }
+
+    let (encoding, position) = match state.get_hex_buffer() {
+        Some(hex_buffer) => {
+            let percentage = (hex_buffer.cursor() + 1) * 100 / hex_buffer.bytes().len().max(1);
+            ("binary", format!("{:#010x} {}%", hex_buffer.cursor(), percentage))
+        },
+        None => {
+            let (line, column) = state.get_cursor();
+            let percentage = (line + 1) * 100 / state.get_line_count().max(line + 1);
+            ("utf-8", format!("{}:{} {}%", line + 1, column + 1, percentage))
+        },
+    };
+    let mut metadata: Vec<String> = vec![];
+    if !pending_keys.is_empty() {
+        metadata.push(pending_keys.to_owned());
+    }
+    if let Some(line_ending) = state.get_line_ending() {
+        metadata.push(line_ending.to_owned());
+    }
+    metadata.push(encoding.to_owned());
+    metadata.push(paths::file_type(path));
+    metadata.push(position);
+    let right = format!("{} ", metadata.join(" | "));
+
+    let used = mode.chars().count() + left.chars().count() + right.chars().count();
+    let padding = " ".repeat((width as usize).saturating_sub(used).max(1));
+    Paragraph::new(Spans::from(vec![
+        Span::styled(mode, mode_style),
+        Span::raw(left),
+        Span::raw(padding),
+        Span::raw(right),
+    ]))
+    .style(status_style)
 }
 
-fn draw_help(actions: &Actions) -> Table {
-    let key_style = Style::default().fg(Color::LightCyan);
-    let help_style = Style::default().fg(Color::Gray);
+fn draw_command_line<'a>(command_line: &str, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(format!(":{}█", command_line)).style(theme.command_line)
+}
+
+fn draw_message<'a>(message: &Message, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(message.text.clone()).style(message_style(message.severity, theme))
+}
+
+fn message_style(severity: Severity, theme: &Theme) -> Style {
+    match severity {
+        Severity::Info => theme.message_info,
+        Severity::Warning => theme.message_warning,
+        Severity::Error => theme.message_error,
+    }
+}
+
+fn draw_large_file_status(large_file: &LargeFile) -> String {
+    if large_file.is_indexed() {
+        format!("Large File, Read-Only: {} lines", large_file.line_count())
+    } else {
+        format!(
+            "Large File, Read-Only: indexing lines {}% ({} so far)",
+            large_file.indexing_progress(),
+            large_file.line_count()
+        )
+    }
+}
+
+fn draw_help<'a>(actions: &'a Actions, theme: &Theme) -> Table<'a> {
+    let key_style = theme.help_key;
+    let help_style = theme.help_text;
 
     let mut rows = vec![];
     for action in actions.actions().iter() {
         let mut first = true;
-        for key in action.keys() {
+        for keys in actions.keys(Mode::Normal, *action) {
             let help = if first {
                 first = false;
                 action.to_string()
@@ -115,7 +721,7 @@ fn draw_help(actions: &Actions) -> Table {
                 String::from("")
             };
             let row = Row::new(vec![
-                Cell::from(Span::styled(key.to_string(), key_style)),
+                Cell::from(Span::styled(keymap::format_sequence(keys), key_style)),
                 Cell::from(Span::styled(help, help_style)),
             ]);
             rows.push(row);
@@ -133,18 +739,206 @@ fn draw_help(actions: &Actions) -> Table {
         .column_spacing(1)
 }
 
-fn draw_logs<'a>() -> TuiLoggerWidget<'a> {
-    TuiLoggerWidget::default()
-        .style_error(Style::default().fg(Color::Red))
-        .style_debug(Style::default().fg(Color::Green))
-        .style_warn(Style::default().fg(Color::Yellow))
-        .style_trace(Style::default().fg(Color::Gray))
-        .style_info(Style::default().fg(Color::Blue))
+/// Every key grouped by mode, narrowed down to those matching the search
+fn draw_help_overlay<'a>(actions: &Actions, overlay: &HelpOverlay, theme: &Theme) -> Paragraph<'a> {
+    let sections = help::search(help::sections(actions), &overlay.query);
+    let mut lines: Vec<Spans> = vec![];
+    for section in sections.iter() {
+        lines.push(Spans::from(Span::styled(section.mode.to_owned(), theme.help_key.add_modifier(Modifier::BOLD))));
+        for entry in section.entries.iter() {
+            lines.push(Spans::from(vec![
+                Span::styled(format!("  {:<24} ", entry.key), theme.help_key),
+                Span::styled(entry.description.clone(), theme.help_text),
+            ]));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(Spans::from(Span::styled(format!("No key matches {}", overlay.query), theme.warning)));
+    }
+    let title = format!("Help - search: {}_ (Esc to close)", overlay.query);
+    Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).border_type(BorderType::Plain).title(title))
+        .scroll((overlay.scroll.min(u16::MAX as usize) as u16, 0))
+}
+
+/// Every message kept, newest first
+fn draw_messages_view<'a>(messages: &Messages, view: &MessagesView, theme: &Theme) -> Paragraph<'a> {
+    let mut lines: Vec<Spans> = messages
+        .history()
+        .iter()
+        .rev()
+        .map(|message| {
+            let style = message_style(message.severity, theme);
+            Spans::from(vec![
+                Span::styled(format!("{:<8} ", message.severity), style.add_modifier(Modifier::BOLD)),
+                Span::styled(message.text.clone(), style),
+            ])
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Spans::from(Span::styled("No messages yet", theme.help_text)));
+    }
+    Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).border_type(BorderType::Plain).title("Messages (Esc to close)"))
+        .scroll((view.scroll.min(u16::MAX as usize) as u16, 0))
+}
+
+/// Centered in the upper half of the screen
+fn palette_area(size: &Rect) -> Rect {
+    let width = (size.width * 2 / 3).max(40).min(size.width);
+    let height = (size.height / 2).max(5).min(size.height);
+    Rect::new(size.x + (size.width - width) / 2, size.y + size.height / 8, width, height)
+}
+
+/// The query, then as many of the matching entries as fit in `rows`, scrolled
+/// to keep the selected one in view
+fn draw_palette<'a>(palette: &Palette, rows: usize, theme: &Theme) -> Paragraph<'a> {
+    let matches = palette.matches();
+    let first = (palette.selected() + 1).saturating_sub(rows);
+    let mut lines = vec![Spans::from(Span::styled(format!("> {}█", palette.query()), theme.prompt))];
+    for (index, entry) in matches.iter().enumerate().skip(first).take(rows) {
+        let (label_style, key_style) = if index == palette.selected() {
+            (theme.selection, theme.selection)
+        } else {
+            (theme.prompt, theme.help_key)
+        };
+        lines.push(Spans::from(vec![
+            Span::styled(format!("{:<32}", entry.label), label_style),
+            Span::styled(format!(" {}", entry.keys), key_style),
+        ]));
+    }
+    if matches.is_empty() {
+        lines.push(Spans::from(Span::styled("No matches", theme.help_text)));
+    }
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain)
+            .border_style(theme.prompt_border)
+            .title("Command Palette"),
+    )
+}
+
+/// Centered area fitting the prompt, taller once it shows a diff
+fn prompt_area(prompt: &Prompt, size: &Rect) -> Rect {
+    let width = (size.width * 3 / 4).max(50).min(size.width);
+    let height = if prompt.diff().is_some() { size.height * 3 / 4 } else { 7 }.min(size.height);
+    Rect::new(
+        size.x + (size.width - width) / 2,
+        size.y + (size.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn draw_prompt<'a>(prompt: &'a Prompt, theme: &Theme) -> Paragraph<'a> {
+    let key_style = theme.help_key;
+    let help_style = theme.help_text;
+
+    let mut choices: Vec<Span> = vec![];
+    for answer in prompt.answers().iter() {
+        choices.push(Span::styled(answer.key().to_string(), key_style));
+        choices.push(Span::styled(format!(" {}  ", answer), help_style));
+    }
+
+    let mut text: Vec<Spans> = vec![
+        Spans::from(Span::raw(prompt.message())),
+        Spans::from(choices),
+        Spans::from(""),
+    ];
+    if let Some(diff) = prompt.diff() {
+        text.push(Spans::from(Span::styled(prompt.diff_legend(), help_style)));
+        if diff.is_empty() {
+            text.push(Spans::from(Span::styled("No differences", help_style)));
+        }
+        for line in diff.iter().skip(prompt.scroll() as usize) {
+            let style = match line.kind {
+                DiffKind::Added => theme.diff_added,
+                DiffKind::Removed => theme.diff_removed,
+                DiffKind::Hunk => theme.diff_hunk,
+                DiffKind::Context => theme.diff_context,
+            };
+            let prefix = match line.kind {
+                DiffKind::Added => "+",
+                DiffKind::Removed => "-",
+                DiffKind::Context => " ",
+                DiffKind::Hunk => "",
+            };
+            text.push(Spans::from(Span::styled(format!("{}{}", prefix, line.text), style)));
+        }
+    }
+
+    Paragraph::new(text)
+        .style(theme.prompt)
+        .alignment(Alignment::Left)
         .block(
             Block::default()
-                .title("Logs")
-                .border_style(Style::default().fg(Color::White).bg(Color::Black))
-                .borders(Borders::ALL),
+                .borders(Borders::ALL)
+                .border_type(BorderType::Double)
+                .border_style(theme.prompt_border)
+                .title(prompt.title()),
         )
-        .style(Style::default().fg(Color::White).bg(Color::Black))
+        .wrap(Wrap { trim: false })
+}
+
+fn draw_logs<'a>(theme: &Theme) -> TuiLoggerWidget<'a> {
+    TuiLoggerWidget::default()
+        .style_error(theme.log_error)
+        .style_debug(theme.log_debug)
+        .style_warn(theme.log_warn)
+        .style_trace(theme.log_trace)
+        .style_info(theme.log_info)
+        .block(Block::default().title("Logs").border_style(theme.logs).borders(Borders::ALL))
+        .style(theme.logs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_collapse_panes_on_small_terminals() {
+        let panels = Panels::default();
+        let areas = layout(&Rect::new(0, 0, 80, 40), &panels);
+        assert!(areas.title.is_some() && areas.help.is_some());
+        assert_eq!(areas.logs.map(|logs| logs.height), Some(panels.logs.size));
+
+        let areas = layout(&Rect::new(0, 0, 40, 20), &panels);
+        assert!(areas.help.is_none());
+        assert_eq!(areas.logs.map(|logs| logs.height), Some(5));
+        assert_eq!(areas.body.height, BODY_MIN_HEIGHT);
+
+        let areas = layout(&Rect::new(0, 0, 20, 8), &panels);
+        assert!(areas.title.is_none() && areas.logs.is_none() && areas.help.is_none());
+        assert_eq!(areas.body, Rect::new(0, 0, 20, 6));
+        assert_eq!(areas.status, Rect::new(0, 6, 20, 1));
+        assert_eq!(areas.message, Rect::new(0, 7, 20, 1));
+    }
+
+    #[test]
+    fn should_give_hidden_panels_room_to_the_body() {
+        let mut panels = Panels::default();
+        panels.help.toggle();
+        panels.resize_logs(-4);
+        let areas = layout(&Rect::new(0, 0, 80, 40), &panels);
+        assert!(areas.help.is_none());
+        assert_eq!(areas.body.width, 80);
+        assert_eq!(areas.logs.map(|logs| logs.height), Some(panels.logs.size));
+
+        panels.logs.toggle();
+        let areas = layout(&Rect::new(0, 0, 80, 40), &panels);
+        assert!(areas.logs.is_none());
+        assert_eq!(areas.status.y, 38);
+        assert_eq!(areas.message.y, 39);
+    }
+
+    #[test]
+    fn should_scroll_tabs_to_keep_the_selected_one_in_view() {
+        let widths = [5; 10];
+        assert_eq!(visible_tabs(&widths, 0, 100), 0..10);
+        assert_eq!(visible_tabs(&widths, 0, 24), 0..3);
+        assert_eq!(visible_tabs(&widths, 9, 24), 7..10);
+        assert_eq!(visible_tabs(&widths, 5, 24), 4..7);
+        assert_eq!(visible_tabs(&[], 0, 24), 0..0);
+    }
 }
@@ -0,0 +1,128 @@
+use super::settings::Settings;
+
+/// Glyph marking an indentation level when indentation guides are on
+pub const INDENT_GUIDE: char = '│';
+
+/// A column of a line as drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell<S> {
+    pub glyph: char,
+    pub style: S,
+    /// Whether the cell comes from whitespace, so that rows can wrap after it
+    /// whatever glyph it is drawn with
+    pub is_whitespace: bool,
+}
+
+/// A line laid out in cells, tabs expanded and whitespace shown as set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cells<S> {
+    pub cells: Vec<Cell<S>>,
+    /// Cell each character of the line starts at, and the cell past its end
+    pub columns: Vec<usize>,
+    /// Cells of leading whitespace
+    pub indent: usize,
+}
+
+impl<S> Cells<S> {
+    /// Cell the cursor at character `column` is drawn on
+    pub fn cursor_cell(&self, column: usize) -> usize {
+        self.columns[column.min(self.columns.len() - 1)]
+    }
+}
+
+/// Lay out a line of styled characters in cells: tabs are expanded to the next
+/// multiple of the tab width, and in list mode tabs, trailing spaces and
+/// non-breaking spaces are drawn with their glyphs in `whitespace_style`, and
+/// the end of the line is marked. Indentation guides are drawn over the blank
+/// cells of the leading whitespace at each indentation level.
+pub fn expand<S: Copy>(chars: &[(char, S)], settings: &Settings, whitespace_style: S, guide_style: S) -> Cells<S> {
+    let tab_width = settings.tab_width.max(1);
+    let list_chars = &settings.list_chars;
+    let trailing_start = chars.iter().rposition(|(c, _)| !c.is_whitespace()).map_or(0, |index| index + 1);
+    let mut cells: Vec<Cell<S>> = vec![];
+    let mut columns = vec![];
+    for (index, (c, style)) in chars.iter().enumerate() {
+        columns.push(cells.len());
+        let whitespace = |glyph| Cell {
+            glyph,
+            style: if settings.list { whitespace_style } else { *style },
+            is_whitespace: true,
+        };
+        match *c {
+            '\t' => {
+                let width = tab_width - cells.len() % tab_width;
+                let (first, rest) = if settings.list { list_chars.tab } else { (' ', ' ') };
+                cells.push(whitespace(first));
+                cells.extend((1..width).map(|_| whitespace(rest)));
+            },
+            '\u{a0}' if settings.list => cells.push(whitespace(list_chars.nbsp)),
+            ' ' if settings.list && index >= trailing_start => cells.push(whitespace(list_chars.trail)),
+            c => cells.push(Cell {
+                glyph: c,
+                style: *style,
+                is_whitespace: c.is_whitespace(),
+            }),
+        }
+    }
+    columns.push(cells.len());
+    let indent = cells.iter().take_while(|cell| cell.is_whitespace).count();
+    if settings.indent_guides {
+        for cell in cells[..indent].iter_mut().step_by(tab_width) {
+            if cell.glyph == ' ' {
+                cell.glyph = INDENT_GUIDE;
+                cell.style = guide_style;
+            }
+        }
+    }
+    if let (true, Some(eol)) = (settings.list, list_chars.eol) {
+        cells.push(Cell {
+            glyph: eol,
+            style: whitespace_style,
+            is_whitespace: true,
+        });
+    }
+    Cells { cells, columns, indent }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyphs(line: &str, settings: &Settings) -> String {
+        let chars: Vec<(char, ())> = line.chars().map(|c| (c, ())).collect();
+        expand(&chars, settings, (), ()).cells.iter().map(|cell| cell.glyph).collect()
+    }
+
+    #[test]
+    fn should_expand_tabs_to_the_next_tab_stop() {
+        let settings = Settings::default();
+        assert_eq!(glyphs("\tx", &settings), "    x");
+        assert_eq!(glyphs("ab\tx", &settings), "ab  x");
+        let chars: Vec<(char, ())> = "a\tb".chars().map(|c| (c, ())).collect();
+        let cells = expand(&chars, &settings, (), ());
+        assert_eq!(cells.columns, vec![0, 1, 4, 5]);
+        assert_eq!(cells.cursor_cell(2), 4);
+        assert_eq!(cells.cursor_cell(10), 5);
+    }
+
+    #[test]
+    fn should_show_whitespace_in_list_mode() {
+        let settings = Settings {
+            list: true,
+            ..Settings::default()
+        };
+        assert_eq!(glyphs("\tx \u{a0}y  ", &settings), "→   x ⍽y··¬");
+        assert_eq!(glyphs("", &settings), "¬");
+    }
+
+    #[test]
+    fn should_draw_guides_at_indentation_levels() {
+        let settings = Settings {
+            indent_guides: true,
+            ..Settings::default()
+        };
+        assert_eq!(glyphs("        x = 1", &settings), "│   │   x = 1");
+        assert_eq!(glyphs("\tx", &settings), "│   x");
+        assert_eq!(glyphs("  x", &settings), "│ x");
+    }
+}
@@ -0,0 +1,508 @@
+use serde::{Deserialize, Serialize};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+
+use super::editing::ViewState;
+
+/// Weight a window starts with within its split
+const DEFAULT_WEIGHT: u32 = 10;
+const MIN_WEIGHT: u32 = 1;
+/// Smallest window, borders included, that can still be split in two
+const MIN_SPLIT_HEIGHT: u16 = 6;
+const MIN_SPLIT_WIDTH: u16 = 24;
+
+/// How a window is split
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplitDirection {
+    /// Windows stacked on top of each other
+    Horizontal,
+    /// Windows side by side
+    Vertical,
+}
+
+/// Direction to move the focus in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A view into an open file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    /// Identifier of the file shown, which outlives its index among the open files
+    pub file_id: u64,
+    pub view: ViewState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    /// Index of a window
+    Window(usize),
+    /// Children with their weight, sharing the area in proportion to it
+    Split {
+        direction: SplitDirection,
+        children: Vec<(Node, u32)>,
+    },
+}
+
+/// A window, or a split of windows, as saved in a session: either `window`
+/// is set, or `direction` and `children` are
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<SplitDirection>,
+    /// Weight within the parent split
+    #[serde(default)]
+    pub weight: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SplitNode>,
+}
+
+/// How the windows are split and which one is focused, without what they
+/// show, as saved in a session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Splits {
+    pub focused: usize,
+    pub root: SplitNode,
+}
+
+/// The windows the body area is split into, one of them focused
+#[derive(Debug, Clone)]
+pub struct Windows {
+    windows: Vec<Window>,
+    root: Node,
+    focused: usize,
+    /// Body area the windows were last laid out in, to find neighbours
+    area: Rect,
+}
+
+impl Windows {
+    pub fn new(window: Window) -> Self {
+        Self {
+            windows: vec![window],
+            root: Node::Window(0),
+            focused: 0,
+            area: Rect::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    pub fn focused(&self) -> usize {
+        self.focused
+    }
+
+    pub fn get(&self, index: usize) -> &Window {
+        &self.windows[index]
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> &mut Window {
+        &mut self.windows[index]
+    }
+
+    /// Split the focused window in two, both showing the same file. The new
+    /// window gets the focus. Refused when the halves would be too small to
+    /// show any text.
+    pub fn split(&mut self, direction: SplitDirection) -> Result<(), String> {
+        if let Some(area) = self.focused_area() {
+            let too_small = match direction {
+                SplitDirection::Horizontal => area.height < MIN_SPLIT_HEIGHT,
+                SplitDirection::Vertical => area.width < MIN_SPLIT_WIDTH,
+            };
+            if too_small {
+                return Err("Window too small to split".to_owned());
+            }
+        }
+        let new_index = self.windows.len();
+        self.windows.push(self.windows[self.focused].clone());
+        let focused = self.focused;
+        match self.root.parent_of(focused) {
+            // Siblings in the same direction share the split rather than nesting
+            Some(Node::Split {
+                direction: parent_direction,
+                children,
+            }) if *parent_direction == direction => {
+                let position = children
+                    .iter()
+                    .position(|(child, _)| *child == Node::Window(focused))
+                    .unwrap();
+                children.insert(position + 1, (Node::Window(new_index), DEFAULT_WEIGHT));
+            },
+            _ => {
+                let leaf = self.root.find_mut(focused).unwrap();
+                *leaf = Node::Split {
+                    direction,
+                    children: vec![
+                        (Node::Window(focused), DEFAULT_WEIGHT),
+                        (Node::Window(new_index), DEFAULT_WEIGHT),
+                    ],
+                };
+            },
+        }
+        self.focused = new_index;
+        Ok(())
+    }
+
+    /// Close the focused window, unless it is the last one. The focus moves to
+    /// its neighbour.
+    pub fn close(&mut self) -> Result<(), String> {
+        if self.windows.len() == 1 {
+            return Err("Cannot close the last window".to_owned());
+        }
+        let closed = self.focused;
+        let neighbour = self.root.remove(closed).unwrap_or(0);
+        self.root.collapse();
+        self.windows.remove(closed);
+        self.root.renumber_after(closed);
+        self.focused = if neighbour > closed { neighbour - 1 } else { neighbour };
+        Ok(())
+    }
+
+    /// Grow the focused window by `delta` within its split, shrinking its siblings
+    pub fn resize(&mut self, delta: i32) -> Result<(), String> {
+        let focused = self.focused;
+        match self.root.parent_of(focused) {
+            Some(Node::Split { children, .. }) => {
+                let (_, weight) = children
+                    .iter_mut()
+                    .find(|(child, _)| *child == Node::Window(focused))
+                    .unwrap();
+                *weight = (*weight as i64 + delta as i64).max(MIN_WEIGHT as i64) as u32;
+                Ok(())
+            },
+            _ => Err("Nothing to resize, the window is not split".to_owned()),
+        }
+    }
+
+    /// Areas of the windows within `area`, remembered to find neighbours
+    pub fn layout(&mut self, area: Rect) -> Vec<(usize, Rect)> {
+        self.area = area;
+        let mut areas = vec![];
+        self.root.layout(area, &mut areas);
+        areas
+    }
+
+    /// Area of the focused window in the last layout, if there was one
+    fn focused_area(&self) -> Option<Rect> {
+        if self.area == Rect::default() {
+            return None;
+        }
+        let mut areas = vec![];
+        self.root.layout(self.area, &mut areas);
+        areas
+            .into_iter()
+            .find(|(index, _)| *index == self.focused)
+            .map(|(_, area)| area)
+    }
+
+    /// Focus the closest window in `direction` from the focused one
+    pub fn focus(&mut self, direction: FocusDirection) -> Result<(), String> {
+        let mut areas = vec![];
+        self.root.layout(self.area, &mut areas);
+        let from = self.focused_area().unwrap_or_default();
+        let center = |area: &Rect| {
+            (
+                area.x as i32 * 2 + area.width as i32,
+                area.y as i32 * 2 + area.height as i32,
+            )
+        };
+        let (from_x, from_y) = center(&from);
+        let closest = areas
+            .iter()
+            .filter(|(index, area)| {
+                *index != self.focused
+                    && match direction {
+                        FocusDirection::Left => area.right() <= from.left(),
+                        FocusDirection::Right => area.left() >= from.right(),
+                        FocusDirection::Up => area.bottom() <= from.top(),
+                        FocusDirection::Down => area.top() >= from.bottom(),
+                    }
+            })
+            .min_by_key(|(_, area)| {
+                let (x, y) = center(area);
+                (x - from_x).abs() + (y - from_y).abs()
+            });
+        match closest {
+            Some((index, _)) => {
+                self.focused = *index;
+                Ok(())
+            },
+            None => Err(format!("No window {:?} of this one", direction).to_lowercase()),
+        }
+    }
+
+    /// Focus the window at `index`
+    pub fn set_focused(&mut self, index: usize) {
+        if index < self.windows.len() {
+            self.focused = index;
+        }
+    }
+
+    /// How the windows are split, to be saved along with what they show
+    pub fn splits(&self) -> Splits {
+        Splits {
+            focused: self.focused,
+            root: self.root.to_split_node(DEFAULT_WEIGHT),
+        }
+    }
+
+    /// `windows` split the way `splits` tells. `None` when it does not fit
+    /// them, e.g. in a session edited by hand.
+    pub fn from_splits(windows: Vec<Window>, splits: &Splits) -> Option<Self> {
+        let mut root = Node::from_split_node(&splits.root)?;
+        root.collapse();
+        let mut shown = vec![];
+        root.collect_windows(&mut shown);
+        shown.sort_unstable();
+        if shown != (0..windows.len()).collect::<Vec<_>>() || splits.focused >= windows.len() {
+            return None;
+        }
+        Some(Self {
+            windows,
+            root,
+            focused: splits.focused,
+            area: Rect::default(),
+        })
+    }
+}
+
+impl Node {
+    fn to_split_node(&self, weight: u32) -> SplitNode {
+        match self {
+            Node::Window(index) => SplitNode {
+                window: Some(*index),
+                direction: None,
+                weight,
+                children: vec![],
+            },
+            Node::Split { direction, children } => SplitNode {
+                window: None,
+                direction: Some(*direction),
+                weight,
+                children: children.iter().map(|(child, weight)| child.to_split_node(*weight)).collect(),
+            },
+        }
+    }
+
+    fn from_split_node(node: &SplitNode) -> Option<Node> {
+        match (node.window, node.direction) {
+            (Some(index), None) if node.children.is_empty() => Some(Node::Window(index)),
+            (None, Some(direction)) if !node.children.is_empty() => Some(Node::Split {
+                direction,
+                children: node
+                    .children
+                    .iter()
+                    .map(|child| Some((Node::from_split_node(child)?, child.weight.max(MIN_WEIGHT))))
+                    .collect::<Option<_>>()?,
+            }),
+            _ => None,
+        }
+    }
+
+    fn find_mut(&mut self, window: usize) -> Option<&mut Node> {
+        match self {
+            Node::Window(index) if *index == window => Some(self),
+            Node::Window(_) => None,
+            Node::Split { children, .. } => children.iter_mut().find_map(|(child, _)| child.find_mut(window)),
+        }
+    }
+
+    /// The split directly holding `window`
+    fn parent_of(&mut self, window: usize) -> Option<&mut Node> {
+        let is_parent = match self {
+            Node::Window(_) => return None,
+            Node::Split { children, .. } => children.iter().any(|(child, _)| *child == Node::Window(window)),
+        };
+        if is_parent {
+            return Some(self);
+        }
+        match self {
+            Node::Split { children, .. } => children.iter_mut().find_map(|(child, _)| child.parent_of(window)),
+            Node::Window(_) => None,
+        }
+    }
+
+    /// Remove `window` from the tree, returning a window next to where it was
+    fn remove(&mut self, window: usize) -> Option<usize> {
+        let children = match self {
+            Node::Window(_) => return None,
+            Node::Split { children, .. } => children,
+        };
+        if let Some(position) = children.iter().position(|(child, _)| *child == Node::Window(window)) {
+            children.remove(position);
+            let (neighbour, _) = &children[position.saturating_sub(1).min(children.len() - 1)];
+            return Some(neighbour.first_window());
+        }
+        children.iter_mut().find_map(|(child, _)| child.remove(window))
+    }
+
+    fn collect_windows(&self, windows: &mut Vec<usize>) {
+        match self {
+            Node::Window(index) => windows.push(*index),
+            Node::Split { children, .. } => {
+                for (child, _) in children.iter() {
+                    child.collect_windows(windows);
+                }
+            },
+        }
+    }
+
+    fn first_window(&self) -> usize {
+        match self {
+            Node::Window(index) => *index,
+            Node::Split { children, .. } => children[0].0.first_window(),
+        }
+    }
+
+    /// Replace splits left with a single child by that child
+    fn collapse(&mut self) {
+        if let Node::Split { children, .. } = self {
+            for (child, _) in children.iter_mut() {
+                child.collapse();
+            }
+            if children.len() == 1 {
+                *self = children.remove(0).0;
+            }
+        }
+    }
+
+    /// Shift down the indices of the windows after a removed one
+    fn renumber_after(&mut self, removed: usize) {
+        match self {
+            Node::Window(index) if *index > removed => *index -= 1,
+            Node::Window(_) => {},
+            Node::Split { children, .. } => {
+                for (child, _) in children.iter_mut() {
+                    child.renumber_after(removed);
+                }
+            },
+        }
+    }
+
+    fn layout(&self, area: Rect, areas: &mut Vec<(usize, Rect)>) {
+        match self {
+            Node::Window(index) => areas.push((*index, area)),
+            Node::Split { direction, children } => {
+                let total: u32 = children.iter().map(|(_, weight)| weight).sum();
+                let constraints: Vec<Constraint> = children
+                    .iter()
+                    .map(|(_, weight)| Constraint::Ratio(*weight, total))
+                    .collect();
+                let chunks = Layout::default()
+                    .direction(match direction {
+                        SplitDirection::Horizontal => Direction::Vertical,
+                        SplitDirection::Vertical => Direction::Horizontal,
+                    })
+                    .constraints(constraints)
+                    .split(area);
+                for ((child, _), chunk) in children.iter().zip(chunks) {
+                    child.layout(chunk, areas);
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(file_id: u64) -> Window {
+        Window {
+            file_id,
+            view: ViewState::default(),
+        }
+    }
+
+    #[test]
+    fn should_split_and_lay_out_windows() {
+        let mut windows = Windows::new(window(1));
+        windows.split(SplitDirection::Vertical).unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows.focused(), 1);
+        assert_eq!(windows.get(1).file_id, 1);
+        windows.split(SplitDirection::Horizontal).unwrap();
+        assert_eq!(
+            windows.layout(Rect::new(0, 0, 80, 20)),
+            vec![
+                (0, Rect::new(0, 0, 40, 20)),
+                (1, Rect::new(40, 0, 40, 10)),
+                (2, Rect::new(40, 10, 40, 10))
+            ]
+        );
+    }
+
+    #[test]
+    fn should_move_focus_to_neighbours() {
+        let mut windows = Windows::new(window(1));
+        windows.split(SplitDirection::Vertical).unwrap();
+        windows.split(SplitDirection::Horizontal).unwrap();
+        windows.layout(Rect::new(0, 0, 80, 20));
+        windows.focus(FocusDirection::Up).unwrap();
+        assert_eq!(windows.focused(), 1);
+        windows.focus(FocusDirection::Left).unwrap();
+        assert_eq!(windows.focused(), 0);
+        assert!(windows.focus(FocusDirection::Left).is_err());
+        windows.layout(Rect::new(0, 0, 40, 5));
+        assert!(windows.split(SplitDirection::Horizontal).is_err());
+    }
+
+    #[test]
+    fn should_resize_within_the_split() {
+        let mut windows = Windows::new(window(1));
+        assert!(windows.resize(5).is_err());
+        windows.split(SplitDirection::Vertical).unwrap();
+        windows.resize(10).unwrap();
+        assert_eq!(
+            windows.layout(Rect::new(0, 0, 90, 20)),
+            vec![(0, Rect::new(0, 0, 30, 20)), (1, Rect::new(30, 0, 60, 20))]
+        );
+    }
+
+    #[test]
+    fn should_rebuild_windows_from_their_splits() {
+        let mut windows = Windows::new(window(1));
+        windows.split(SplitDirection::Vertical).unwrap();
+        windows.split(SplitDirection::Horizontal).unwrap();
+        windows.resize(5).unwrap();
+        let area = Rect::new(0, 0, 80, 20);
+        let windows_list: Vec<Window> = (0..windows.len()).map(|index| windows.get(index).clone()).collect();
+        let mut rebuilt = Windows::from_splits(windows_list.clone(), &windows.splits()).unwrap();
+        assert_eq!(rebuilt.focused(), 2);
+        assert_eq!(rebuilt.layout(area), windows.layout(area));
+        assert!(Windows::from_splits(windows_list[..2].to_vec(), &windows.splits()).is_none());
+    }
+
+    #[test]
+    fn should_close_windows_and_collapse_splits() {
+        let mut windows = Windows::new(window(1));
+        windows.split(SplitDirection::Vertical).unwrap();
+        windows.get_mut(1).file_id = 2;
+        windows.split(SplitDirection::Horizontal).unwrap();
+        windows.get_mut(2).file_id = 3;
+        windows.close().unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows.focused(), 1);
+        assert_eq!(windows.get(1).file_id, 2);
+        assert_eq!(
+            windows.layout(Rect::new(0, 0, 80, 20)),
+            vec![(0, Rect::new(0, 0, 40, 20)), (1, Rect::new(40, 0, 40, 20))]
+        );
+        windows.close().unwrap();
+        assert!(windows.close().is_err());
+        assert_eq!(
+            windows.layout(Rect::new(0, 0, 80, 20)),
+            vec![(0, Rect::new(0, 0, 80, 20))]
+        );
+    }
+}
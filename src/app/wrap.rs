@@ -0,0 +1,73 @@
+use std::ops::Range;
+
+/// Rows a line of `chars` is drawn in when soft-wrapped to `width` columns, as
+/// ranges of its characters. Rows break after the last whitespace that fits,
+/// or mid-word when there is none, and continuation rows leave room for an
+/// `indent` given by [`continuation_indent`].
+pub fn wrap_line<T>(chars: &[T], width: usize, indent: usize, is_whitespace: impl Fn(&T) -> bool) -> Vec<Range<usize>> {
+    let width = width.max(1);
+    let indent = indent.min(width - 1);
+    let mut rows = vec![];
+    let mut start = 0;
+    loop {
+        let available = if rows.is_empty() { width } else { width - indent };
+        if chars.len() - start <= available {
+            rows.push(start..chars.len());
+            return rows;
+        }
+        let end = (start + 1..=start + available)
+            .rev()
+            .find(|index| is_whitespace(&chars[*index - 1]) && !is_whitespace(&chars[*index]))
+            .unwrap_or(start + available);
+        rows.push(start..end);
+        start = end;
+    }
+}
+
+/// Columns continuation rows are indented by: the `leading` whitespace of the
+/// line, as long as it leaves them half of the `width`
+pub fn continuation_indent(leading: usize, width: usize) -> usize {
+    if leading <= width / 2 {
+        leading
+    } else {
+        0
+    }
+}
+
+/// Index of the row holding `column`, a column at the end of a row belonging
+/// to the next one
+pub fn cursor_row(rows: &[Range<usize>], column: usize) -> usize {
+    rows.iter().rposition(|row| row.start <= column).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(line: &str, width: usize) -> Vec<String> {
+        let chars: Vec<char> = line.chars().collect();
+        let leading = chars.iter().take_while(|c| c.is_whitespace()).count();
+        wrap_line(&chars, width, continuation_indent(leading, width), |c| c.is_whitespace())
+            .into_iter()
+            .map(|row| chars[row].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn should_wrap_at_word_boundaries_keeping_indentation() {
+        assert_eq!(wrap("", 10), vec![""]);
+        assert_eq!(wrap("short", 10), vec!["short"]);
+        assert_eq!(wrap("the quick brown fox", 10), vec!["the quick ", "brown fox"]);
+        // Continuation rows are 8 wide to repeat the indentation
+        assert_eq!(wrap("  let value = 1;", 10), vec!["  let ", "value = ", "1;"]);
+        assert_eq!(wrap("abcdefghijkl", 5), vec!["abcde", "fghij", "kl"]);
+    }
+
+    #[test]
+    fn should_find_the_row_of_the_cursor() {
+        let rows = vec![0..4, 4..8, 8..10];
+        assert_eq!(cursor_row(&rows, 0), 0);
+        assert_eq!(cursor_row(&rows, 4), 1);
+        assert_eq!(cursor_row(&rows, 10), 2);
+    }
+}
@@ -29,9 +29,13 @@ impl Events {
             loop {
                 // poll for tick rate duration, if no event, sent tick event.
                 if crossterm::event::poll(tick_rate).unwrap() {
-                    if let crossterm::event::Event::Key(key) = crossterm::event::read().unwrap() {
-                        let key = Key::from(key);
-                        if let Err(err) = event_tx.send(InputEvent::Input(key)).await {
+                    let event = match crossterm::event::read().unwrap() {
+                        crossterm::event::Event::Key(key) => Some(InputEvent::Input(Key::from(key))),
+                        crossterm::event::Event::Resize(width, height) => Some(InputEvent::Resize(width, height)),
+                        _ => None,
+                    };
+                    if let Some(event) = event {
+                        if let Err(err) = event_tx.send(event).await {
                             error!("Oops!, {}", err);
                         }
                     }
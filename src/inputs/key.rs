@@ -1,10 +1,62 @@
 use std::fmt::{self, Display, Formatter};
+use std::ops::BitOr;
+use std::str::FromStr;
 
 use crossterm::event;
 
-/// Represents an key.
+/// Modifiers held with a key, as a set
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(0);
+    pub const CTRL: Modifiers = Modifiers(1);
+    pub const ALT: Modifiers = Modifiers(1 << 1);
+    pub const SHIFT: Modifiers = Modifiers(1 << 2);
+
+    pub const fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn union(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+
+    pub const fn without(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 & !other.0)
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        self.union(other)
+    }
+}
+
+/// Modifiers as they are written before a key, e.g. `<C-A-x>`, in this order
+const MODIFIER_NAMES: [(Modifiers, &str); 3] = [(Modifiers::CTRL, "C"), (Modifiers::ALT, "A"), (Modifiers::SHIFT, "S")];
+
+/// Prefixes parsed as modifiers, ignoring case. `M-` is Alt, as for Meta, and
+/// `Ctrl+` and the like are kept for config files written before `C-`.
+const MODIFIER_PREFIXES: [(&str, Modifiers); 7] = [
+    ("C-", Modifiers::CTRL),
+    ("Ctrl+", Modifiers::CTRL),
+    ("A-", Modifiers::ALT),
+    ("M-", Modifiers::ALT),
+    ("Alt+", Modifiers::ALT),
+    ("S-", Modifiers::SHIFT),
+    ("Shift+", Modifiers::SHIFT),
+];
+
+/// A key, without its modifiers
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
-pub enum Key {
+pub enum KeyCode {
     Space,
     /// Both Enter (or Return) and numpad Enter
     Enter,
@@ -37,170 +89,280 @@ pub enum Key {
     /// Page Down key
     PageDown,
 
-    /// F0 key
-    F0,
-    /// F1 key
-    F1,
-    /// F2 key
-    F2,
-    /// F3 key
-    F3,
-    /// F4 key
-    F4,
-    /// F5 key
-    F5,
-    /// F6 key
-    F6,
-    /// F7 key
-    F7,
-    /// F8 key
-    F8,
-    /// F9 key
-    F9,
-    /// F10 key
-    F10,
-    /// F11 key
-    F11,
-    /// F12 key
-    F12,
+    /// Function key, e.g. `F(1)` for F1
+    F(u8),
     Char(char),
-    Ctrl(char),
-    Alt(char),
     Unknown,
 }
 
+/// Keys written by name between angle brackets, as they are shown. `lt` and
+/// `gt` stand for the brackets themselves.
+const NAMED_KEYS: [(&str, KeyCode); 17] = [
+    ("Space", KeyCode::Space),
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Esc", KeyCode::Esc),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Ins", KeyCode::Ins),
+    ("Delete", KeyCode::Delete),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("lt", KeyCode::Char('<')),
+    ("gt", KeyCode::Char('>')),
+];
+
+/// Other names parsed for keys, as in Vim
+const KEY_ALIASES: [(&str, KeyCode); 6] = [
+    ("CR", KeyCode::Enter),
+    ("Return", KeyCode::Enter),
+    ("BS", KeyCode::Backspace),
+    ("Escape", KeyCode::Esc),
+    ("Insert", KeyCode::Ins),
+    ("Del", KeyCode::Delete),
+];
+
+impl KeyCode {
+    /// Name of the key between angle brackets, unless it is a character shown
+    /// as it is
+    fn name(&self) -> Option<String> {
+        match self {
+            KeyCode::F(n) => Some(format!("F{}", n)),
+            KeyCode::Unknown => Some("Unknown".to_owned()),
+            code => NAMED_KEYS.iter().find(|(_, named)| named == code).map(|(name, _)| (*name).to_owned()),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<KeyCode> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Some(KeyCode::Char(c));
+        }
+        let mut named = NAMED_KEYS.iter().chain(KEY_ALIASES.iter());
+        if let Some((_, code)) = named.find(|(key_name, _)| key_name.eq_ignore_ascii_case(name)) {
+            return Some(*code);
+        }
+        name.strip_prefix(['F', 'f']).and_then(|n| n.parse().ok()).map(KeyCode::F)
+    }
+}
+
+/// Represents an key, with the modifiers held
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
 impl Key {
+    /// A key with modifiers. A space is always `Space`, and Shift is taken
+    /// into the character it types, so that `<S-a>` is `A` as terminals
+    /// report it.
+    pub const fn new(code: KeyCode, modifiers: Modifiers) -> Key {
+        match code {
+            KeyCode::Char(' ') => Key {
+                code: KeyCode::Space,
+                modifiers,
+            },
+            KeyCode::Char(c) if modifiers.contains(Modifiers::SHIFT) => Key {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers.without(Modifiers::SHIFT),
+            },
+            code => Key { code, modifiers },
+        }
+    }
+
+    /// A key without modifiers
+    pub const fn plain(code: KeyCode) -> Key {
+        Key::new(code, Modifiers::NONE)
+    }
+
+    pub const fn char(c: char) -> Key {
+        Key::plain(KeyCode::Char(c))
+    }
+
+    pub const fn ctrl(c: char) -> Key {
+        Key::new(KeyCode::Char(c), Modifiers::CTRL)
+    }
+
+    pub const fn alt(c: char) -> Key {
+        Key::new(KeyCode::Char(c), Modifiers::ALT)
+    }
+
+    /// The key code, if no modifier is held
+    pub fn unmodified(&self) -> Option<KeyCode> {
+        Some(self.code).filter(|_| self.modifiers.is_empty())
+    }
+
+    /// Whether the key types text in write mode, rather than running what it
+    /// is bound to in normal mode
+    pub fn types_text(&self) -> bool {
+        matches!(self.unmodified(), Some(KeyCode::Backspace | KeyCode::Enter | KeyCode::Space | KeyCode::Char(_)))
+    }
+
     /// If exit
     pub fn is_exit(&self) -> bool {
-        matches!(self, Key::Ctrl('c') | Key::Char('q') | Key::Esc)
-    }
-
-    /// Returns the function key corresponding to the given number
-    ///
-    /// 1 -> F1, etc...
-    ///
-    /// # Panics
-    ///
-    /// If `n == 0 || n > 12`
-    pub fn from_f(n: u8) -> Key {
-        match n {
-            0 => Key::F0,
-            1 => Key::F1,
-            2 => Key::F2,
-            3 => Key::F3,
-            4 => Key::F4,
-            5 => Key::F5,
-            6 => Key::F6,
-            7 => Key::F7,
-            8 => Key::F8,
-            9 => Key::F9,
-            10 => Key::F10,
-            11 => Key::F11,
-            12 => Key::F12,
-            _ => panic!("unknown function key: F{}", n),
-        }
+        [Key::ctrl('c'), Key::char('q'), Key::plain(KeyCode::Esc)].contains(self)
+    }
+}
+
+impl From<KeyCode> for Key {
+    fn from(code: KeyCode) -> Self {
+        Key::plain(code)
     }
 }
 
+/// Keys are written as in Vim: a character alone as it is, e.g. `q` or `%`,
+/// and other keys by name between angle brackets, after their modifiers, e.g.
+/// `<Enter>`, `<F1>`, `<C-s>`, `<C-A-Del>` or `<S-Tab>`
 impl Display for Key {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match *self {
-            Key::Alt(' ') => write!(f, "<Alt+Space>"),
-            Key::Ctrl(' ') => write!(f, "<Ctrl+Space>"),
-            Key::Char(' ') => write!(f, "<Space>"),
-            Key::Space => write!(f, "<Space>"),
-            Key::Alt(c) => write!(f, "<Alt+{}>", c),
-            Key::Ctrl(c) => write!(f, "<Ctrl+{}>", c),
-            Key::Char(c) => write!(f, "<{}>", c),
-            _ => write!(f, "<{:?}>", self),
+        match self.code {
+            KeyCode::Char(c) if c != '<' && self.modifiers.is_empty() => return write!(f, "{}", c),
+            _ => write!(f, "<")?,
         }
+        for (modifier, prefix) in MODIFIER_NAMES.iter() {
+            if self.modifiers.contains(*modifier) {
+                write!(f, "{}-", prefix)?;
+            }
+        }
+        match (self.code, self.code.name()) {
+            (_, Some(name)) => write!(f, "{}>", name),
+            (KeyCode::Char(c), None) => write!(f, "{}>", c),
+            (code, None) => write!(f, "{:?}>", code),
+        }
+    }
+}
+
+/// Strip a modifier written before a key, unless nothing is left after it
+fn strip_modifier(name: &str) -> Option<(Modifiers, &str)> {
+    MODIFIER_PREFIXES.iter().find_map(|(prefix, modifier)| {
+        let start = name.get(..prefix.len()).filter(|start| start.eq_ignore_ascii_case(prefix))?;
+        Some(&name[start.len()..]).filter(|rest| !rest.is_empty()).map(|rest| (*modifier, rest))
+    })
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Parse a key written as it is shown, e.g. `q`, `<C-s>`, `<A-Space>`,
+    /// `<Enter>` or `<S-F1>`. Modifiers and names ignore case, e.g. `<c-cr>`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid key {}, expected e.g. q, <C-s>, <A-x>, <C-A-Del>, <Enter> or <F1>", value);
+        let mut name = match value.strip_prefix('<').and_then(|value| value.strip_suffix('>')) {
+            Some(name) if !name.is_empty() => name,
+            _ => {
+                let mut chars = value.chars();
+                return match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(Key::char(c)),
+                    _ => Err(invalid()),
+                };
+            },
+        };
+        let mut modifiers = Modifiers::NONE;
+        while let Some((modifier, rest)) = strip_modifier(name) {
+            modifiers = modifiers | modifier;
+            name = rest;
+        }
+        KeyCode::from_name(name).map(|code| Key::new(code, modifiers)).ok_or_else(invalid)
     }
 }
 
 impl From<event::KeyEvent> for Key {
     fn from(key_event: event::KeyEvent) -> Self {
-        match key_event {
-            event::KeyEvent {
-                code: event::KeyCode::Esc,
-                ..
-            } => Key::Esc,
-            event::KeyEvent {
-                code: event::KeyCode::Char(' '),
-                ..
-            } => Key::Space,
-            event::KeyEvent {
-                code: event::KeyCode::Backspace,
-                ..
-            } => Key::Backspace,
-            event::KeyEvent {
-                code: event::KeyCode::Left,
-                ..
-            } => Key::Left,
-            event::KeyEvent {
-                code: event::KeyCode::Right,
-                ..
-            } => Key::Right,
-            event::KeyEvent {
-                code: event::KeyCode::Up,
-                ..
-            } => Key::Up,
-            event::KeyEvent {
-                code: event::KeyCode::Down,
-                ..
-            } => Key::Down,
-            event::KeyEvent {
-                code: event::KeyCode::Home,
-                ..
-            } => Key::Home,
-            event::KeyEvent {
-                code: event::KeyCode::End,
-                ..
-            } => Key::End,
-            event::KeyEvent {
-                code: event::KeyCode::PageUp,
-                ..
-            } => Key::PageUp,
-            event::KeyEvent {
-                code: event::KeyCode::PageDown,
-                ..
-            } => Key::PageDown,
-            event::KeyEvent {
-                code: event::KeyCode::Delete,
-                ..
-            } => Key::Delete,
-            event::KeyEvent {
-                code: event::KeyCode::Insert,
-                ..
-            } => Key::Ins,
-            event::KeyEvent {
-                code: event::KeyCode::F(n),
-                ..
-            } => Key::from_f(n),
-            event::KeyEvent {
-                code: event::KeyCode::Enter,
-                ..
-            } => Key::Enter,
-            event::KeyEvent {
-                code: event::KeyCode::Tab,
-                ..
-            } => Key::Tab,
-
-            // First check for char + modifier
-            event::KeyEvent {
-                code: event::KeyCode::Char(c),
-                modifiers: event::KeyModifiers::ALT,
-            } => Key::Alt(c),
-            event::KeyEvent {
-                code: event::KeyCode::Char(c),
-                modifiers: event::KeyModifiers::CONTROL,
-            } => Key::Ctrl(c),
-
-            event::KeyEvent {
-                code: event::KeyCode::Char(c),
-                ..
-            } => Key::Char(c),
-
-            _ => Key::Unknown,
+        let mut modifiers = Modifiers::NONE;
+        for (flag, modifier) in [
+            (event::KeyModifiers::CONTROL, Modifiers::CTRL),
+            (event::KeyModifiers::ALT, Modifiers::ALT),
+            (event::KeyModifiers::SHIFT, Modifiers::SHIFT),
+        ] {
+            if key_event.modifiers.contains(flag) {
+                modifiers = modifiers | modifier;
+            }
+        }
+        let code = match key_event.code {
+            event::KeyCode::Esc => KeyCode::Esc,
+            event::KeyCode::Backspace => KeyCode::Backspace,
+            event::KeyCode::Left => KeyCode::Left,
+            event::KeyCode::Right => KeyCode::Right,
+            event::KeyCode::Up => KeyCode::Up,
+            event::KeyCode::Down => KeyCode::Down,
+            event::KeyCode::Home => KeyCode::Home,
+            event::KeyCode::End => KeyCode::End,
+            event::KeyCode::PageUp => KeyCode::PageUp,
+            event::KeyCode::PageDown => KeyCode::PageDown,
+            event::KeyCode::Delete => KeyCode::Delete,
+            event::KeyCode::Insert => KeyCode::Ins,
+            event::KeyCode::F(n) => KeyCode::F(n),
+            event::KeyCode::Enter => KeyCode::Enter,
+            event::KeyCode::Tab => KeyCode::Tab,
+            // Terminals report Shift+Tab as a key of its own
+            event::KeyCode::BackTab => {
+                modifiers = modifiers | Modifiers::SHIFT;
+                KeyCode::Tab
+            },
+            event::KeyCode::Char(c) => KeyCode::Char(c),
+            _ => KeyCode::Unknown,
+        };
+        Key::new(code, modifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_keys_as_they_are_shown() {
+        let keys = [
+            Key::char('q'),
+            Key::char('<'),
+            Key::ctrl('s'),
+            Key::alt(','),
+            Key::alt(' '),
+            Key::new(KeyCode::Char('>'), Modifiers::CTRL),
+            Key::new(KeyCode::Char('x'), Modifiers::CTRL | Modifiers::ALT),
+            Key::new(KeyCode::Delete, Modifiers::CTRL | Modifiers::ALT),
+            Key::new(KeyCode::Up, Modifiers::SHIFT),
+            Key::new(KeyCode::F(5), Modifiers::CTRL | Modifiers::SHIFT),
+            Key::plain(KeyCode::Enter),
+            Key::plain(KeyCode::F(12)),
+            Key::plain(KeyCode::PageDown),
+        ];
+        for key in keys {
+            assert_eq!(key.to_string().parse(), Ok(key));
         }
+        assert_eq!(Key::new(KeyCode::Char('x'), Modifiers::ALT | Modifiers::CTRL).to_string(), "<C-A-x>");
+        assert_eq!(Key::char('<').to_string(), "<lt>");
+        assert_eq!("q".parse(), Ok(Key::char('q')));
+        assert_eq!("<Space>".parse(), Ok(Key::plain(KeyCode::Space)));
+        assert_eq!("<Ctrl+s>".parse(), Ok(Key::ctrl('s')));
+        assert_eq!("<c-cr>".parse(), Ok(Key::new(KeyCode::Enter, Modifiers::CTRL)));
+        assert_eq!("<C-->".parse(), Ok(Key::ctrl('-')));
+        assert_eq!("<S-a>".parse(), Ok(Key::char('A')));
+        assert!("<C->".parse::<Key>().is_err());
+        assert!("<Ctrl+>".parse::<Key>().is_err());
+        assert!("Enter".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn should_keep_the_modifiers_of_terminal_keys() {
+        let key = |code, modifiers| Key::from(event::KeyEvent::new(code, modifiers));
+        let ctrl_alt = event::KeyModifiers::CONTROL | event::KeyModifiers::ALT;
+        let ctrl_alt_x = Key::new(KeyCode::Char('x'), Modifiers::CTRL | Modifiers::ALT);
+        assert_eq!(key(event::KeyCode::Char('x'), ctrl_alt), ctrl_alt_x);
+        assert_eq!(key(event::KeyCode::Up, event::KeyModifiers::SHIFT), Key::new(KeyCode::Up, Modifiers::SHIFT));
+        assert_eq!(key(event::KeyCode::Right, event::KeyModifiers::CONTROL), Key::new(KeyCode::Right, Modifiers::CTRL));
+        assert_eq!(key(event::KeyCode::Enter, event::KeyModifiers::ALT), Key::new(KeyCode::Enter, Modifiers::ALT));
+        assert_eq!(key(event::KeyCode::F(2), event::KeyModifiers::SHIFT), Key::new(KeyCode::F(2), Modifiers::SHIFT));
+        assert_eq!(key(event::KeyCode::BackTab, event::KeyModifiers::SHIFT), Key::new(KeyCode::Tab, Modifiers::SHIFT));
+        assert_eq!(key(event::KeyCode::Char('A'), event::KeyModifiers::SHIFT), Key::char('A'));
+        let ctrl_shift = event::KeyModifiers::CONTROL | event::KeyModifiers::SHIFT;
+        assert_eq!(key(event::KeyCode::Char('P'), ctrl_shift), Key::ctrl('P'));
+        assert_eq!(key(event::KeyCode::Char(' '), event::KeyModifiers::NONE), Key::plain(KeyCode::Space));
     }
 }
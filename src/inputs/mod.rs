@@ -6,5 +6,7 @@ pub mod key;
 pub enum InputEvent {
     /// An input event occurred.
     Input(Key),
+    /// The terminal was resized to `(width, height)`
+    Resize(u16, u16),
     Tick,
 }
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+
+use toml::Value;
+
+/// Where the user's config is kept, within the user's config directory
+const CONFIG_FILE: &str = "rust_text_editor/config.toml";
+/// Config of a project, in the working directory, overriding the user's
+pub const LOCAL_CONFIG_FILE: &str = ".rust_text_editor.toml";
+
+pub fn config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join(CONFIG_FILE))
+}
+
+pub fn local_config_path() -> Option<PathBuf> {
+    Some(std::env::current_dir().ok()?.join(LOCAL_CONFIG_FILE))
+}
+
+/// An option of a config file by name, with its value or why it is invalid
+pub type ConfigOption = (String, Result<String, String>);
+
+/// Keys bound to an action in a mode, as written in a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigBinding {
+    pub mode: String,
+    pub action: String,
+    /// The keys, or why they are invalid
+    pub keys: Result<Vec<String>, String>,
+}
+
+/// A user-defined command by name, with the command line it runs or why it
+/// is invalid
+pub type ConfigCommand = (String, Result<String, String>);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    pub options: Vec<ConfigOption>,
+    pub bindings: Vec<ConfigBinding>,
+    pub commands: Vec<ConfigCommand>,
+}
+
+/// Table of the key bindings, by mode
+const KEYS_TABLE: &str = "keys";
+/// Table of the user-defined commands
+const COMMANDS_TABLE: &str = "commands";
+
+/// Parse a config file. Options are named as for `:set` and given as a TOML
+/// string, number or boolean, e.g. `tabwidth = 2` or `numbers = "relative"`.
+/// Their values are returned as written after `:set <option>=`, or as an
+/// error for those of another type. Key bindings are given by mode and
+/// action, as a key or a list of keys, e.g. `[keys.normal]` then
+/// `quit = ["q", "<C-q>"]`. Commands are defined by name as the command line
+/// they run, e.g. `[commands]` then `wide = "tabwidth 8"`.
+pub fn parse(source: &str) -> Result<Config, String> {
+    let mut table: toml::value::Table = toml::from_str(source).map_err(|err| err.to_string())?;
+    let keys = match table.remove(KEYS_TABLE) {
+        Some(Value::Table(keys)) => keys,
+        Some(value) => return Err(format!("{} should be a table of modes, got {}", KEYS_TABLE, value.type_str())),
+        None => Default::default(),
+    };
+    let commands = match table.remove(COMMANDS_TABLE) {
+        Some(Value::Table(commands)) => commands
+            .into_iter()
+            .map(|(name, line)| {
+                let line = match line {
+                    Value::String(line) => Ok(line),
+                    line => Err(format!("expected a command line as a string, got {}", line.type_str())),
+                };
+                (name, line)
+            })
+            .collect(),
+        Some(value) => {
+            return Err(format!("{} should be a table of commands, got {}", COMMANDS_TABLE, value.type_str()))
+        },
+        None => vec![],
+    };
+    let options = table
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::String(value) => Ok(value),
+                Value::Integer(value) => Ok(value.to_string()),
+                Value::Boolean(value) => Ok(if value { "on" } else { "off" }.to_owned()),
+                value => Err(format!("expected a string, a number or a boolean, got {}", value.type_str())),
+            };
+            (name, value)
+        })
+        .collect();
+    let mut bindings = vec![];
+    for (mode, actions) in keys {
+        let actions = match actions {
+            Value::Table(actions) => actions,
+            value => {
+                return Err(format!("{}.{} should be a table of actions, got {}", KEYS_TABLE, mode, value.type_str()))
+            },
+        };
+        for (action, keys) in actions {
+            let keys = match keys {
+                Value::String(key) => Ok(vec![key]),
+                Value::Array(keys) => keys
+                    .into_iter()
+                    .map(|key| match key {
+                        Value::String(key) => Ok(key),
+                        key => Err(format!("expected keys as strings, got {}", key.type_str())),
+                    })
+                    .collect(),
+                keys => Err(format!("expected a key or a list of keys, got {}", keys.type_str())),
+            };
+            bindings.push(ConfigBinding {
+                mode: mode.clone(),
+                action,
+                keys,
+            });
+        }
+    }
+    Ok(Config {
+        options,
+        bindings,
+        commands,
+    })
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> std::io::Result<Config> {
+    let content = std::fs::read_to_string(path)?;
+    parse(&content).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_options_as_set_values() {
+        let config = parse("tabwidth = 2\nlist = true\nnumbers = \"relative\"\nguides = [1]\n").unwrap();
+        assert!(config.bindings.is_empty());
+        assert_eq!(
+            config.options,
+            vec![
+                ("guides".to_owned(), Err("expected a string, a number or a boolean, got array".to_owned())),
+                ("list".to_owned(), Ok("on".to_owned())),
+                ("numbers".to_owned(), Ok("relative".to_owned())),
+                ("tabwidth".to_owned(), Ok("2".to_owned())),
+            ]
+        );
+        assert!(parse("tabwidth = ").is_err());
+    }
+
+    #[test]
+    fn should_read_key_bindings_by_mode() {
+        let source = "[keys.normal]\nquit = [\"q\", \"<C-q>\"]\n[keys.write]\nend_write_mode = \"<Esc>\"\n";
+        let config = parse(source).unwrap();
+        assert!(config.options.is_empty());
+        assert_eq!(
+            config.bindings,
+            vec![
+                ConfigBinding {
+                    mode: "normal".to_owned(),
+                    action: "quit".to_owned(),
+                    keys: Ok(vec!["q".to_owned(), "<C-q>".to_owned()]),
+                },
+                ConfigBinding {
+                    mode: "write".to_owned(),
+                    action: "end_write_mode".to_owned(),
+                    keys: Ok(vec!["<Esc>".to_owned()]),
+                },
+            ]
+        );
+        assert!(parse("keys = 1").is_err());
+    }
+
+    #[test]
+    fn should_read_user_commands_by_name() {
+        let config = parse("[commands]
+wide = \"tabwidth 8\"
+bad = 8
+").unwrap();
+        assert!(config.options.is_empty());
+        assert_eq!(
+            config.commands,
+            vec![
+                ("bad".to_owned(), Err("expected a command line as a string, got integer".to_owned())),
+                ("wide".to_owned(), Ok("tabwidth 8".to_owned())),
+            ]
+        );
+        assert!(parse("commands = \"wide\"").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::Metadata;
+use std::hash::Hasher;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Outcome of comparing a file on disk against its last known state
+pub enum DiskChange {
+    Unchanged,
+    /// Metadata changed but not the content, e.g. after a `touch`
+    Touched(DiskState),
+    /// Content changed, with the new content
+    Changed(Vec<u8>, DiskState),
+    /// Metadata changed on a file too big to be read to tell whether its
+    /// content did, which is then assumed
+    Resized(DiskState),
+}
+
+/// What a file looked like on disk when it was last loaded or saved, used to
+/// notice other programs changing it behind our back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskState {
+    modified: Option<SystemTime>,
+    len: u64,
+    /// `None` for files too big to be read whole
+    hash: Option<u64>,
+}
+
+impl DiskState {
+    /// Disk state of a file whose content was just read or written
+    pub fn new(metadata: &Metadata, content: &[u8]) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: Some(hash_bytes(content)),
+        }
+    }
+
+    /// Disk state of a file too big to be read whole, told apart by its
+    /// metadata only
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: None,
+        }
+    }
+
+    /// Size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.len
+    }
+
+    /// Compare the file at `path` against this state. The content is only read
+    /// when the metadata hints at a change.
+    pub fn check<P: AsRef<Path>>(&self, path: P) -> std::io::Result<DiskChange> {
+        let metadata = std::fs::metadata(&path)?;
+        if self.modified == metadata.modified().ok() && self.len == metadata.len() {
+            return Ok(DiskChange::Unchanged);
+        }
+        if self.hash.is_none() {
+            return Ok(DiskChange::Resized(DiskState::from_metadata(&metadata)));
+        }
+        let content = std::fs::read(&path)?;
+        let disk_state = DiskState::new(&metadata, &content);
+        if disk_state.hash == self.hash {
+            Ok(DiskChange::Touched(disk_state))
+        } else {
+            Ok(DiskChange::Changed(content, disk_state))
+        }
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
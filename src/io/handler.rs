@@ -1,10 +1,27 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use eyre::Result;
-use log::{error, info, warn};
+use log::{debug, info, warn};
 use copypasta::{ClipboardContext, ClipboardProvider};
 
+use super::config::{self, ConfigBinding};
+use super::disk_state::{DiskChange, DiskState};
+use super::layout;
+use super::session::{self, Session};
+use super::swap::{self, SwapJob};
 use super::IoEvent;
+use crate::app::commands::{Command, UserCommand};
+use crate::app::diff;
+use crate::app::editing::ViewState;
+use crate::app::hex_buffer;
+use crate::app::actions::Action;
+use crate::app::keymap::{self, KeySequence, Mode};
+use crate::app::messages::Severity;
+use crate::app::options::{Assignment, EditorOption};
+use crate::app::prompt::{Prompt, PromptAnswer, PromptKind};
+use crate::app::theme::{self, Theme};
+use crate::app::windows::{Window, Windows};
 use crate::app::{App};
 
 /// In the IO thread, we handle IO event without blocking the UI thread
@@ -31,13 +48,18 @@ impl IoAsyncHandler {
             IoEvent::ScrollUp => self.scroll_vertical(-1).await,
             IoEvent::ScrollLeft => self.scroll_horizontal(-1).await,
             IoEvent::ScrollRight => self.scroll_horizontal(1).await,
+            IoEvent::JournalSwapFiles => self.journal_swap_files().await,
+            IoEvent::AnswerPrompt(answer) => self.answer_prompt(answer).await,
+            IoEvent::CheckExternalChanges => self.check_external_changes().await,
+            IoEvent::RunCommand(command) => self.run_command(command).await,
+            IoEvent::MoveTabLeft => self.move_tab(-1).await,
+            IoEvent::MoveTabRight => self.move_tab(1).await,
         };
 
+        let mut app = self.app.lock().await;
         if let Err(err) = result {
-            error!("Oops, something wrong happen: {:?}", err);
+            app.notify(Severity::Error, format!("Oops, something wrong happen: {:#}", err));
         }
-
-        let mut app = self.app.lock().await;
         app.loaded();
     }
 
@@ -47,6 +69,26 @@ impl IoAsyncHandler {
         let mut app = self.app.lock().await;
         app.initialized(); // we could update the app state
         info!("👍 Application initialized");
+        if let Some(path) = layout::layout_path().filter(|path| path.exists()) {
+            match layout::read(&path) {
+                Ok(panels) => app.set_panels(panels),
+                Err(err) => app.notify(Severity::Error, format!("Failed to read layout {}: {}", path.display(), err)),
+            }
+        }
+        // The project's config goes last, to override the user's
+        for path in [config::config_path(), config::local_config_path()].into_iter().flatten() {
+            if path.exists() {
+                Self::apply_config(&mut app, &path);
+            }
+        }
+        if let Some(path) = session::directory_session_path().filter(|path| path.exists()) {
+            match session::read(&path) {
+                Ok(session) => Self::restore_session(&mut app, session)?,
+                Err(err) => {
+                    app.notify(Severity::Error, format!("🗂 Failed to read session {}: {}", path.display(), err))
+                },
+            }
+        }
         Ok(())
     }
 
@@ -62,37 +104,72 @@ impl IoAsyncHandler {
     /// Open a file
     async fn open_file(&mut self) -> Result<()> {
         let mut ctx = ClipboardContext::new().unwrap();
-        if let Some(clipboard_text) = ctx.get_contents().ok() {
+        if let Ok(clipboard_text) = ctx.get_contents() {
             let mut app = self.app.lock().await;
-            let result = app.open_files_data_mut().open_file(&clipboard_text);
-            match result {
-                Ok(()) => {
-                    info!("📄 Opened file: {}", clipboard_text);
-                    Ok(())
-                },
-                Err(err) => {
-                    error!("📄 Failed to open file: {}", err);
-                    Ok(())
-                }
-            }
+            Self::open_path(&mut app, &clipboard_text)?;
+            Ok(())
         } else {
-            warn!("📄 Open file: <empty>");
+            let mut app = self.app.lock().await;
+            app.notify(Severity::Warning, "📄 Nothing to open, the clipboard is empty");
             Ok(())
         }
     }
 
+    /// Open the file at `file_path`, offering to recover its swap file if one
+    /// was left behind. Returns whether it was opened.
+    fn open_path(app: &mut App, file_path: &str) -> Result<bool> {
+        match app.open_files_data_mut().open_file(file_path) {
+            Ok(()) => {
+                // Known by its canonical path from now on
+                let opened_path = app.open_files_data_mut().get_currently_selected_file_path();
+                let file_path = &opened_path.unwrap_or(file_path).to_owned();
+                app.notify(Severity::Info, format!("📄 Opened file: {}", file_path));
+                let files_data = app.open_files_data_mut();
+                let content = files_data.get_journal_content(files_data.get_currently_selected_file_index());
+                if let Some(swap_content) = swap::read(file_path) {
+                    if Some(&swap_content) == content.as_ref() {
+                        // Left behind after the changes were saved after all
+                        info!("💾 Removing stale swap file of {}", file_path);
+                        swap::remove(file_path)?;
+                    } else {
+                        warn!("💾 Found swap file of {}", file_path);
+                        app.show_prompt(Prompt::new(PromptKind::RecoverSwap {
+                            file_path: file_path.to_owned(),
+                            swap_content,
+                        }));
+                    }
+                }
+                Ok(true)
+            },
+            Err(err) => {
+                app.notify(Severity::Error, format!("📄 Failed to open file: {}", err));
+                Ok(false)
+            }
+        }
+    }
+
     /// Close the file
     async fn close_file(&mut self) -> Result<()> {
+        let index = self.app.lock().await.open_files_data_mut().get_currently_selected_file_index();
+        self.close_file_at(index).await
+    }
+
+    /// Close the file at `index`, removing the swap file this session wrote for it
+    async fn close_file_at(&mut self, index: usize) -> Result<()> {
         let mut app = self.app.lock().await;
-        let current_opened_file_path = app.open_files_data_mut().get_currently_selected_file_path();
-        let result = app.open_files_data_mut().close_file();
+        let file_path = app.open_files_data_mut().get_open_file_paths().get(index).cloned().unwrap_or_default();
+        let owns_swap = app.open_files_data_mut().owned_swap_file_paths().contains(&file_path);
+        let result = app.open_files_data_mut().close_file_at(index);
         match result {
             Ok(()) => {
-                info!("📄 Closed file: {}", current_opened_file_path);
+                app.notify(Severity::Info, format!("📄 Closed file: {}", file_path));
+                if owns_swap {
+                    swap::remove(&file_path)?;
+                }
                 Ok(())
             },
             Err(err) => {
-                error!("📄 Failed to Close file: {}", err);
+                app.notify(Severity::Error, format!("📄 Failed to close file: {}", err));
                 Ok(())
             }
         }
@@ -100,17 +177,53 @@ impl IoAsyncHandler {
 
     /// Save the file
     async fn save_file(&mut self) -> Result<()> {
+        let index = {
+            let mut app = self.app.lock().await;
+            // Saving with no file open saves a brand new one
+            app.open_files_data_mut().ensure_currently_selected_content()
+        };
+        self.save_file_at(index).await?;
+        Ok(())
+    }
+
+    /// Save the file at `index`, returns whether it was saved
+    async fn save_file_at(&mut self, index: usize) -> Result<bool> {
         let mut app = self.app.lock().await;
-        let current_opened_file_path = app.open_files_data_mut().get_currently_selected_file_path();
-        let result = app.open_files_data_mut().save_file();
+        let file_path = app.open_files_data_mut().get_open_file_paths().get(index).cloned().unwrap_or_default();
+        // Never clobber changes made by another program without asking
+        if let Some(disk_state) = app.open_files_data_mut().get_disk_state(index).cloned() {
+            match disk_state.check(&file_path) {
+                Ok(DiskChange::Changed(content, new_disk_state)) => {
+                    app.notify(Severity::Warning, format!("📄 Not saving, {} was changed on disk", file_path));
+                    app.open_files_data_mut().set_disk_state(index, Some(new_disk_state));
+                    let disk_content = app.open_files_data_mut().render_disk_content(index, &content);
+                    app.show_prompt(Prompt::new(PromptKind::ExternalChange {
+                        file_path,
+                        disk_content,
+                        saving: true,
+                    }));
+                    return Ok(false);
+                },
+                Ok(DiskChange::Touched(new_disk_state)) => {
+                    app.open_files_data_mut().set_disk_state(index, Some(new_disk_state));
+                },
+                // Gone or unreadable, saving recreates it
+                _ => {},
+            }
+        }
+        let owns_swap = app.open_files_data_mut().owned_swap_file_paths().contains(&file_path);
+        let result = app.open_files_data_mut().save_file_at(index);
         match result {
             Ok(()) => {
-                info!("📄 Saved file: {}", current_opened_file_path);
-                Ok(())
+                app.notify(Severity::Info, format!("📄 Saved file: {}", file_path));
+                if owns_swap {
+                    swap::remove(&file_path)?;
+                }
+                Ok(true)
             }
             Err(err) => {
-                error!("📄 Failed to save file: {}", err);
-                Ok(())
+                app.notify(Severity::Error, format!("📄 Failed to save file: {}", err));
+                Ok(false)
             }
         }
     }
@@ -119,7 +232,6 @@ impl IoAsyncHandler {
     async fn next_file(&mut self) -> Result<()> {
         let mut app = self.app.lock().await;
         app.open_files_data_mut().select_next_file();
-        app.reset_scroll();
         Ok(())
     }
 
@@ -127,7 +239,22 @@ impl IoAsyncHandler {
     async fn previous_file(&mut self) -> Result<()> {
         let mut app = self.app.lock().await;
         app.open_files_data_mut().select_previous_file();
-        app.reset_scroll();
+        Ok(())
+    }
+
+    /// Move the current file `delta` tabs to the right
+    async fn move_tab(&mut self, delta: i32) -> Result<()> {
+        let mut app = self.app.lock().await;
+        let files_data = app.open_files_data_mut();
+        let from = files_data.get_currently_selected_file_index();
+        let result = match (from as i64 + delta as i64).try_into() {
+            Ok(to) => files_data.move_file(from, to).map(|()| to),
+            Err(_) => Err("already the first one".to_owned()),
+        };
+        match result {
+            Ok(to) => info!("📑 Moved tab {} to {}", from + 1, to + 1),
+            Err(err) => app.notify(Severity::Warning, format!("📑 Failed to move tab: {}", err)),
+        }
         Ok(())
     }
 
@@ -135,13 +262,13 @@ impl IoAsyncHandler {
     /// direction: 1 for down, -1 for up
     async fn scroll_vertical(&mut self, direction: i32) -> Result<()> {
         let mut app = self.app.lock().await;
-        match app.scroll_horizontal(direction) {
+        match app.scroll_vertical(direction) {
             Ok(()) => {
-                info!("↨ Scrolled vertical. Current Scroll Offset: {:?}", app.state().get_scroll_offset());
+                debug!("↨ Scrolled vertical. Current Scroll Offset: {:?}, Cursor: {:?}", app.state().get_scroll_offset(), app.state().get_cursor());
                 Ok(())
             },
             Err(err) => {
-                error!("Failed to scroll vertical: {}", err);
+                debug!("Failed to scroll vertical: {}", err);
                 Ok(())
             }
         }
@@ -153,13 +280,469 @@ impl IoAsyncHandler {
         let mut app = self.app.lock().await;
         match app.scroll_horizontal(direction) {
             Ok(()) => {
-                info!("🔛 Scrolled horizontal. Current Scroll Offset: {:?}", app.state().get_scroll_offset());
+                debug!("🔛 Scrolled horizontal. Current Scroll Offset: {:?}, Cursor: {:?}", app.state().get_scroll_offset(), app.state().get_cursor());
                 Ok(())
             },
             Err(err) => {
-                error!("Failed to scroll horizonta: {}", err);
+                debug!("Failed to scroll horizontal: {}", err);
                 Ok(())
             }
         }
     }
+
+    /// Journal unsaved edits to swap files, so they survive a crash
+    async fn journal_swap_files(&mut self) -> Result<()> {
+        let jobs: Vec<SwapJob> = {
+            let mut app = self.app.lock().await;
+            app.open_files_data_mut().take_swap_jobs()
+        };
+        // Write without holding the lock, the UI keeps going meanwhile
+        for job in jobs.iter() {
+            if let Err(err) = job.run() {
+                let mut app = self.app.lock().await;
+                app.notify(Severity::Error, format!("💾 Failed to journal {}: {}", job.file_path(), err));
+            }
+        }
+        Ok(())
+    }
+
+    /// Act on the answer picked for the displayed prompt
+    async fn answer_prompt(&mut self, answer: PromptAnswer) -> Result<()> {
+        let mut app = self.app.lock().await;
+        let mut prompt = match app.take_prompt() {
+            Some(prompt) => prompt,
+            None => return Ok(()),
+        };
+        let file_path = match prompt.kind() {
+            PromptKind::QuitWithUnsavedChanges { .. } => {
+                drop(app);
+                return self.save_all_and_quit().await;
+            },
+            PromptKind::RecoverSwap { file_path, .. }
+            | PromptKind::ExternalChange { file_path, .. }
+            | PromptKind::CloseWithUnsavedChanges { file_path } => file_path.clone(),
+        };
+        let index = match app.open_files_data_mut().find_file(&file_path) {
+            Some(index) => index,
+            None => {
+                app.notify(Severity::Error, format!("📄 Failed to answer prompt: {} is not open", file_path));
+                return Ok(());
+            },
+        };
+        match (prompt.kind().clone(), answer) {
+            (_, PromptAnswer::Diff) => {
+                // Binary content is diffed as hex dumps
+                let buffer_content = app.open_files_data_mut().get_journal_content(index).unwrap_or_default();
+                let other_content = match prompt.kind() {
+                    PromptKind::RecoverSwap { swap_content, .. } => swap_content.as_str(),
+                    PromptKind::ExternalChange { disk_content, .. } => disk_content.as_str(),
+                    _ => "",
+                };
+                let diff = diff::unified(&buffer_content, other_content, 3);
+                prompt.set_diff(diff);
+                app.restore_prompt(prompt);
+            },
+            (PromptKind::RecoverSwap { swap_content, .. }, PromptAnswer::Recover) => {
+                match app.open_files_data_mut().recover_file(index, &swap_content) {
+                    Ok(()) => app.notify(Severity::Info, format!("💾 Recovered unsaved changes of {}", file_path)),
+                    Err(err) => app.notify(Severity::Error, format!("💾 Failed to recover {}: {}", file_path, err)),
+                }
+            },
+            (PromptKind::RecoverSwap { .. }, PromptAnswer::DeleteSwap) => {
+                swap::remove(&file_path)?;
+                app.notify(Severity::Info, format!("💾 Deleted swap file of {}", file_path));
+            },
+            (PromptKind::RecoverSwap { .. }, PromptAnswer::Keep) => {
+                // Journaling the next edit would overwrite it otherwise
+                let kept_path = swap::move_aside(&file_path)?;
+                app.notify(Severity::Info, format!("💾 Kept swap file of {} as {}", file_path, kept_path.display()));
+            },
+            (PromptKind::ExternalChange { .. }, PromptAnswer::Reload) => {
+                let content = std::fs::read(&file_path)?;
+                let metadata = std::fs::metadata(&file_path)?;
+                let disk_state = DiskState::new(&metadata, &content);
+                app.open_files_data_mut().reload_file(index, content, disk_state);
+                app.notify(Severity::Info, format!("🔄 Reloaded {} from disk, discarding your changes", file_path));
+            },
+            (PromptKind::ExternalChange { saving, .. }, PromptAnswer::KeepMine) => {
+                app.notify(Severity::Info, format!("📄 Kept your changes of {} over the version on disk", file_path));
+                if saving {
+                    drop(app);
+                    self.save_file_at(index).await?;
+                }
+            },
+            (PromptKind::CloseWithUnsavedChanges { .. }, PromptAnswer::SaveAndClose) => {
+                drop(app);
+                if self.save_file_at(index).await? {
+                    self.close_file_at(index).await?;
+                }
+            },
+            (PromptKind::CloseWithUnsavedChanges { .. }, PromptAnswer::CloseWithoutSaving) => {
+                drop(app);
+                self.close_file_at(index).await?;
+            },
+            (_, answer) => warn!("No answer accociated to {:?}", answer),
+        }
+        Ok(())
+    }
+
+    /// Save every file with unsaved changes, then quit if all of them could be saved
+    async fn save_all_and_quit(&mut self) -> Result<()> {
+        let modified_file_indices = {
+            let mut app = self.app.lock().await;
+            app.open_files_data_mut().get_modified_file_indices()
+        };
+        let mut all_saved = true;
+        for index in modified_file_indices {
+            all_saved &= self.save_file_at(index).await?;
+        }
+        let mut app = self.app.lock().await;
+        if all_saved {
+            app.request_exit();
+        } else {
+            app.notify(Severity::Warning, "Not quitting, some files could not be saved");
+        }
+        Ok(())
+    }
+
+    /// Poll the open files for changes made by other programs. Files without
+    /// unsaved changes are reloaded, otherwise the user is asked what to keep.
+    async fn check_external_changes(&mut self) -> Result<()> {
+        let disk_states = {
+            let mut app = self.app.lock().await;
+            app.open_files_data_mut().get_disk_states()
+        };
+        for (file_path, disk_state) in disk_states {
+            let change = disk_state.check(&file_path);
+            let mut app = self.app.lock().await;
+            let index = match app.open_files_data_mut().find_file(&file_path) {
+                Some(index) => index,
+                None => continue,
+            };
+            match change {
+                Ok(DiskChange::Unchanged) => {},
+                Ok(DiskChange::Touched(new_disk_state)) => {
+                    app.open_files_data_mut().set_disk_state(index, Some(new_disk_state));
+                },
+                // Large files are read-only, so there is nothing of the user's to lose
+                Ok(DiskChange::Resized(new_disk_state)) => {
+                    app.open_files_data_mut().reload_large_file(index, new_disk_state);
+                    app.notify(Severity::Info, format!("🔄 Reloaded {}, it was changed on disk", file_path));
+                },
+                Ok(DiskChange::Changed(content, new_disk_state)) => {
+                    if !app.open_files_data_mut().is_modified(index) {
+                        app.open_files_data_mut().reload_file(index, content, new_disk_state);
+                        app.notify(Severity::Info, format!("🔄 Reloaded {}, it was changed on disk", file_path));
+                    } else {
+                        warn!("🔄 {} was changed on disk", file_path);
+                        app.open_files_data_mut().set_disk_state(index, Some(new_disk_state));
+                        let disk_content = app.open_files_data_mut().render_disk_content(index, &content);
+                        app.show_prompt(Prompt::new(PromptKind::ExternalChange {
+                            file_path,
+                            disk_content,
+                            saving: false,
+                        }));
+                    }
+                },
+                Err(err) => {
+                    app.notify(Severity::Warning, format!("🔄 {} can no longer be read from disk: {}", file_path, err));
+                    app.open_files_data_mut().set_disk_state(index, None);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a command typed in the command line
+    async fn run_command(&mut self, command: Command) -> Result<()> {
+        let mut app = self.app.lock().await;
+        let result = match command {
+            Command::Goto(target) => Self::goto(&mut app, &target),
+            Command::Find(pattern) => {
+                let pattern = if pattern.is_empty() {
+                    app.last_search().cloned().unwrap_or_default()
+                } else {
+                    pattern
+                };
+                app.set_last_search(&pattern);
+                Self::find(&mut app, &pattern)
+            },
+            Command::MkSession(path) => Self::make_session(&mut app, path),
+            Command::LoadSession(path) => Self::load_session(&mut app, path),
+            Command::RmSession => Self::remove_session(),
+            Command::Tab(position) => Self::select_tab(&mut app, position),
+            Command::TabMove(position) => Self::move_tab_to(&mut app, position),
+            // The commands switching options are shorthands for `:set`
+            Command::Numbers(line_numbers) => {
+                app.set_option(EditorOption::Numbers, &Assignment::Set(line_numbers.to_string()))
+            },
+            Command::Wrap(Some(wrap)) => app.set_option(EditorOption::Wrap, &Assignment::Set(wrap.to_string())),
+            Command::Wrap(None) => app.set_option(EditorOption::Wrap, &Assignment::Toggle),
+            Command::TabWidth(width) => app.set_option(EditorOption::TabWidth, &Assignment::Set(width.to_string())),
+            Command::List(list) => app.set_option(EditorOption::List, &Assignment::from_switch(list)),
+            Command::ListChars(list_chars) => {
+                app.set_option(EditorOption::ListChars, &Assignment::Set(list_chars.to_string()))
+            },
+            Command::Guides(guides) => app.set_option(EditorOption::Guides, &Assignment::from_switch(guides)),
+            Command::Scrollbar(scrollbar) => app.set_option(EditorOption::Scrollbar, &Assignment::from_switch(scrollbar)),
+            Command::Minimap(minimap) => app.set_option(EditorOption::Minimap, &Assignment::from_switch(minimap)),
+            Command::Theme(name) => Self::set_theme(&mut app, name),
+            Command::Set(Some((option, assignment))) => Self::set_option(&mut app, option, &assignment),
+            Command::Set(None) => Ok(EditorOption::all()
+                .iter()
+                .map(|option| format!("{}={}", option, app.option_value(*option)))
+                .collect::<Vec<_>>()
+                .join(" ")),
+            Command::Messages => {
+                app.show_messages();
+                return Ok(());
+            },
+        };
+        match result {
+            Ok(message) => app.notify(Severity::Info, format!("🔎 {}", message)),
+            Err(err) => app.notify(Severity::Error, format!("🔎 {}", err)),
+        }
+        Ok(())
+    }
+
+    /// Write the open files and their views to `path`, or to the session of
+    /// the working directory
+    fn make_session(app: &mut App, path: Option<String>) -> Result<String, String> {
+        let path = Self::session_path(path)?;
+        let session = app.to_session();
+        session::write(&path, &session).map_err(|err| format!("Failed to write session {}: {}", path.display(), err))?;
+        Ok(format!("Saved session of {} files to {}", session.files.len(), path.display()))
+    }
+
+    /// Restore the session at `path`, or the session of the working directory
+    fn load_session(app: &mut App, path: Option<String>) -> Result<String, String> {
+        let path = Self::session_path(path)?;
+        let session = session::read(&path).map_err(|err| format!("Failed to read session {}: {}", path.display(), err))?;
+        Self::restore_session(app, session).map_err(|err| err.to_string())?;
+        Ok(format!("Restored session {}", path.display()))
+    }
+
+    fn remove_session() -> Result<String, String> {
+        let path = Self::session_path(None)?;
+        session::remove(&path).map_err(|err| format!("Failed to remove session {}: {}", path.display(), err))?;
+        Ok("Removed the session of the working directory".to_owned())
+    }
+
+    /// Switch to the built-in theme `name`, or else to the user theme of that
+    /// name. Without a name, list the themes.
+    fn set_theme(app: &mut App, name: Option<String>) -> Result<String, String> {
+        let name = match name {
+            Some(name) => name,
+            None => {
+                return Ok(format!(
+                    "Theme: {}, built-in themes: {}, user themes go in {}",
+                    app.theme().name,
+                    Theme::built_in_names().join(", "),
+                    theme::user_theme_path("<name>").map_or("no config directory".to_owned(), |path| path.display().to_string())
+                ))
+            },
+        };
+        let theme = match Theme::built_in(&name) {
+            Some(theme) => theme,
+            None => {
+                let path = theme::user_theme_path(&name).ok_or_else(|| "No config directory for user themes".to_owned())?;
+                let source = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("Failed to read theme {}: {}", path.display(), err))?;
+                Theme::parse(&name, &source)?
+            },
+        };
+        app.set_theme(theme);
+        Ok(format!("Theme: {}", name))
+    }
+
+    /// Set the options and bind the keys of the config file at `path`,
+    /// reporting those which are invalid and the keys bound to several
+    /// actions
+    fn apply_config(app: &mut App, path: &Path) {
+        let config = match config::read(path) {
+            Ok(config) => config,
+            Err(err) => {
+                app.notify(Severity::Error, format!("⚙ Failed to read config {}: {}", path.display(), err));
+                return;
+            },
+        };
+        for (name, value) in config.options {
+            let result = value.and_then(|value| {
+                let option = EditorOption::from_name(&name)?;
+                Self::set_option(app, option, &Assignment::Set(value))
+            });
+            if let Err(err) = result {
+                app.notify(Severity::Error, format!("⚙ Invalid option {} in {}: {}", name, path.display(), err));
+            }
+        }
+        for (name, line) in config.commands {
+            match line.and_then(|line| UserCommand::new(&name, &line)) {
+                Ok(user_command) => app.define_command(user_command),
+                Err(err) => {
+                    app.notify(Severity::Error, format!("⚙ Invalid command {} in {}: {}", name, path.display(), err))
+                },
+            }
+        }
+        if !config.bindings.is_empty() {
+            let mut keymap = app.actions().keymap().clone();
+            // Those of a config read before were reported already
+            let known_conflicts = keymap.conflicts();
+            for binding in config.bindings {
+                match Self::parse_binding(&binding) {
+                    Ok((mode, action, keys)) => keymap.bind(mode, action, keys),
+                    Err(err) => {
+                        let name = format!("keys.{}.{}", binding.mode, binding.action);
+                        app.notify(Severity::Error, format!("⚙ Invalid binding {} in {}: {}", name, path.display(), err));
+                    },
+                }
+            }
+            for conflict in keymap.conflicts().into_iter().filter(|conflict| !known_conflicts.contains(conflict)) {
+                app.notify(Severity::Error, format!("⚙ {} in {}", conflict, path.display()));
+            }
+            app.set_keymap(keymap);
+        }
+        info!("⚙ Applied config {}", path.display());
+    }
+
+    fn parse_binding(binding: &ConfigBinding) -> Result<(Mode, Action, Vec<KeySequence>), String> {
+        let mode = binding.mode.parse()?;
+        let action = Action::from_name(&binding.action)?;
+        let sequences = binding.keys.clone()?.iter().map(|keys| keymap::parse_sequence(keys)).collect::<Result<_, _>>()?;
+        Ok((mode, action, sequences))
+    }
+
+    /// Apply `assignment` to an option, loading the theme when it is the one
+    /// set
+    fn set_option(app: &mut App, option: EditorOption, assignment: &Assignment) -> Result<String, String> {
+        match (option, assignment) {
+            (EditorOption::Theme, Assignment::Set(name)) => Self::set_theme(app, Some(name.clone())),
+            (EditorOption::Theme, Assignment::Show) => Ok(format!("theme={}", app.theme().name)),
+            (EditorOption::Theme, _) => Err("theme is not a switch, set it with theme=<name>".to_owned()),
+            _ => app.set_option(option, assignment),
+        }
+    }
+
+    fn session_path(path: Option<String>) -> Result<PathBuf, String> {
+        match path {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => session::directory_session_path().ok_or_else(|| "No data directory for sessions".to_owned()),
+        }
+    }
+
+    /// Open the files of `session` that are not open yet, and put their views,
+    /// the windows and the panels back the way they were
+    fn restore_session(app: &mut App, session: Session) -> Result<()> {
+        let mut selected = None;
+        let mut restored = 0;
+        for (position, session_file) in session.files.iter().enumerate() {
+            let index = match app.open_files_data_mut().find_file(&session_file.path) {
+                Some(index) => index,
+                None if Self::open_path(app, &session_file.path)? => {
+                    app.open_files_data_mut().get_currently_selected_file_index()
+                },
+                None => continue,
+            };
+            let files_data = app.open_files_data_mut();
+            files_data.set_view(
+                index,
+                ViewState {
+                    scroll_offset: session_file.scroll_offset,
+                    cursor: session_file.cursor,
+                },
+            );
+            if let Some(hex_cursor) = session_file.hex_cursor {
+                files_data.select_file(index);
+                if let Some(hex_buffer) = files_data.get_currently_selected_hex_buffer_mut() {
+                    hex_buffer.goto(hex_cursor).ok();
+                }
+            }
+            restored += 1;
+            if position == session.selected || selected.is_none() {
+                selected = Some(index);
+            }
+        }
+        if let Some(selected) = selected {
+            app.open_files_data_mut().select_file(selected);
+        }
+        if let Some(splits) = &session.splits {
+            // Windows of files that could not be opened show the selected file
+            let files_data = app.open_files_data_mut();
+            let selected_index = files_data.get_currently_selected_file_index();
+            let selected_id = files_data.get_file_id(selected_index).unwrap_or_default();
+            let windows = session
+                .windows
+                .iter()
+                .map(|window| Window {
+                    file_id: files_data
+                        .find_file(&window.path)
+                        .and_then(|index| files_data.get_file_id(index))
+                        .unwrap_or(selected_id),
+                    view: ViewState {
+                        scroll_offset: window.scroll_offset,
+                        cursor: window.cursor,
+                    },
+                })
+                .collect();
+            match Windows::from_splits(windows, splits) {
+                Some(windows) => app.set_windows(windows),
+                None => app.notify(Severity::Warning, "🗂 Ignored the windows of the session, they do not fit"),
+            }
+        }
+        if let Some(panels) = session.panels {
+            app.set_panels(panels);
+        }
+        app.notify(Severity::Info, format!("🗂 Restored {} of {} files of the session", restored, session.files.len()));
+        Ok(())
+    }
+
+    /// Select the tab at the 1-based `position`
+    fn select_tab(app: &mut App, position: usize) -> Result<String, String> {
+        let files_data = app.open_files_data_mut();
+        if position > files_data.get_open_file_contents().len() {
+            return Err(format!("No tab at position {}", position));
+        }
+        files_data.select_file(position - 1);
+        Ok(format!("At tab {}", position))
+    }
+
+    /// Move the selected tab to the 1-based `position`
+    fn move_tab_to(app: &mut App, position: usize) -> Result<String, String> {
+        let files_data = app.open_files_data_mut();
+        let from = files_data.get_currently_selected_file_index();
+        files_data.move_file(from, position - 1)?;
+        Ok(format!("Moved tab {} to {}", from + 1, position))
+    }
+
+    /// Go to a byte offset in the hex view, or to a line otherwise
+    fn goto(app: &mut App, target: &str) -> Result<String, String> {
+        match app.state_mut().get_hex_buffer_mut() {
+            Some(hex_buffer) => {
+                let offset = hex_buffer::parse_offset(target)?;
+                hex_buffer.goto(offset)?;
+                Ok(format!("At offset {:#010x}", offset))
+            },
+            None => {
+                let line = target.parse::<usize>().map_err(|_| format!("Invalid line {}", target))?;
+                app.state_mut().goto_line(line)?;
+                Ok(format!("At line {}", line))
+            },
+        }
+    }
+
+    /// Find a byte sequence in the hex view, or text otherwise
+    fn find(app: &mut App, pattern: &str) -> Result<String, String> {
+        if pattern.is_empty() {
+            return Err("Nothing to find".to_owned());
+        }
+        match app.state_mut().get_hex_buffer_mut() {
+            Some(hex_buffer) => {
+                let offset = hex_buffer.find(&hex_buffer::parse_bytes(pattern)?)?;
+                Ok(format!("Found {} at offset {:#010x}", pattern, offset))
+            },
+            None => {
+                let line = app.state_mut().find_text(pattern)?;
+                Ok(format!("Found {} at line {}", pattern, line))
+            },
+        }
+    }
 }
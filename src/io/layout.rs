@@ -0,0 +1,23 @@
+use std::path::{Path, PathBuf};
+
+use super::session;
+use crate::app::panels::Panels;
+
+/// Where the layout of the panels is kept, within the user's data directory
+const LAYOUT_FILE: &str = "rust_text_editor/layout.toml";
+
+/// The layout is shared by every working directory, read on startup and
+/// written on exit
+pub fn layout_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join(LAYOUT_FILE))
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> std::io::Result<Panels> {
+    let content = std::fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+pub fn write<P: AsRef<Path>>(path: P, panels: &Panels) -> std::io::Result<()> {
+    let content = toml::to_string(panels).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    session::write_atomically(path.as_ref(), &content)
+}
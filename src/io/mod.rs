@@ -1,4 +1,13 @@
+use crate::app::commands::Command;
+use crate::app::prompt::PromptAnswer;
+
+pub mod config;
+pub mod disk_state;
 pub mod handler;
+pub mod layout;
+pub mod session;
+pub mod swap;
+
 // For this dummy application we only need two IO event
 #[derive(Debug, Clone)]
 pub enum IoEvent {
@@ -13,5 +22,11 @@ pub enum IoEvent {
     ScrollUp,        // Scroll up
     ScrollLeft,      // Scroll left
     ScrollRight,     // Scroll right
+    JournalSwapFiles, // Write unsaved edits to swap files
+    AnswerPrompt(PromptAnswer), // Resolve the displayed prompt
+    CheckExternalChanges, // Look for open files changed by other programs
+    RunCommand(Command), // Run a command typed in the command line
+    MoveTabLeft,     // Move the current file one tab to the left
+    MoveTabRight,    // Move the current file one tab to the right
 }
  
\ No newline at end of file
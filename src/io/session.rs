@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::disk_state::hash_bytes;
+use crate::app::panels::Panels;
+use crate::app::windows::Splits;
+
+/// Directory of the per working directory sessions, within the user's data directory
+const SESSIONS_DIR: &str = "rust_text_editor/sessions";
+
+/// Open files and how they were looked at, with the windows and panels they
+/// were shown in, saved with `:mksession` to be picked up again later
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// Index of the selected file among `files`
+    pub selected: usize,
+    #[serde(default)]
+    pub files: Vec<SessionFile>,
+    /// Windows the body was split into, as `splits` numbers them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<SessionWindow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub splits: Option<Splits>,
+    /// Panels of the session, over the layout shared by every working directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub panels: Option<Panels>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub path: String,
+    /// Scroll offset of the view as `(x, y)`
+    #[serde(default)]
+    pub scroll_offset: (usize, usize),
+    /// Cursor as `(line, column)`
+    #[serde(default)]
+    pub cursor: (usize, usize),
+    /// Offset of the cursor, for files edited in the hex view
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hex_cursor: Option<usize>,
+}
+
+/// A window and the file it showed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionWindow {
+    pub path: String,
+    #[serde(default)]
+    pub scroll_offset: (usize, usize),
+    #[serde(default)]
+    pub cursor: (usize, usize),
+}
+
+/// Session of the current working directory, kept in the user's data
+/// directory. It is restored on startup and updated on exit for as long as
+/// it exists.
+pub fn directory_session_path() -> Option<PathBuf> {
+    let working_dir = std::env::current_dir().ok()?;
+    let name = format!("{:016x}.toml", hash_bytes(working_dir.to_string_lossy().as_bytes()));
+    Some(dirs::data_dir()?.join(SESSIONS_DIR).join(name))
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> std::io::Result<Session> {
+    let content = std::fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+/// Write `session` to `path`, through a temporary file so that a crash
+/// mid-write never leaves a truncated session behind
+pub fn write<P: AsRef<Path>>(path: P, session: &Session) -> std::io::Result<()> {
+    let content = toml::to_string(session).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    write_atomically(path.as_ref(), &content)
+}
+
+/// Write `content` to `path` through a temporary file, creating the parent
+/// directories as needed
+pub fn write_atomically(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut temp_path = path.to_path_buf().into_os_string();
+    temp_path.push(".tmp");
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Remove the session at `path`, if there is one
+pub fn remove<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::panels::Panel;
+    use crate::app::windows::{SplitDirection, SplitNode};
+
+    fn window_node(window: usize, weight: u32) -> SplitNode {
+        SplitNode {
+            window: Some(window),
+            direction: None,
+            weight,
+            children: vec![],
+        }
+    }
+
+    #[test]
+    fn should_round_trip_sessions() {
+        let name = format!("rust_text_editor_session_test_{}.toml", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let session = Session {
+            selected: 1,
+            files: vec![
+                SessionFile {
+                    path: "/tmp/a.txt".to_owned(),
+                    scroll_offset: (0, 10),
+                    cursor: (12, 4),
+                    hex_cursor: None,
+                },
+                SessionFile {
+                    path: "/tmp/b.bin".to_owned(),
+                    hex_cursor: Some(0x20),
+                    ..SessionFile::default()
+                },
+            ],
+            windows: vec![
+                SessionWindow {
+                    path: "/tmp/a.txt".to_owned(),
+                    scroll_offset: (0, 10),
+                    cursor: (12, 4),
+                },
+                SessionWindow {
+                    path: "/tmp/a.txt".to_owned(),
+                    ..SessionWindow::default()
+                },
+                SessionWindow {
+                    path: "/tmp/b.bin".to_owned(),
+                    ..SessionWindow::default()
+                },
+            ],
+            splits: Some(Splits {
+                focused: 2,
+                root: SplitNode {
+                    window: None,
+                    direction: Some(SplitDirection::Vertical),
+                    weight: 10,
+                    children: vec![
+                        window_node(0, 15),
+                        SplitNode {
+                            window: None,
+                            direction: Some(SplitDirection::Horizontal),
+                            weight: 10,
+                            children: vec![window_node(1, 10), window_node(2, 5)],
+                        },
+                    ],
+                },
+            }),
+            panels: Some(Panels {
+                logs: Panel { visible: false, size: 8 },
+                ..Panels::default()
+            }),
+        };
+        write(&path, &session).unwrap();
+        assert_eq!(read(&path).unwrap(), session);
+        remove(&path).unwrap();
+        assert!(read(&path).is_err());
+    }
+}
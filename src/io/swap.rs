@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+/// Work needed to bring a buffer's swap file in line with its content
+#[derive(Debug, Clone)]
+pub enum SwapJob {
+    /// Journal the unsaved content of the file
+    Write { file_path: String, content: String },
+    /// The buffer went back to its saved content, the journal is stale
+    Remove { file_path: String },
+}
+
+/// Location of the swap file journaling unsaved edits of `file_path`:
+/// a hidden `.<name>.rte.swp` file next to it.
+pub fn swap_path(file_path: &str) -> PathBuf {
+    let path = Path::new(file_path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file_path.to_owned());
+    let swap_name = format!(".{}.rte.swp", name);
+    match path.parent() {
+        Some(parent) => parent.join(swap_name),
+        None => PathBuf::from(swap_name),
+    }
+}
+
+/// Content journaled by an earlier session, if a swap file exists
+pub fn read(file_path: &str) -> Option<String> {
+    std::fs::read_to_string(swap_path(file_path)).ok()
+}
+
+/// Journal `content` to the swap file of `file_path`. The content is written
+/// next to the swap file first, so a crash mid-write never leaves a truncated
+/// journal behind.
+pub fn write(file_path: &str, content: &str) -> std::io::Result<()> {
+    let swap_path = swap_path(file_path);
+    let mut temp_path = swap_path.clone().into_os_string();
+    temp_path.push(".tmp");
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, &swap_path)
+}
+
+/// Remove the swap file of `file_path`, if there is one
+pub fn remove(file_path: &str) -> std::io::Result<()> {
+    match std::fs::remove_file(swap_path(file_path)) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Move the swap file of `file_path` aside, to the first free
+/// `<swap file>.<n>`, so that journaling the buffer does not overwrite it.
+/// Returns where it was moved to.
+pub fn move_aside(file_path: &str) -> std::io::Result<PathBuf> {
+    let swap_path = swap_path(file_path);
+    let mut number = 1;
+    loop {
+        let mut kept_path = swap_path.clone().into_os_string();
+        kept_path.push(format!(".{}", number));
+        let kept_path = PathBuf::from(kept_path);
+        if !kept_path.exists() {
+            std::fs::rename(&swap_path, &kept_path)?;
+            return Ok(kept_path);
+        }
+        number += 1;
+    }
+}
+
+impl SwapJob {
+    pub fn run(&self) -> std::io::Result<()> {
+        match self {
+            SwapJob::Write { file_path, content } => write(file_path, content),
+            SwapJob::Remove { file_path } => remove(file_path),
+        }
+    }
+
+    pub fn file_path(&self) -> &str {
+        match self {
+            SwapJob::Write { file_path, .. } | SwapJob::Remove { file_path } => file_path,
+        }
+    }
+}
@@ -4,6 +4,7 @@ use std::time::Duration;
 
 use app::{App, AppReturn};
 use eyre::Result;
+use log::debug;
 use inputs::events::Events;
 use inputs::InputEvent;
 use io::IoEvent;
@@ -45,11 +46,19 @@ pub async fn start_ui(app: &Arc<tokio::sync::Mutex<App>>) -> Result<()> {
         // Handle inputs
         let result = match events.next().await {
             InputEvent::Input(key) => app.do_action(key).await,
-            InputEvent::Tick => AppReturn::Continue,
+            InputEvent::Resize(width, height) => {
+                debug!("Terminal resized to {}x{}", width, height);
+                terminal.autoresize()?;
+                AppReturn::Continue
+            },
+            InputEvent::Tick => app.on_tick().await,
         };
         // Check if we should exit
-        if result == AppReturn::Exit {
+        if result == AppReturn::Exit || app.is_exit_requested() {
             events.close();
+            app.update_directory_session();
+            app.save_layout();
+            app.remove_swap_files();
             break;
         }
     }